@@ -0,0 +1,493 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crossbeam::channel::RecvTimeoutError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message as WsMessage, WebSocket};
+use uuid::Uuid;
+
+use common::types::{ChatCommand, Message, WebCommand};
+use wg_internal::network::NodeId;
+
+use crate::{metrics, shortcut, utils, SimulationController};
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Largest request body accepted, commands are a few fields of JSON.
+const MAX_BODY: usize = 64 * 1024;
+
+/// Networks and the files shipped with them, `/web/add` also reads from the output directory.
+const CONFIG_DIR: &str = "./config";
+
+/// How long a client has to send its request before the connection is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connections served at once, the ones past it are answered 503 right away.
+const MAX_CONNECTIONS: usize = 32;
+
+/// How long `/events` waits for a log line before checking whether the client closed the socket.
+const EVENTS_POLL: Duration = Duration::from_millis(500);
+
+/// Called after a command changed the network, so the UI can redraw from its own thread.
+pub type OnChange = Arc<dyn Fn() + Send + Sync>;
+
+/// Control API on `127.0.0.1:<port>`, for scripts and grading tools.
+///
+/// | Method | Path | Body |
+/// |---|---|---|
+/// | GET | `/topology` | |
+/// | GET | `/stats` | |
+/// | GET | `/metrics` | OpenMetrics text, for Prometheus |
+/// | GET | `/events` | WebSocket upgrade, streams every log line as `{"message", "color"}` |
+/// | POST | `/crash` | `{"node"}`, clients and servers crash without telling their neighbours |
+/// | POST | `/remove` | `{"node"}`, a crashed client or server |
+/// | POST | `/shutdown` | `{"node"}` |
+/// | POST | `/pdr` | `{"node", "pdr"}` with `pdr` in `[0, 1]` |
+/// | POST | `/add-sender`, `/remove-sender` | `{"node", "sender"}` |
+/// | POST | `/chat/send` | `{"node", "to", "text"}` |
+/// | POST | `/chat/register` | `{"node", "server"}` |
+/// | POST | `/chat/history`, `/chat/clients` | `{"node"}` |
+/// | POST | `/web/list` | `{"node"}`, files list of a browser or files of a server |
+/// | POST | `/web/get` | `{"node", "uuid"}` |
+/// | POST | `/web/add` | `{"node", "path", "media"}`, a file in the output or config directory |
+/// | POST | `/web/remove` | `{"node", "uuid", "media"}` |
+/// | GET, POST | `/shortcut-policy` | `{"policy"}`, `deliver`, `drop` or `delay:<ms>` |
+///
+/// Commands answer `{"ok": true}` once sent, or `{"error": "..."}` with status 400 or 404.
+/// Their outcome comes later as events, on `/events` or in the request list of the UI.
+///
+/// Requests must name `127.0.0.1:<port>` or `localhost:<port>` as `Host` and carry no `Origin`,
+/// so web pages open in a browser, rebinding a domain or not, get 403.
+pub struct ApiServer {
+    addr: SocketAddr,
+}
+
+impl ApiServer {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+/// Binds the API, port 0 picks a free one, and serves it from a background thread.
+pub fn serve(port: u16, sc: Arc<Mutex<SimulationController>>, on_change: OnChange) -> io::Result<ApiServer> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let addr = listener.local_addr()?;
+
+    std::thread::spawn(move || {
+        let open = Arc::new(AtomicUsize::new(0));
+        for mut stream in listener.incoming().filter_map(|s| s.ok()) {
+            if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                open.fetch_sub(1, Ordering::SeqCst);
+                let _ = respond(&mut stream, 503, &json!({ "error": "too many connections" }));
+                continue;
+            }
+            let sc = sc.clone();
+            let on_change = on_change.clone();
+            let open = open.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle_connection(stream, &sc, &on_change) {
+                    eprintln!("api: {e}");
+                }
+                open.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+
+    Ok(ApiServer { addr })
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: BTreeMap<String, String>,
+    body: Vec<u8>,
+}
+
+fn read_request(stream: &mut TcpStream) -> io::Result<Request> {
+    // byte by byte so nothing past the head is consumed before a WebSocket takes over the stream
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 || head.len() > MAX_BODY {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "incomplete request head"));
+        }
+        head.push(byte[0]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect::<BTreeMap<_, _>>();
+
+    let length = headers.get("content-length").and_then(|l| l.parse::<usize>().ok()).unwrap_or(0);
+    if length > MAX_BODY {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request body too large"));
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body)?;
+
+    Ok(Request { method, path, headers, body })
+}
+
+fn respond(stream: &mut TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    respond_with(stream, status, "application/json", &body.to_string())
+}
+
+fn respond_with(stream: &mut TcpStream, status: u16, content_type: &str, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn handle_connection(mut stream: TcpStream, sc: &Mutex<SimulationController>, on_change: &OnChange) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let request = read_request(&mut stream)?;
+
+    if let Err(e) = check_caller(&request, stream.local_addr()?.port()) {
+        return respond(&mut stream, 403, &json!({ "error": e }));
+    }
+
+    if request.method == "GET" && request.path == "/events" {
+        return stream_events(stream, &request);
+    }
+    if request.method == "GET" && request.path == "/metrics" {
+        let text = metrics::render(&sc.lock().unwrap());
+        return respond_with(&mut stream, 200, metrics::CONTENT_TYPE, &text);
+    }
+
+    let (status, body) = match route(&request, sc) {
+        Ok((changed, body)) => {
+            if changed {
+                on_change();
+            }
+            (200, body)
+        }
+        Err(ApiError::NotFound) => (404, json!({ "error": format!("no route for {} {}", request.method, request.path) })),
+        Err(ApiError::BadRequest(e)) => (400, json!({ "error": e })),
+    };
+    respond(&mut stream, status, &body)
+}
+
+/// Refuses requests a browser makes for a web page, see `ApiServer`.
+fn check_caller(request: &Request, port: u16) -> Result<(), String> {
+    if let Some(origin) = request.headers.get("origin") {
+        return Err(format!("requests from {origin} are not accepted"));
+    }
+    let host = request.headers.get("host").map(String::as_str).unwrap_or_default();
+    if host != format!("127.0.0.1:{port}") && host != format!("localhost:{port}") {
+        return Err(format!("unexpected host {host:?}"));
+    }
+    Ok(())
+}
+
+enum ApiError {
+    NotFound,
+    BadRequest(String),
+}
+
+impl From<String> for ApiError {
+    fn from(e: String) -> Self {
+        ApiError::BadRequest(e)
+    }
+}
+
+#[derive(Deserialize)]
+struct NodeArgs {
+    node: NodeId,
+}
+
+#[derive(Deserialize)]
+struct PdrArgs {
+    node: NodeId,
+    pdr: f32,
+}
+
+#[derive(Deserialize)]
+struct SenderArgs {
+    node: NodeId,
+    sender: NodeId,
+}
+
+#[derive(Deserialize)]
+struct SendArgs {
+    node: NodeId,
+    to: NodeId,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct RegisterArgs {
+    node: NodeId,
+    server: NodeId,
+}
+
+#[derive(Deserialize)]
+struct FileArgs {
+    node: NodeId,
+    uuid: Uuid,
+    #[serde(default)]
+    media: bool,
+}
+
+#[derive(Deserialize)]
+struct PolicyArgs {
+    policy: String,
+}
+
+#[derive(Deserialize)]
+struct AddArgs {
+    node: NodeId,
+    path: String,
+    #[serde(default)]
+    media: bool,
+}
+
+fn args<'a, T: Deserialize<'a>>(request: &'a Request) -> Result<T, ApiError> {
+    serde_json::from_slice(&request.body).map_err(|e| ApiError::BadRequest(format!("invalid body: {e}")))
+}
+
+/// Runs a request, returns whether the network changed and the JSON answer.
+fn route(request: &Request, sc: &Mutex<SimulationController>) -> Result<(bool, Value), ApiError> {
+    let ok = json!({ "ok": true });
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/topology") => Ok((false, topology(&sc.lock().unwrap()))),
+        ("GET", "/stats") => {
+            let sc = sc.lock().unwrap();
+            let stats = sc.stats.lock().unwrap().clone();
+            Ok((false, serde_json::to_value(stats).unwrap_or_default()))
+        }
+
+        ("GET", "/shortcut-policy") => {
+            let policy = sc.lock().unwrap().shortcuts.lock().unwrap().policy;
+            Ok((false, json!({ "policy": policy.to_string() })))
+        }
+        ("POST", "/shortcut-policy") => {
+            let PolicyArgs { policy } = args(request)?;
+            let policy = shortcut::Policy::parse(&policy)?;
+            sc.lock().unwrap().shortcuts.lock().unwrap().policy = policy;
+            utils::log(&format!("CONTROLLER SHORTCUT POLICY: {policy}"), slint::Color::from_rgb_u8(123, 132, 150));
+            Ok((false, ok))
+        }
+
+        ("POST", "/crash") => {
+            let NodeArgs { node } = args(request)?;
+            sc.lock().unwrap().crash(node)?;
+            Ok((true, ok))
+        }
+        ("POST", "/remove") => {
+            let NodeArgs { node } = args(request)?;
+            sc.lock().unwrap().remove_dead(node)?;
+            Ok((true, ok))
+        }
+        ("POST", "/shutdown") => {
+            let NodeArgs { node } = args(request)?;
+            sc.lock().unwrap().shutdown(node)?;
+            Ok((true, ok))
+        }
+        ("POST", "/pdr") => {
+            let PdrArgs { node, pdr } = args(request)?;
+            sc.lock().unwrap().set_packet_drop_rate(node, pdr)?;
+            Ok((true, ok))
+        }
+        ("POST", "/add-sender") => {
+            let SenderArgs { node, sender } = args(request)?;
+            sc.lock().unwrap().add_sender(node, sender)?;
+            Ok((true, ok))
+        }
+        ("POST", "/remove-sender") => {
+            let SenderArgs { node, sender } = args(request)?;
+            sc.lock().unwrap().remove_sender(node, sender)?;
+            Ok((true, ok))
+        }
+
+        ("POST", "/chat/send") => {
+            let SendArgs { node, to, text } = args(request)?;
+            let sc = sc.lock().unwrap();
+            let message = Message { from: node, to, text };
+            if sc.clients.contains_key(&node) {
+                sc.chats.lock().unwrap().on_command(&message);
+            }
+            send_chat(&sc, node, ChatCommand::SendMessage(message))?;
+            Ok((false, ok))
+        }
+        ("POST", "/chat/register") => {
+            let RegisterArgs { node, server } = args(request)?;
+            send_chat(&sc.lock().unwrap(), node, ChatCommand::RegisterToServer(server))?;
+            Ok((false, ok))
+        }
+        ("POST", "/chat/history") => {
+            let NodeArgs { node } = args(request)?;
+            send_chat(&sc.lock().unwrap(), node, ChatCommand::GetChatsHistory)?;
+            Ok((false, ok))
+        }
+        ("POST", "/chat/clients") => {
+            let NodeArgs { node } = args(request)?;
+            send_chat(&sc.lock().unwrap(), node, ChatCommand::GetRegisteredClients)?;
+            Ok((false, ok))
+        }
+
+        ("POST", "/web/list") => {
+            let NodeArgs { node } = args(request)?;
+            let sc = sc.lock().unwrap();
+            if sc.servers.contains_key(&node) {
+                sc.request_server_files(node);
+            } else {
+                send_web(&sc, node, WebCommand::QueryTextFilesList)?;
+            }
+            Ok((false, ok))
+        }
+        ("POST", "/web/get") => {
+            let FileArgs { node, uuid, .. } = args(request)?;
+            send_web(&sc.lock().unwrap(), node, WebCommand::GetFile(uuid))?;
+            Ok((false, ok))
+        }
+        ("POST", "/web/add") => {
+            let AddArgs { node, path, media } = args(request)?;
+            let sc = sc.lock().unwrap();
+            let path = readable_path(&sc, &path)?.display().to_string();
+            let bytes = match sc.servers.get(&node) {
+                Some(_) => Some(std::fs::read(&path).map_err(|e| format!("cannot read {path}: {e}"))?),
+                None => None,
+            };
+            let command = if media { WebCommand::AddMediaFileFromPath(path) } else { WebCommand::AddTextFileFromPath(path) };
+            match bytes {
+                Some(bytes) => {
+                    let (_, sender) = &sc.servers[&node];
+                    sender.send(sc.tracked_add(node, command, &bytes)).map_err(|_| format!("Node {node} is not listening"))?;
+                }
+                None => send_web(&sc, node, command)?,
+            }
+            Ok((false, ok))
+        }
+        ("POST", "/web/remove") => {
+            let FileArgs { node, uuid, media } = args(request)?;
+            let command = if media { WebCommand::RemoveMediaFile(uuid) } else { WebCommand::RemoveTextFile(uuid) };
+            send_web(&sc.lock().unwrap(), node, command)?;
+            Ok((false, ok))
+        }
+
+        _ => Err(ApiError::NotFound),
+    }
+}
+
+/// `path` made absolute, if it is in the output or the config directory.
+/// Anything else is refused, a caller of the API must not get other files of this machine read.
+fn readable_path(sc: &SimulationController, path: &str) -> Result<PathBuf, String> {
+    let file = Path::new(path).canonicalize().map_err(|e| format!("cannot read {path}: {e}"))?;
+    let roots = [sc.output.lock().unwrap().base().to_path_buf(), PathBuf::from(CONFIG_DIR)];
+    if roots.iter().filter_map(|root| root.canonicalize().ok()).any(|root| file.starts_with(root)) {
+        Ok(file)
+    } else {
+        Err(format!("{path} is not in the output or config directory"))
+    }
+}
+
+fn send_web(sc: &SimulationController, node: NodeId, command: WebCommand) -> Result<(), String> {
+    let (_, sender) = sc.clients.get(&node).or_else(|| sc.servers.get(&node)).ok_or_else(|| format!("Node {node} is not a running client or server"))?;
+    sender.send(sc.tracked_web(node, command)).map_err(|_| format!("Node {node} is not listening"))
+}
+
+fn send_chat(sc: &SimulationController, node: NodeId, command: ChatCommand) -> Result<(), String> {
+    let (_, sender) = sc.clients.get(&node).or_else(|| sc.servers.get(&node)).ok_or_else(|| format!("Node {node} is not a running client or server"))?;
+    sender.send(sc.tracked_chat(node, command)).map_err(|_| format!("Node {node} is not listening"))
+}
+
+#[derive(Serialize)]
+struct NodeView {
+    id: NodeId,
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pdr: Option<f32>,
+    neighbors: Vec<NodeId>,
+}
+
+/// Running nodes with their links, drones first, each group sorted by id, then the crashed clients and servers.
+pub fn topology(sc: &SimulationController) -> Value {
+    let graph = sc.generic_graph();
+    let neighbors = |id: NodeId, kind: &str| {
+        let mut neighbors = graph.get(&(id, kind.to_string())).map(|a| a.iter().copied().collect::<Vec<_>>()).unwrap_or_default();
+        neighbors.sort();
+        neighbors
+    };
+
+    let drones = sc.get_drones_pdr().into_iter()
+        .map(|(id, pdr)| NodeView { id, kind: "drone".to_string(), pdr: Some(pdr), neighbors: neighbors(id, "drone") })
+        .collect::<Vec<_>>();
+    let (clients, servers) = sc.get_nodes_with_type();
+    let clients = clients.into_iter()
+        .map(|(id, kind)| NodeView { id, kind, pdr: None, neighbors: neighbors(id, "client") })
+        .collect::<Vec<_>>();
+    let servers = servers.into_iter()
+        .map(|(id, kind)| NodeView { id, kind, pdr: None, neighbors: neighbors(id, "server") })
+        .collect::<Vec<_>>();
+
+    let crashed = sc.dead.keys().copied().collect::<Vec<_>>();
+
+    json!({ "drones": drones, "clients": clients, "servers": servers, "crashed": crashed })
+}
+
+fn stream_events(mut stream: TcpStream, request: &Request) -> io::Result<()> {
+    let Some(key) = request.headers.get("sec-websocket-key") else {
+        return respond(&mut stream, 400, &json!({ "error": "/events expects a WebSocket upgrade" }));
+    };
+
+    // subscribed before the handshake completes, so the client gets every line logged after it connected
+    let logs = utils::subscribe_logs();
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        derive_accept_key(key.as_bytes())
+    )?;
+
+    // reads only look for a close, they must not hold up the next line
+    stream.set_read_timeout(Some(Duration::from_millis(10)))?;
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    // leaving drops `logs`, which unsubscribes
+    loop {
+        match logs.recv_timeout(EVENTS_POLL) {
+            Ok(entry) => {
+                let color = entry.color;
+                let line = json!({
+                    "message": entry.message.as_str(),
+                    "color": format!("#{:02x}{:02x}{:02x}", color.red(), color.green(), color.blue()),
+                });
+                if socket.send(WsMessage::Text(line.to_string())).is_err() {
+                    return Ok(()); // the client went away
+                }
+            }
+            // nothing logged for a while, a client that left is noticed here instead of at the next line
+            Err(RecvTimeoutError::Timeout) => match socket.read() {
+                Ok(WsMessage::Close(_)) => return Ok(()),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+                Err(_) => return Ok(()),
+            },
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use slint::{SharedString, VecModel};
+use uuid::Uuid;
+
+use common::types::{MediaFile, TextFile};
+use wg_internal::network::NodeId;
+
+use crate::file_browser::{self, FileCatalog};
+use crate::{BrowserBlock, FileEntry, MainWindow};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaStatus {
+    Pending,
+    Loaded { title: String, bytes: Vec<u8> },
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageMedia {
+    pub id: Uuid,
+    pub location: NodeId,
+    pub status: MediaStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageStatus {
+    /// `GetTextFile` was sent, the browser did not answer yet.
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub text_file: Uuid,
+    pub title: String,
+    pub content: String,
+    pub status: PageStatus,
+    pub media: Vec<PageMedia>,
+}
+
+impl Page {
+    pub fn is_complete(&self) -> bool {
+        self.status == PageStatus::Loaded && self.media.iter().all(|m| m.status != MediaStatus::Pending)
+    }
+}
+
+/// Page currently open in each web browser.
+///
+/// A page is built in two steps: the text file comes back from `GetTextFile`, then every
+/// `MediaReference` in it is fetched with `GetMediaFile` from its `location` server.
+#[derive(Debug, Default)]
+pub struct BrowserStore {
+    pages: HashMap<NodeId, Page>,
+}
+
+impl BrowserStore {
+
+    pub fn open(&mut self, client: NodeId, text_file: Uuid) {
+        self.pages.insert(client, Page {
+            text_file,
+            title: String::new(),
+            content: String::new(),
+            status: PageStatus::Loading,
+            media: Vec::new(),
+        });
+    }
+
+    /// Fills the page waiting for `file` and returns the `(media_id, location)` pairs to request.
+    pub fn on_text_file(&mut self, client: NodeId, file: &TextFile) -> Vec<(Uuid, NodeId)> {
+        let Some(page) = self.pages.get_mut(&client).filter(|p| p.text_file == file.id && p.status == PageStatus::Loading) else {
+            return Vec::new();
+        };
+
+        page.title = file.title.clone();
+        page.content = file.content.clone();
+        page.status = PageStatus::Loaded;
+        page.media = file.media_refs.iter().map(|r| PageMedia {
+            id: r.id,
+            location: r.location,
+            status: MediaStatus::Pending,
+        }).collect();
+
+        page.media.iter().map(|m| (m.id, m.location)).collect()
+    }
+
+    /// Returns whether the media belonged to the open page.
+    pub fn on_media_file(&mut self, client: NodeId, file: &MediaFile) -> bool {
+        let Some(page) = self.pages.get_mut(&client) else {
+            return false;
+        };
+
+        let mut found = false;
+        for media in page.media.iter_mut().filter(|m| m.id == file.id) {
+            media.status = MediaStatus::Loaded { title: file.title.clone(), bytes: file.content.concat() };
+            found = true;
+        }
+        found
+    }
+
+    /// Marks the text file or the media with `uuid` as failed, returns whether it belonged to the open page.
+    pub fn on_failure(&mut self, client: NodeId, uuid: &Uuid, reason: &str) -> bool {
+        let Some(page) = self.pages.get_mut(&client) else {
+            return false;
+        };
+
+        if page.text_file == *uuid && page.status == PageStatus::Loading {
+            page.status = PageStatus::Failed(reason.to_string());
+            return true;
+        }
+
+        let mut found = false;
+        for media in page.media.iter_mut().filter(|m| m.id == *uuid && m.status == MediaStatus::Pending) {
+            media.status = MediaStatus::Failed(reason.to_string());
+            found = true;
+        }
+        found
+    }
+
+    pub fn page(&self, client: NodeId) -> Option<&Page> {
+        self.pages.get(&client)
+    }
+}
+
+fn block(kind: &str, text: String) -> BrowserBlock {
+    BrowserBlock { kind: kind.into(), text: text.into(), image: Default::default() }
+}
+
+/// Redraws the browser view of the web client currently open in it.
+pub fn refresh(main_window: &MainWindow, store: &BrowserStore, catalog: &FileCatalog) {
+    let links = catalog.text_files().into_iter().map(|(server, e)| FileEntry {
+        id: e.id.to_string().into(),
+        title: if e.title.is_empty() { e.id.to_string().into() } else { e.title.clone().into() },
+        kind: format!("server {server}").into(),
+        loaded: true,
+    }).collect::<Vec<_>>();
+    main_window.set_browser_links(Rc::new(VecModel::from(links)).into());
+
+    let page = main_window.get_browser_client().parse::<NodeId>().ok().and_then(|client| store.page(client));
+    let Some(page) = page else {
+        main_window.set_browser_title(SharedString::new());
+        main_window.set_browser_blocks(Rc::new(VecModel::from(Vec::<BrowserBlock>::new())).into());
+        return;
+    };
+
+    let mut blocks = Vec::new();
+    match &page.status {
+        PageStatus::Loading => blocks.push(block("pending", format!("Loading {}...", page.text_file))),
+        PageStatus::Failed(reason) => blocks.push(block("error", format!("Cannot load {}: {reason}", page.text_file))),
+        PageStatus::Loaded => blocks.push(block("text", page.content.clone())),
+    }
+
+    for media in &page.media {
+        blocks.push(match &media.status {
+            MediaStatus::Pending => block("pending", format!("Loading media {} from server {}...", media.id, media.location)),
+            MediaStatus::Failed(reason) => block("error", format!("Media {} from server {}: {reason}", media.id, media.location)),
+            MediaStatus::Loaded { title, bytes } => match file_browser::load_image(&media.id, title, bytes) {
+                Some(image) => BrowserBlock { kind: "image".into(), text: title.clone().into(), image },
+                None => block("error", format!("Media {title} ({} bytes) is not an image", bytes.len())),
+            },
+        });
+    }
+
+    let title = if page.title.is_empty() { page.text_file.to_string() } else { page.title.clone() };
+    let loaded = page.media.iter().filter(|m| matches!(m.status, MediaStatus::Loaded { .. })).count();
+    main_window.set_browser_title(format!("{title} · {loaded}/{} media", page.media.len()).into());
+    main_window.set_browser_blocks(Rc::new(VecModel::from(blocks)).into());
+}
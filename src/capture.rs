@@ -0,0 +1,294 @@
+use std::any::Any;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use slint::Color;
+
+use common::types::NodeEvent;
+use wg_internal::controller::DroneEvent;
+use wg_internal::network::NodeId;
+use wg_internal::packet::{Packet, PacketType};
+
+use crate::observer::EventObserver;
+use crate::{utils, EventContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    Sent,
+    Dropped,
+    Shortcut,
+}
+
+impl Disposition {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sent" => Some(Disposition::Sent),
+            "dropped" => Some(Disposition::Dropped),
+            "shortcut" => Some(Disposition::Shortcut),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Disposition::Sent => "sent",
+            Disposition::Dropped => "dropped",
+            Disposition::Shortcut => "shortcut",
+        }
+    }
+}
+
+/// What a packet carried, fragment data is kept up to its length, in hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Payload {
+    MsgFragment { fragment_index: u64, total_n_fragments: u64, length: u8, data: String },
+    Ack { fragment_index: u64 },
+    Nack { fragment_index: u64, nack_type: String },
+    FloodRequest { flood_id: u64, initiator_id: NodeId, path_trace: Vec<NodeId> },
+    FloodResponse { flood_id: u64, path_trace: Vec<NodeId> },
+}
+
+impl Payload {
+    pub fn of(packet: &Packet) -> Self {
+        match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => Payload::MsgFragment {
+                fragment_index: fragment.fragment_index,
+                total_n_fragments: fragment.total_n_fragments,
+                length: fragment.length,
+                data: fragment.data[..(fragment.length as usize).min(fragment.data.len())].iter().map(|b| format!("{b:02x}")).collect(),
+            },
+            PacketType::Ack(ack) => Payload::Ack { fragment_index: ack.fragment_index },
+            PacketType::Nack(nack) => Payload::Nack { fragment_index: nack.fragment_index, nack_type: format!("{:?}", nack.nack_type) },
+            PacketType::FloodRequest(request) => Payload::FloodRequest {
+                flood_id: request.flood_id,
+                initiator_id: request.initiator_id,
+                path_trace: request.path_trace.iter().map(|(id, _)| *id).collect(),
+            },
+            PacketType::FloodResponse(response) => Payload::FloodResponse {
+                flood_id: response.flood_id,
+                path_trace: response.path_trace.iter().map(|(id, _)| *id).collect(),
+            },
+        }
+    }
+
+    /// Names accepted by `Filter::packet_type`.
+    pub const TYPES: [&'static str; 5] = ["msg_fragment", "ack", "nack", "flood_request", "flood_response"];
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Payload::MsgFragment { .. } => "msg_fragment",
+            Payload::Ack { .. } => "ack",
+            Payload::Nack { .. } => "nack",
+            Payload::FloodRequest { .. } => "flood_request",
+            Payload::FloodResponse { .. } => "flood_response",
+        }
+    }
+}
+
+/// One line of a capture file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Milliseconds since the Unix epoch.
+    pub time_ms: u64,
+    /// Milliseconds since the capture started.
+    pub elapsed_ms: u64,
+    /// Node that reported the packet, the sender for `sent`, the drone holding it otherwise.
+    pub observer: Option<NodeId>,
+    pub disposition: Disposition,
+    pub session_id: u64,
+    pub hops: Vec<NodeId>,
+    pub hop_index: usize,
+    pub payload: Payload,
+}
+
+impl Record {
+
+    /// Records `packet` now, `start` is when the capture began.
+    pub fn new(packet: &Packet, observer: Option<NodeId>, disposition: Disposition, start: Instant) -> Self {
+        Self {
+            time_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            elapsed_ms: start.elapsed().as_millis() as u64,
+            observer,
+            disposition,
+            session_id: packet.session_id,
+            hops: packet.routing_header.hops.clone(),
+            hop_index: packet.routing_header.hop_index,
+            payload: Payload::of(packet),
+        }
+    }
+
+    pub fn source(&self) -> Option<NodeId> {
+        self.hops.first().copied()
+    }
+
+    pub fn destination(&self) -> Option<NodeId> {
+        self.hops.last().copied()
+    }
+
+    pub fn summary(&self) -> String {
+        let route = self.hops.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" > ");
+        let observer = self.observer.map(|id| id.to_string()).unwrap_or_else(|| "?".to_string());
+        let detail = match &self.payload {
+            Payload::MsgFragment { fragment_index, total_n_fragments, length, .. } => format!("fragment {fragment_index}/{total_n_fragments}, {length} bytes"),
+            Payload::Ack { fragment_index } => format!("fragment {fragment_index}"),
+            Payload::Nack { fragment_index, nack_type } => format!("fragment {fragment_index}, {nack_type}"),
+            Payload::FloodRequest { flood_id, initiator_id, .. } => format!("flood {flood_id} from {initiator_id}"),
+            Payload::FloodResponse { flood_id, .. } => format!("flood {flood_id}"),
+        };
+        format!(
+            "{:>10.3}s  node {observer:<3} {:<8} {:<14} session {:<6} [{route}] hop {}  {detail}",
+            self.elapsed_ms as f64 / 1000.,
+            self.disposition.name(),
+            self.payload.type_name(),
+            self.session_id,
+            self.hop_index
+        )
+    }
+}
+
+/// The packet of a drone event and what happened to it.
+pub fn packet_of(event: &DroneEvent) -> (&Packet, Disposition) {
+    match event {
+        DroneEvent::PacketSent(packet) => (packet, Disposition::Sent),
+        DroneEvent::PacketDropped(packet) => (packet, Disposition::Dropped),
+        DroneEvent::ControllerShortcut(packet) => (packet, Disposition::Shortcut),
+    }
+}
+
+/// The node that just sent `packet`, it was already moved to its next hop.
+pub fn sender(packet: &Packet) -> Option<NodeId> {
+    packet.routing_header.previous_hop().or_else(|| packet.routing_header.hops.first().copied())
+}
+
+/// The node a drone event is about: the drone that sent, dropped or handed over the packet.
+pub fn reporting_drone(event: &DroneEvent) -> Option<NodeId> {
+    match event {
+        DroneEvent::PacketSent(packet) => sender(packet),
+        DroneEvent::PacketDropped(packet) | DroneEvent::ControllerShortcut(packet) => packet.routing_header.hops.get(packet.routing_header.hop_index).copied(),
+    }
+}
+
+/// Writes every packet seen by the controller to a JSONL file, one `Record` per line.
+///
+/// Records are flushed at most `FLUSH_INTERVAL` after being written and when the simulation stops.
+/// The first error is logged and stops the capture, the file keeps what was written before it.
+pub struct CaptureWriter {
+    out: BufWriter<File>,
+    start: Instant,
+    written: u64,
+    flushed: Instant,
+    failed: bool,
+}
+
+impl CaptureWriter {
+    pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self { out: BufWriter::new(File::create(path)?), start: Instant::now(), written: 0, flushed: Instant::now(), failed: false })
+    }
+
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn write(&mut self, packet: &Packet, observer: Option<NodeId>, disposition: Disposition) -> io::Result<()> {
+        let record = Record::new(packet, observer, disposition, self.start);
+        serde_json::to_writer(&mut self.out, &record)?;
+        self.out.write_all(b"\n")?;
+        self.written += 1;
+        if self.flushed.elapsed() >= Self::FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.flushed = Instant::now();
+        self.out.flush()
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Writes through `op` unless an earlier write failed, the first failure is logged once.
+    fn guarded(&mut self, op: impl FnOnce(&mut Self) -> io::Result<()>) {
+        if self.failed {
+            return;
+        }
+        if let Err(e) = op(self) {
+            self.failed = true;
+            utils::log(format!("Capture stopped after {} packets: {e}", self.written), Color::from_rgb_u8(255, 94, 160));
+        }
+    }
+}
+
+impl EventObserver for CaptureWriter {
+    fn on_node_event(&mut self, event: &dyn Any, ctx: &EventContext) -> bool {
+        if let Some(NodeEvent::PacketSent(packet)) = event.downcast_ref::<NodeEvent>() {
+            self.guarded(|w| w.write(packet, sender(packet), Disposition::Sent));
+        }
+        false
+    }
+
+    fn on_drone_event(&mut self, event: &DroneEvent, ctx: &EventContext) {
+        let (packet, disposition) = packet_of(event);
+        self.guarded(|w| w.write(packet, reporting_drone(event), disposition));
+    }
+
+    fn on_stop(&mut self, ctx: &EventContext) {
+        self.guarded(Self::flush);
+    }
+}
+
+impl Drop for CaptureWriter {
+    fn drop(&mut self) {
+        self.guarded(Self::flush);
+    }
+}
+
+/// Which records to keep when reading a capture, `None` fields match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub session: Option<u64>,
+    /// `msg_fragment`, `ack`, `nack`, `flood_request` or `flood_response`.
+    pub packet_type: Option<String>,
+    pub source: Option<NodeId>,
+    pub destination: Option<NodeId>,
+    /// A node anywhere on the route.
+    pub hop: Option<NodeId>,
+    pub disposition: Option<Disposition>,
+}
+
+impl Filter {
+    pub fn matches(&self, record: &Record) -> bool {
+        self.session.map_or(true, |s| record.session_id == s)
+            && self.packet_type.as_deref().map_or(true, |t| record.payload.type_name() == t)
+            && self.source.map_or(true, |s| record.source() == Some(s))
+            && self.destination.map_or(true, |d| record.destination() == Some(d))
+            && self.hop.map_or(true, |h| record.hops.contains(&h))
+            && self.disposition.map_or(true, |d| record.disposition == d)
+    }
+}
+
+/// Reads the records of `path` kept by `filter`, in file order. Malformed lines are errors, with their line number.
+pub fn read(path: impl AsRef<Path>, filter: &Filter) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Record = serde_json::from_str(&line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {e}", n + 1)))?;
+        if filter.matches(&record) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
@@ -0,0 +1,147 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+use chrono::Local;
+use slint::{Color, SharedString, VecModel};
+
+use common::types::Message;
+use wg_internal::network::NodeId;
+
+use crate::{ChatLine, ChatPeer, MainWindow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// `SendMessage` was issued by the controller, the client did not confirm it yet.
+    Requested,
+    /// The sending client reported `MessageSent`, the receiver did not report it yet.
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatEntry {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub text: String,
+    pub time: String,
+    pub delivery: Delivery,
+}
+
+/// Conversations between clients, built live from `ChatCommand::SendMessage`,
+/// `ChatEvent::MessageSent` and `ChatEvent::MessageReceived`.
+///
+/// Threads are keyed by the ordered pair of clients, so both directions end up in the same thread.
+#[derive(Debug, Default)]
+pub struct ChatStore {
+    threads: BTreeMap<(NodeId, NodeId), Vec<ChatEntry>>,
+}
+
+fn key(a: NodeId, b: NodeId) -> (NodeId, NodeId) {
+    (a.min(b), a.max(b))
+}
+
+fn now() -> String {
+    Local::now().format("%H:%M:%S").to_string()
+}
+
+impl ChatStore {
+
+    /// Records a message the controller asked `message.from` to send.
+    pub fn on_command(&mut self, message: &Message) {
+        self.threads.entry(key(message.from, message.to)).or_default().push(ChatEntry {
+            from: message.from,
+            to: message.to,
+            text: message.text.clone(),
+            time: now(),
+            delivery: Delivery::Requested,
+        });
+    }
+
+    /// `MessageSent` only carries the receiver, so it confirms the oldest requested message on that direction.
+    pub fn on_sent(&mut self, from: NodeId, to: NodeId) {
+        let thread = self.threads.entry(key(from, to)).or_default();
+
+        match thread.iter_mut().find(|e| e.from == from && e.to == to && e.delivery == Delivery::Requested) {
+            Some(entry) => entry.delivery = Delivery::Sent,
+            None => thread.push(ChatEntry { from, to, text: String::new(), time: now(), delivery: Delivery::Sent }),
+        }
+    }
+
+    pub fn on_received(&mut self, message: &Message) {
+        let thread = self.threads.entry(key(message.from, message.to)).or_default();
+
+        let pending = thread.iter_mut().find(|e| {
+            e.from == message.from && e.to == message.to && e.delivery != Delivery::Received
+                && (e.text == message.text || e.text.is_empty())
+        });
+
+        match pending {
+            Some(entry) => {
+                entry.text = message.text.clone();
+                entry.delivery = Delivery::Received;
+            }
+            None => thread.push(ChatEntry {
+                from: message.from,
+                to: message.to,
+                text: message.text.clone(),
+                time: now(),
+                delivery: Delivery::Received,
+            }),
+        }
+    }
+
+    /// Clients that exchanged at least one message with `client`.
+    pub fn peers_of(&self, client: NodeId) -> Vec<NodeId> {
+        self.threads.keys()
+            .filter_map(|(a, b)| if *a == client { Some(*b) } else if *b == client { Some(*a) } else { None })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    pub fn thread(&self, a: NodeId, b: NodeId) -> &[ChatEntry] {
+        self.threads.get(&key(a, b)).map(|t| t.as_slice()).unwrap_or(&[])
+    }
+
+    /// Messages that left their sender but never reached the receiver.
+    pub fn undelivered(&self) -> usize {
+        self.threads.values().flatten().filter(|e| e.delivery != Delivery::Received).count()
+    }
+}
+
+/// Redraws the chat panel for the client and peer currently selected in it.
+pub fn refresh(main_window: &MainWindow, store: &ChatStore) {
+    let Ok(client) = main_window.get_chat_client().parse::<NodeId>() else {
+        return;
+    };
+
+    let peers = store.peers_of(client).into_iter().map(|peer| {
+        let thread = store.thread(client, peer);
+        ChatPeer {
+            id: peer.to_string().into(),
+            title: format!("Client {peer}").into(),
+            subtitle: thread.last().map(|e| e.text.clone()).unwrap_or_default().into(),
+        }
+    }).collect::<Vec<_>>();
+    main_window.set_chat_peers(Rc::new(VecModel::from(peers)).into());
+
+    let Ok(peer) = main_window.get_chat_peer().parse::<NodeId>() else {
+        main_window.set_chat_messages(Rc::new(VecModel::from(Vec::<ChatLine>::new())).into());
+        return;
+    };
+
+    let lines = store.thread(client, peer).iter().map(|e| {
+        let (status, color) = match e.delivery {
+            Delivery::Requested => ("requested", Color::from_rgb_u8(123, 132, 150)),
+            Delivery::Sent => ("sent, not received", Color::from_rgb_u8(255, 189, 94)),
+            Delivery::Received => ("received", Color::from_rgb_u8(94, 255, 108)),
+        };
+        ChatLine {
+            text: SharedString::from(if e.text.is_empty() { "(message sent outside the controller)".to_string() } else { e.text.clone() }),
+            mine: e.from == client,
+            status: format!("{} · {status}", e.time).into(),
+            status_color: color,
+        }
+    }).collect::<Vec<_>>();
+    main_window.set_chat_messages(Rc::new(VecModel::from(lines)).into());
+}
@@ -0,0 +1,351 @@
+use crate::capture::{self, Disposition, Filter, Payload};
+use crate::export::{ExportGraph, Format};
+use crate::generator::{self, GeneratorParams, PdrDistribution, Shape};
+use crate::shortcut::Policy;
+use crate::topology::TopologyConfig;
+use crate::validation::{self, Severity};
+
+const USAGE: &str = "\
+usage:
+  simulation_controller [--output <dir>]     start the UI, saving files and chat histories under <dir>/<timestamp>/ (default: ./runs)
+                       [--api [port]]       also serve the control API on 127.0.0.1:<port> (default: 7878)
+                       [--capture <file>]   write every packet the controller sees to <file>, one JSON record per line
+                       [--shortcut-policy <deliver|drop|delay:<ms>>]
+                                            what the controller does with the packets drones hand to it (default: deliver)
+  simulation_controller generate [options]   write a generated network config
+  simulation_controller lint [--fix] [files]  check the links declared in network configs (default: config/*.toml)
+  simulation_controller export <config> [--format <dot|graphml|svg>] [--out <path>]
+                                             draw the network of a config (default: dot, on stdout)
+  simulation_controller capture <file> [filters] [--json]
+                                             print the packets of a capture, --json keeps the records as they are
+
+capture filters:
+  --session <n>               session id
+  --type <t>                  msg_fragment, ack, nack, flood_request or flood_response
+  --source <n>                first hop of the route
+  --destination <n>           last hop of the route
+  --hop <n>                   node anywhere on the route
+  --disposition <d>           sent, dropped or shortcut
+
+generate options:
+  --shape <ring|grid|er|ba>   shape of the drone core (default: ring)
+  --param <value>             grid columns, Erdős–Rényi p or Barabási–Albert m
+  --drones <n>                number of drones (default: 10)
+  --clients <n>               number of clients (default: 2)
+  --servers <n>               number of servers (default: 2)
+  --pdr <p>                   fixed packet drop rate for every drone
+  --pdr-min <p> --pdr-max <p> uniform packet drop rate range (default: 0 - 0.2)
+  --seed <n>                  seed, the same seed always gives the same network (default: 0)
+  --out <path>                output file (default: ./config/generated.toml)";
+
+/// Runs the subcommand in `args`, if any.
+///
+/// Returns `None` when no subcommand was given and the UI should start,
+/// otherwise the exit code of the subcommand.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.get(1).filter(|a| !matches!(a.as_str(), "--output" | "--api" | "--capture" | "--shortcut-policy"))?;
+
+    let code = match command.as_str() {
+        "generate" => generate(&args[2..]),
+        "lint" => lint(&args[2..]),
+        "export" => export(&args[2..]),
+        "capture" => read_capture(&args[2..]),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            0
+        }
+        other => {
+            eprintln!("unknown command '{other}'\n\n{USAGE}");
+            2
+        }
+    };
+
+    Some(code)
+}
+
+/// Reports a bad option of the UI with the usage, like the subcommands do, and exits.
+pub fn exit_with_usage(error: &str) -> ! {
+    eprintln!("{error}\n\n{USAGE}");
+    std::process::exit(2);
+}
+
+/// Base folder given with `--output` when starting the UI.
+pub fn output_dir(args: &[String]) -> Option<String> {
+    let position = args.iter().position(|a| a == "--output")?;
+    args.get(position + 1).cloned()
+}
+
+/// Port given with `--api`, the default one when the flag has no value.
+pub fn api_port(args: &[String]) -> Option<u16> {
+    let position = args.iter().position(|a| a == "--api")?;
+    Some(args.get(position + 1).and_then(|p| p.parse().ok()).unwrap_or(crate::api::DEFAULT_PORT))
+}
+
+/// File given with `--capture` when starting the UI.
+pub fn capture_file(args: &[String]) -> Option<String> {
+    let position = args.iter().position(|a| a == "--capture")?;
+    args.get(position + 1).cloned()
+}
+
+/// Policy given with `--shortcut-policy` when starting the UI.
+pub fn shortcut_policy(args: &[String]) -> Option<Result<Policy, String>> {
+    let position = args.iter().position(|a| a == "--shortcut-policy")?;
+    Some(args.get(position + 1).ok_or_else(|| "missing value for --shortcut-policy".to_string()).and_then(|p| Policy::parse(p)))
+}
+
+fn generate(args: &[String]) -> i32 {
+    let (params, out) = match parse_generate(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return 2;
+        }
+    };
+
+    match generator::generate(&params) {
+        Ok(config) => match config.save(&out) {
+            Ok(()) => {
+                println!(
+                    "generated {} drones, {} clients and {} servers into {out}",
+                    config.drone.len(),
+                    config.client.len(),
+                    config.server.len()
+                );
+                0
+            }
+            Err(e) => {
+                eprintln!("cannot write {out}: {e}");
+                1
+            }
+        },
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{error}");
+            }
+            1
+        }
+    }
+}
+
+pub fn parse_generate(args: &[String]) -> Result<(GeneratorParams, String), String> {
+    let mut params = GeneratorParams::default();
+    let mut out = "./config/generated.toml".to_string();
+    let mut shape = "ring".to_string();
+    let mut shape_param = None;
+    let mut pdr_fixed = None;
+    let (mut pdr_min, mut pdr_max) = (0.0, 0.2);
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let value = iter.next().ok_or_else(|| format!("missing value for {flag}"))?;
+        match flag.as_str() {
+            "--shape" => shape = value.clone(),
+            "--param" => shape_param = Some(parse_value(flag, value)?),
+            "--drones" => params.drones = parse_value(flag, value)?,
+            "--clients" => params.clients = parse_value(flag, value)?,
+            "--servers" => params.servers = parse_value(flag, value)?,
+            "--pdr" => pdr_fixed = Some(parse_value(flag, value)?),
+            "--pdr-min" => pdr_min = parse_value(flag, value)?,
+            "--pdr-max" => pdr_max = parse_value(flag, value)?,
+            "--seed" => params.seed = parse_value(flag, value)?,
+            "--out" => out = value.clone(),
+            other => return Err(format!("unknown option '{other}'")),
+        }
+    }
+
+    params.shape = Shape::parse(&shape, shape_param)?;
+    params.pdr = match pdr_fixed {
+        Some(pdr) => PdrDistribution::Fixed(pdr),
+        None => PdrDistribution::Uniform { min: pdr_min, max: pdr_max },
+    };
+
+    Ok((params, out))
+}
+
+fn lint(args: &[String]) -> i32 {
+    if let Some(other) = args.iter().find(|a| a.starts_with('-') && *a != "--fix") {
+        exit_with_usage(&format!("unknown option '{other}'"));
+    }
+    let fix = args.iter().any(|a| a == "--fix");
+    let mut files: Vec<String> = args.iter().filter(|a| *a != "--fix").cloned().collect();
+
+    if files.is_empty() {
+        files = match std::fs::read_dir("./config") {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+                .map(|p| p.display().to_string())
+                .collect(),
+            Err(e) => {
+                eprintln!("cannot read ./config: {e}");
+                return 1;
+            }
+        };
+        files.sort();
+    }
+
+    let mut errors = 0;
+    let mut fixable = 0;
+
+    for file in &files {
+        let diagnostics = match validation::lint_file(file) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => {
+                eprintln!("{e}");
+                errors += 1;
+                continue;
+            }
+        };
+
+        for diagnostic in &diagnostics {
+            println!("{diagnostic}");
+        }
+        errors += diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+
+        let file_fixable = diagnostics.iter().filter(|d| d.fixable).count();
+        fixable += file_fixable;
+
+        if fix && file_fixable > 0 {
+            let fixed = std::fs::read_to_string(file).map_err(|e| e.to_string())
+                .and_then(|content| validation::fix_symmetric_links_in(&content))
+                .and_then(|content| std::fs::write(file, content).map_err(|e| e.to_string()));
+            match fixed {
+                Ok(()) => println!("{file}: added the missing end of {file_fixable} link(s)"),
+                Err(e) => eprintln!("{file}: cannot fix: {e}"),
+            }
+        }
+    }
+
+    if fixable > 0 && !fix {
+        println!("{fixable} one-sided link(s) can be fixed automatically, run again with --fix");
+    }
+
+    // fixed links do not count anymore, everything else still needs a human
+    let remaining = if fix { errors - fixable } else { errors };
+    if remaining > 0 { 1 } else { 0 }
+}
+
+fn export(args: &[String]) -> i32 {
+    let (config, format, out) = match parse_export(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return 2;
+        }
+    };
+
+    let graph = match TopologyConfig::from_file(&config) {
+        Ok(topology) => ExportGraph::of_config(&topology),
+        Err(e) => {
+            eprintln!("{config}: {e}");
+            return 1;
+        }
+    };
+
+    let rendered = graph.render(format);
+    match out {
+        Some(out) => match std::fs::write(&out, rendered) {
+            Ok(()) => {
+                println!("exported {} nodes and {} links into {out}", graph.nodes.len(), graph.edges.len());
+                0
+            }
+            Err(e) => {
+                eprintln!("cannot write {out}: {e}");
+                1
+            }
+        },
+        None => {
+            print!("{rendered}");
+            0
+        }
+    }
+}
+
+/// Config path, format and output file of `export`, the format defaults to the extension of the output.
+pub fn parse_export(args: &[String]) -> Result<(String, Format, Option<String>), String> {
+    let mut config = None;
+    let mut format = None;
+    let mut out = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => {
+                let value = iter.next().ok_or("missing value for --format")?;
+                format = Some(Format::parse(value).ok_or_else(|| format!("unknown format '{value}', expected dot, graphml or svg"))?);
+            }
+            "--out" => out = Some(iter.next().ok_or("missing value for --out")?.clone()),
+            other if other.starts_with("--") => return Err(format!("unknown option '{other}'")),
+            path => config = Some(path.to_string()),
+        }
+    }
+
+    let config = config.ok_or("missing config file")?;
+    let from_extension = out.as_deref().and_then(|o| o.rsplit_once('.')).and_then(|(_, ext)| Format::parse(ext));
+    Ok((config, format.or(from_extension).unwrap_or(Format::Dot), out))
+}
+
+fn read_capture(args: &[String]) -> i32 {
+    let (file, filter, json) = match parse_capture(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return 2;
+        }
+    };
+
+    match capture::read(&file, &filter) {
+        Ok(records) => {
+            for record in &records {
+                if json {
+                    println!("{}", serde_json::to_string(record).unwrap_or_default());
+                } else {
+                    println!("{}", record.summary());
+                }
+            }
+            if !json {
+                println!("{} packet(s)", records.len());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{file}: {e}");
+            1
+        }
+    }
+}
+
+/// Capture file, filter and whether to print raw records, for `capture`.
+pub fn parse_capture(args: &[String]) -> Result<(String, Filter, bool), String> {
+    let mut file = None;
+    let mut filter = Filter::default();
+    let mut json = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            flag @ ("--session" | "--type" | "--source" | "--destination" | "--hop" | "--disposition") => {
+                let value = iter.next().ok_or_else(|| format!("missing value for {flag}"))?;
+                match flag {
+                    "--session" => filter.session = Some(parse_value(flag, value)?),
+                    "--source" => filter.source = Some(parse_value(flag, value)?),
+                    "--destination" => filter.destination = Some(parse_value(flag, value)?),
+                    "--hop" => filter.hop = Some(parse_value(flag, value)?),
+                    "--type" if Payload::TYPES.contains(&value.as_str()) => filter.packet_type = Some(value.clone()),
+                    "--type" => return Err(format!("unknown packet type '{value}'")),
+                    _ => filter.disposition = Some(Disposition::parse(value).ok_or_else(|| format!("unknown disposition '{value}', expected sent, dropped or shortcut"))?),
+                }
+            }
+            other if other.starts_with("--") => return Err(format!("unknown option '{other}'")),
+            path => file = Some(path.to_string()),
+        }
+    }
+
+    Ok((file.ok_or("missing capture file")?, filter, json))
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse::<T>().map_err(|_| format!("invalid value '{value}' for {flag}"))
+}
@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+
+use slint::{ModelRc, SharedString, VecModel};
+use wg_internal::network::NodeId;
+
+use crate::topology::{ClientConfig, DroneConfig, ServerConfig, TopologyConfig};
+use crate::validation;
+use crate::{Edge, MainWindow, Position};
+
+const CANVAS_SIZE: f64 = 560.0;
+const NODE_SIZE: f32 = 40.0;
+
+/// State of the topology editor.
+///
+/// The edited network is kept as a `TopologyConfig`, so validating and saving it goes through
+/// the same code as generated and exported networks. Links are always written on both ends.
+#[derive(Default)]
+pub struct Editor {
+    pub config: TopologyConfig,
+    positions: HashMap<NodeId, (f32, f32)>,
+    selected: Option<NodeId>,
+}
+
+impl Editor {
+
+    /// Starts editing `config`, nodes are laid out on a circle like in the graph view.
+    pub fn load(&mut self, config: TopologyConfig) {
+        let mut ids: Vec<NodeId> = config.node_kinds().keys().copied().collect();
+        ids.sort();
+
+        let n = ids.len().max(1) as f64;
+        let r = CANVAS_SIZE / 2.0 - NODE_SIZE as f64;
+
+        self.positions = ids.iter().enumerate().map(|(i, id)| {
+            let theta = 2.0 * PI * (i as f64) / n;
+            (*id, ((CANVAS_SIZE / 2.0 + r * theta.cos()) as f32, (CANVAS_SIZE / 2.0 + r * theta.sin()) as f32))
+        }).collect();
+
+        self.config = config;
+        self.selected = None;
+    }
+
+    /// Places a new node of `kind` at the given canvas position and returns its id.
+    pub fn place(&mut self, kind: &str, x: f32, y: f32) -> Result<NodeId, String> {
+        let kinds = self.config.node_kinds();
+        let id = (1..=NodeId::MAX).find(|id| !kinds.contains_key(id)).ok_or("no free node id left")?;
+
+        match kind {
+            "drone" => self.config.drone.push(DroneConfig { id, connected_node_ids: Vec::new(), pdr: 0.0 }),
+            "client" => self.config.client.push(ClientConfig { id, connected_drone_ids: Vec::new() }),
+            "server" => self.config.server.push(ServerConfig { id, connected_drone_ids: Vec::new() }),
+            other => return Err(format!("unknown node kind '{other}'")),
+        }
+
+        self.positions.insert(id, (x, y));
+        self.selected = Some(id);
+        Ok(id)
+    }
+
+    /// Selects `id`, or links it to the selected node when `link` is set.
+    ///
+    /// Clicking twice on the same pair removes the link.
+    pub fn click(&mut self, id: NodeId, link: bool) -> Result<(), String> {
+        match self.selected {
+            Some(selected) if link && selected != id => {
+                self.toggle_link(selected, id)?;
+                self.selected = None;
+            }
+            _ => self.selected = Some(id),
+        }
+        Ok(())
+    }
+
+    pub fn selected(&self) -> Option<NodeId> {
+        self.selected
+    }
+
+    pub fn toggle_link(&mut self, a: NodeId, b: NodeId) -> Result<(), String> {
+        let kinds = self.config.node_kinds();
+        let (Some(kind_a), Some(kind_b)) = (kinds.get(&a), kinds.get(&b)) else {
+            return Err(format!("cannot link {a} and {b}: unknown node"));
+        };
+        if *kind_a != "drone" && *kind_b != "drone" {
+            return Err(format!("cannot link {kind_a} {a} and {kind_b} {b}: one of them must be a drone"));
+        }
+
+        let linked = self.neighbours_mut(a).map(|n| n.contains(&b)).unwrap_or(false);
+
+        for (from, to) in [(a, b), (b, a)] {
+            if let Some(neighbours) = self.neighbours_mut(from) {
+                if linked {
+                    neighbours.retain(|n| *n != to);
+                } else if !neighbours.contains(&to) {
+                    neighbours.push(to);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_pdr(&mut self, id: NodeId, pdr: f32) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&pdr) {
+            return Err("Packet Drop Rate must be between 0 and 100".to_string());
+        }
+        let drone = self.config.drone.iter_mut().find(|d| d.id == id).ok_or(format!("node {id} is not a drone"))?;
+        drone.pdr = pdr;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: NodeId) {
+        self.config.drone.retain(|d| d.id != id);
+        self.config.client.retain(|c| c.id != id);
+        self.config.server.retain(|s| s.id != id);
+
+        for d in &mut self.config.drone { d.connected_node_ids.retain(|n| *n != id); }
+        for c in &mut self.config.client { c.connected_drone_ids.retain(|n| *n != id); }
+        for s in &mut self.config.server { s.connected_drone_ids.retain(|n| *n != id); }
+
+        self.positions.remove(&id);
+        if self.selected == Some(id) {
+            self.selected = None;
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        validation::validate_topology(&self.config)
+    }
+
+    /// Validates the network and writes it to `path`.
+    pub fn save(&self, path: &str) -> Result<(), Vec<String>> {
+        self.validate()?;
+        let mut config = self.config.clone();
+        config.sort();
+        config.save(path).map_err(|e| vec![format!("cannot write {path}: {e}")])
+    }
+
+    fn describe_selected(&self) -> String {
+        let Some(id) = self.selected else {
+            return String::new();
+        };
+        if let Some(d) = self.config.drone.iter().find(|d| d.id == id) {
+            return format!("Drone {id}, PDR {:.0}%, linked to {:?}", d.pdr * 100., d.connected_node_ids);
+        }
+        if let Some(c) = self.config.client.iter().find(|c| c.id == id) {
+            return format!("Client {id}, linked to {:?}", c.connected_drone_ids);
+        }
+        if let Some(s) = self.config.server.iter().find(|s| s.id == id) {
+            return format!("Server {id}, linked to {:?}", s.connected_drone_ids);
+        }
+        String::new()
+    }
+
+    fn neighbours_mut(&mut self, id: NodeId) -> Option<&mut Vec<NodeId>> {
+        if let Some(d) = self.config.drone.iter_mut().find(|d| d.id == id) {
+            return Some(&mut d.connected_node_ids);
+        }
+        if let Some(c) = self.config.client.iter_mut().find(|c| c.id == id) {
+            return Some(&mut c.connected_drone_ids);
+        }
+        self.config.server.iter_mut().find(|s| s.id == id).map(|s| &mut s.connected_drone_ids)
+    }
+
+    /// Pushes nodes, links and the selection to the editor canvas.
+    pub fn render(&self, main_window: &MainWindow) {
+        let kinds = self.config.node_kinds();
+
+        let mut nodes = Vec::with_capacity(self.positions.len());
+        for (id, (x, y)) in &self.positions {
+            nodes.push(Position {
+                id: *id as i32,
+                x: *x,
+                y: *y,
+                size: NODE_SIZE,
+                kind: SharedString::from(*kinds.get(id).unwrap_or(&"unknown")),
+                label: SharedString::from(id.to_string()),
+                highlighted: false,
+                crashed: false,
+            });
+        }
+
+        let links: HashSet<(NodeId, NodeId)> = self.config.declared_links().into_iter().collect();
+        let mut edges = Vec::new();
+        for (a, b) in &links {
+            let (a, b) = (*a, *b);
+            // each link is declared on both ends, draw it once
+            if a > b && links.contains(&(b, a)) {
+                continue;
+            }
+            if let (Some(from), Some(to)) = (self.positions.get(&a), self.positions.get(&b)) {
+                edges.push(Edge { from_x: from.0, from_y: from.1, to_x: to.0, to_y: to.1, offset: 0., faulty: false });
+            }
+        }
+
+        main_window.set_editor_nodes(ModelRc::new(VecModel::from(nodes)));
+        main_window.set_editor_edges(ModelRc::new(VecModel::from(edges)));
+        main_window.set_editor_selected(self.selected.map(|id| id.to_string()).unwrap_or_default().into());
+        main_window.set_editor_status(self.describe_selected().into());
+    }
+}
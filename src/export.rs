@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use wg_internal::network::NodeId;
+use wg_internal::packet::NodeType as WGNodeType;
+
+use crate::graph_utils::GraphLayout;
+use crate::topology::TopologyConfig;
+use crate::{Position, SimulationController};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Dot,
+    GraphMl,
+    Svg,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "dot" | "gv" => Some(Format::Dot),
+            "graphml" => Some(Format::GraphMl),
+            "svg" => Some(Format::Svg),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Dot => "dot",
+            Format::GraphMl => "graphml",
+            Format::Svg => "svg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportNode {
+    pub id: NodeId,
+    /// `drone`, `client` or `server`, as in `generate_generic_network_view`.
+    pub kind: String,
+    pub pdr: Option<f32>,
+    pub crashed: bool,
+}
+
+/// A network ready to be written out, nodes and links sorted by id so exports are stable.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportGraph {
+    pub nodes: Vec<ExportNode>,
+    pub edges: Vec<(NodeId, NodeId)>,
+    /// Packets sent over each link, keyed by `(smaller id, larger id)`.
+    pub traffic: BTreeMap<(NodeId, NodeId), u64>,
+}
+
+impl ExportGraph {
+
+    pub fn new(graph: &HashMap<(NodeId, String), HashSet<NodeId>>, pdr: &HashMap<NodeId, f32>) -> Self {
+        let mut nodes = graph.keys()
+            .map(|(id, kind)| ExportNode { id: *id, kind: kind.clone(), pdr: pdr.get(id).copied(), crashed: false })
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|n| n.id);
+
+        let mut edges = graph.iter()
+            .flat_map(|((a, _), adjacent)| adjacent.iter().map(move |b| (*a.min(b), *a.max(b))))
+            .filter(|(a, b)| a != b)
+            .collect::<Vec<_>>();
+        edges.sort();
+        edges.dedup();
+
+        Self { nodes, edges, traffic: BTreeMap::new() }
+    }
+
+    /// The network declared in a config, before anything ran.
+    pub fn of_config(config: &TopologyConfig) -> Self {
+        let pdr = config.drone.iter().map(|d| (d.id, d.pdr)).collect();
+        Self::new(&config.to_generic_graph(), &pdr)
+    }
+
+    /// The running network, with the nodes crashed so far and the traffic seen on each link.
+    pub fn of_controller(sc: &SimulationController) -> Self {
+        let pdr = sc.drones.iter().map(|(id, (pdr, _))| (*id, *pdr)).collect();
+        let mut export = Self::new(&sc.generic_graph(), &pdr);
+
+        for id in &sc.crashed {
+            if !export.nodes.iter().any(|n| n.id == *id) {
+                export.nodes.push(ExportNode { id: *id, kind: "drone".to_string(), pdr: None, crashed: true });
+            }
+        }
+        for node in sc.network_view.nodes.iter().filter(|n| sc.dead.contains_key(&n.get_id())) {
+            let kind = if matches!(node.get_node_type(), WGNodeType::Client) { "client" } else { "server" };
+            export.nodes.push(ExportNode { id: node.get_id(), kind: kind.to_string(), pdr: None, crashed: true });
+        }
+        export.nodes.sort_by_key(|n| n.id);
+        export.traffic = sc.metrics.lock().unwrap().edge_packets.clone();
+        export
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Dot => self.to_dot(),
+            Format::GraphMl => self.to_graphml(),
+            Format::Svg => self.to_svg(&self.layout()),
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("graph network {\n    node [style=filled, fontname=\"Helvetica\"];\n");
+        for node in &self.nodes {
+            let shape = match node.kind.as_str() {
+                "client" => "box",
+                "server" => "doubleoctagon",
+                _ => "circle",
+            };
+            let pdr = node.pdr.map(|p| format!(", pdr=\"{p}\"")).unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "    {} [label=\"{}\", shape={shape}, fillcolor=\"{}\", type=\"{}\"{pdr}, crashed=\"{}\"];",
+                node.id, node.id, fill(node), node.kind, node.crashed
+            );
+        }
+        for (a, b) in &self.edges {
+            match self.traffic.get(&(*a, *b)) {
+                Some(packets) => { let _ = writeln!(out, "    {a} -- {b} [packets=\"{packets}\", label=\"{packets}\"];"); }
+                None => { let _ = writeln!(out, "    {a} -- {b};"); }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>\n",
+            "  <key id=\"pdr\" for=\"node\" attr.name=\"pdr\" attr.type=\"double\"/>\n",
+            "  <key id=\"crashed\" for=\"node\" attr.name=\"crashed\" attr.type=\"boolean\"><default>false</default></key>\n",
+            "  <key id=\"packets\" for=\"edge\" attr.name=\"packets\" attr.type=\"long\"/>\n",
+            "  <graph id=\"network\" edgedefault=\"undirected\">\n",
+        ));
+        for node in &self.nodes {
+            let _ = writeln!(out, "    <node id=\"n{}\">", node.id);
+            let _ = writeln!(out, "      <data key=\"type\">{}</data>", node.kind);
+            if let Some(pdr) = node.pdr {
+                let _ = writeln!(out, "      <data key=\"pdr\">{pdr}</data>");
+            }
+            if node.crashed {
+                out.push_str("      <data key=\"crashed\">true</data>\n");
+            }
+            out.push_str("    </node>\n");
+        }
+        for (a, b) in &self.edges {
+            match self.traffic.get(&(*a, *b)) {
+                Some(packets) => {
+                    let _ = writeln!(out, "    <edge source=\"n{a}\" target=\"n{b}\">");
+                    let _ = writeln!(out, "      <data key=\"packets\">{packets}</data>");
+                    out.push_str("    </edge>\n");
+                }
+                None => { let _ = writeln!(out, "    <edge source=\"n{a}\" target=\"n{b}\"/>"); }
+            }
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    /// The positions the graph view would give these nodes, crashed ones included.
+    pub fn layout(&self) -> HashMap<NodeId, Position> {
+        let mut graph = HashMap::<(NodeId, String), HashSet<NodeId>>::new();
+        for node in &self.nodes {
+            graph.insert((node.id, node.kind.clone()), HashSet::new());
+        }
+        let mut layout = GraphLayout::default();
+        layout.update(&graph);
+        layout.positions().clone()
+    }
+
+    /// Draws the nodes at `positions`, nodes without one are lined up along the bottom edge.
+    /// Links are thicker the more packets they carried.
+    pub fn to_svg(&self, positions: &HashMap<NodeId, Position>) -> String {
+        let positions = &self.place_missing(positions);
+        let mut out = String::from(concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"648\" height=\"648\" viewBox=\"0 0 648 648\" font-family=\"Helvetica, sans-serif\">\n",
+            "  <rect width=\"648\" height=\"648\" fill=\"#1e222a\"/>\n",
+        ));
+
+        let busiest = self.traffic.values().copied().max().unwrap_or(0).max(1) as f32;
+        for (a, b) in &self.edges {
+            let (Some(p1), Some(p2)) = (positions.get(a), positions.get(b)) else {
+                continue;
+            };
+            let packets = self.traffic.get(&(*a, *b)).copied();
+            let width = 1.5 + 4.5 * packets.unwrap_or(0) as f32 / busiest;
+            let _ = writeln!(
+                out,
+                "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#7b8496\" stroke-width=\"{width:.1}\"/>",
+                p1.x, p1.y, p2.x, p2.y
+            );
+            if let Some(packets) = packets {
+                let _ = writeln!(
+                    out,
+                    "  <text x=\"{:.1}\" y=\"{:.1}\" fill=\"#d8dee9\" font-size=\"10\" text-anchor=\"middle\">{packets}</text>",
+                    (p1.x + p2.x) / 2., (p1.y + p2.y) / 2. - 3.
+                );
+            }
+        }
+
+        for node in &self.nodes {
+            let Some(p) = positions.get(&node.id) else {
+                continue;
+            };
+            let _ = writeln!(
+                out,
+                "  <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"#1e222a\" stroke-width=\"2\"/>",
+                p.x, p.y, p.size / 2., fill(node)
+            );
+            let pdr = node.pdr.map(|p| format!(" ({:.0}%)", p * 100.)).unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "  <text x=\"{:.1}\" y=\"{:.1}\" fill=\"#ffffff\" font-size=\"12\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}{pdr}</text>",
+                p.x, p.y, node.id
+            );
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// `positions` with a spot for every node, the ones it does not have go in a row at the bottom.
+    fn place_missing(&self, positions: &HashMap<NodeId, Position>) -> HashMap<NodeId, Position> {
+        let mut all = positions.clone();
+        let missing = self.nodes.iter().filter(|n| !positions.contains_key(&n.id));
+        for (i, node) in missing.enumerate() {
+            all.insert(node.id, Position {
+                id: node.id as i32,
+                x: 24. + 32. * i as f32,
+                y: 624.,
+                size: 24.,
+                kind: node.kind.clone().into(),
+                label: node.id.to_string().into(),
+                highlighted: false,
+                crashed: node.crashed,
+            });
+        }
+        all
+    }
+}
+
+fn fill(node: &ExportNode) -> &'static str {
+    if node.crashed {
+        return "#4c566a";
+    }
+    match node.kind.as_str() {
+        "drone" => "#805f2f",
+        "client" => "#5e81ac",
+        "server" => "#a3be8c",
+        _ => "#bf616a",
+    }
+}
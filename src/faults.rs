@@ -0,0 +1,285 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{after, never, unbounded, Receiver, RecvTimeoutError, Sender};
+use crossbeam::select;
+use serde::{Deserialize, Serialize};
+
+use wg_internal::network::NodeId;
+use wg_internal::packet::Packet;
+
+use crate::generator::Rng;
+
+/// Longest latency, jitter or cut accepted, an hour.
+pub const MAX_MS: u64 = 60 * 60 * 1000;
+
+/// Faults the controller adds on a link, in both directions. The default is a plain link.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkFault {
+    /// Added to every packet.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Each packet gets up to this much more or less latency.
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Probability that a packet is held back and overtaken by the ones after it.
+    #[serde(default)]
+    pub reorder: f32,
+    /// Probability that a packet arrives twice.
+    #[serde(default)]
+    pub duplicate: f32,
+    /// The link loses every packet for this long once the fault is set, then heals.
+    #[serde(default)]
+    pub cut_ms: u64,
+}
+
+impl LinkFault {
+    pub fn is_clear(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Reads the fields of the link faults menu, probabilities in percent. Empty fields are 0.
+    pub fn from_fields(latency: &str, jitter: &str, reorder: &str, duplicate: &str, cut: &str) -> Result<Self, String> {
+        fn field<T: std::str::FromStr + Default>(name: &str, value: &str) -> Result<T, String> {
+            let value = value.trim();
+            if value.is_empty() {
+                return Ok(T::default());
+            }
+            value.parse().map_err(|_| format!("Invalid {name}: {value}"))
+        }
+
+        let fault = Self {
+            latency_ms: field("latency", latency)?,
+            jitter_ms: field("jitter", jitter)?,
+            reorder: field::<f32>("reorder", reorder)? / 100.,
+            duplicate: field::<f32>("duplicate", duplicate)? / 100.,
+            cut_ms: field("cut", cut)?,
+        };
+        fault.check()?;
+        Ok(fault)
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.reorder) || !(0.0..=1.0).contains(&self.duplicate) {
+            return Err("Reorder and duplicate probabilities must be between 0 and 100%".to_string());
+        }
+        if self.latency_ms > MAX_MS || self.jitter_ms > MAX_MS || self.cut_ms > MAX_MS {
+            return Err(format!("Latency, jitter and cut must be at most {MAX_MS} ms"));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for LinkFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clear() {
+            return write!(f, "no faults");
+        }
+        write!(f, "latency {} ms ± {} ms, reorder {:.0}%, duplicate {:.0}%", self.latency_ms, self.jitter_ms, self.reorder * 100., self.duplicate * 100.)?;
+        if self.cut_ms > 0 {
+            write!(f, ", cut for {} ms", self.cut_ms)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `[[link]]` table of a network file, only read by the controller.
+///
+/// ```toml
+/// [[link]]
+/// between = [1, 2]
+/// latency_ms = 40
+/// jitter_ms = 10
+/// reorder = 0.05
+/// duplicate = 0.01
+/// cut_ms = 3000
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkConfig {
+    pub between: [NodeId; 2],
+    #[serde(flatten)]
+    pub fault: LinkFault,
+}
+
+/// `(smaller id, larger id)`, links are undirected.
+pub fn key(a: NodeId, b: NodeId) -> (NodeId, NodeId) {
+    (a.min(b), a.max(b))
+}
+
+#[derive(Debug)]
+struct LinkState {
+    fault: LinkFault,
+    cut_until: Option<Instant>,
+}
+
+impl LinkState {
+    fn new(fault: LinkFault) -> Self {
+        let cut_until = (fault.cut_ms > 0).then(|| Instant::now().checked_add(Duration::from_millis(fault.cut_ms))).flatten();
+        Self { fault, cut_until }
+    }
+}
+
+/// A link with faults: the nodes at both ends send to these channels instead of each other's,
+/// a thread per direction forwards what they send once the faults are applied.
+///
+/// The threads stop with `stop`, when the link is dropped, dropping the packets still in it.
+pub struct FaultyLink {
+    state: Arc<Mutex<LinkState>>,
+    /// Channel into the link towards each end.
+    ends: [(NodeId, Sender<Packet>); 2],
+    /// Nothing is sent on it, the threads stop once it disconnects.
+    stop: Option<Sender<()>>,
+}
+
+impl FaultyLink {
+
+    /// Interposes on the link between `a` and `b`, `to_a` and `to_b` are their real channels.
+    pub fn new(a: NodeId, to_a: Sender<Packet>, b: NodeId, to_b: Sender<Packet>, fault: LinkFault) -> Self {
+        let state = Arc::new(Mutex::new(LinkState::new(fault)));
+        let (stop, stopped) = unbounded();
+        let seed = ((a as u64) << 8) | b as u64;
+        Self {
+            ends: [
+                (a, interpose(to_a, state.clone(), stopped.clone(), seed)),
+                (b, interpose(to_b, state.clone(), stopped, seed.rotate_left(16))),
+            ],
+            state,
+            stop: Some(stop),
+        }
+    }
+
+    /// Stops forwarding in both directions, the ends keep senders that lead nowhere.
+    pub fn stop(&mut self) {
+        self.stop = None;
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_none()
+    }
+
+    pub fn fault(&self) -> LinkFault {
+        self.state.lock().unwrap().fault
+    }
+
+    /// Applies `fault` to the packets sent from now on, a cut starts again.
+    pub fn set_fault(&self, fault: LinkFault) {
+        *self.state.lock().unwrap() = LinkState::new(fault);
+    }
+
+    pub fn is_cut(&self) -> bool {
+        self.state.lock().unwrap().cut_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    /// The channel to hand out to the other end as the sender of `node`.
+    pub fn sender_to(&self, node: NodeId) -> Option<Sender<Packet>> {
+        self.ends.iter().find(|(id, _)| *id == node).map(|(_, sender)| sender.clone())
+    }
+}
+
+impl Drop for FaultyLink {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Something waiting until `due`, a `BinaryHeap` gives the earliest first and ties keep their order.
+pub(crate) struct Delayed<T> {
+    pub due: Instant,
+    pub seq: u64,
+    pub item: T,
+}
+
+impl<T> PartialEq for Delayed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.due, self.seq) == (other.due, other.seq)
+    }
+}
+
+impl<T> Eq for Delayed<T> {}
+
+impl<T> PartialOrd for Delayed<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Delayed<T> {
+    // reversed, `BinaryHeap` pops the largest
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.due, other.seq).cmp(&(self.due, self.seq))
+    }
+}
+
+fn interpose(to: Sender<Packet>, state: Arc<Mutex<LinkState>>, stop: Receiver<()>, seed: u64) -> Sender<Packet> {
+    let (sender, receiver) = unbounded();
+    std::thread::spawn(move || forward(receiver, to, state, stop, Rng(seed)));
+    sender
+}
+
+fn forward(from: Receiver<Packet>, to: Sender<Packet>, state: Arc<Mutex<LinkState>>, stop: Receiver<()>, mut rng: Rng) {
+    let mut queue = BinaryHeap::<Delayed<Packet>>::new();
+    let mut seq = 0;
+    let mut open = true;
+
+    while open || !queue.is_empty() {
+        let wait = queue.peek().map(|d| d.due.saturating_duration_since(Instant::now()));
+        let timer = wait.map_or_else(never, after);
+        let received = if open {
+            select! {
+                recv(from) -> packet => packet.map_err(|_| RecvTimeoutError::Disconnected),
+                recv(stop) -> _ => return,
+                recv(timer) -> _ => Err(RecvTimeoutError::Timeout),
+            }
+        } else {
+            // nothing more comes in, the packets already in the link still arrive
+            select! {
+                recv(stop) -> _ => return,
+                recv(timer) -> _ => Err(RecvTimeoutError::Timeout),
+            }
+        };
+
+        match received {
+            Ok(packet) => {
+                let now = Instant::now();
+                let (fault, cut_until) = {
+                    let state = state.lock().unwrap();
+                    (state.fault, state.cut_until)
+                };
+                if cut_until.is_some_and(|until| now < until) {
+                    continue;
+                }
+
+                let copies = if rng.next_f64() < fault.duplicate as f64 { 2 } else { 1 };
+                for _ in 0..copies {
+                    let jitter = fault.jitter_ms as f64 * (2. * rng.next_f64() - 1.);
+                    let mut delay = (fault.latency_ms as f64 + jitter).max(0.);
+                    if rng.next_f64() < fault.reorder as f64 {
+                        // long enough for the next packets to overtake it
+                        delay += fault.jitter_ms.saturating_mul(2).saturating_add(fault.latency_ms).max(20) as f64;
+                    }
+                    // `check` keeps delays far from this, a packet delayed past what `Instant` holds is lost
+                    let Some(due) = now.checked_add(Duration::from_micros((delay * 1000.) as u64)) else {
+                        continue;
+                    };
+                    queue.push(Delayed { due, seq, item: packet.clone() });
+                    seq += 1;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => open = false,
+        }
+
+        let now = Instant::now();
+        while queue.peek().is_some_and(|d| d.due <= now) {
+            if let Some(delayed) = queue.pop() {
+                // the other end is gone, like a crashed drone
+                if to.send(delayed.item).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::rc::Rc;
+
+use slint::{Image, SharedString, VecModel};
+use uuid::Uuid;
+
+use common::types::{MediaFile, TextFile};
+use wg_internal::network::NodeId;
+
+use crate::{FileEntry, MainWindow};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileContent {
+    /// Only the title is known, e.g. from `FilesLists` or `TextFileAdded`.
+    Unknown,
+    Text(String),
+    Media(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    pub id: Uuid,
+    pub title: String,
+    pub is_media: bool,
+    pub content: FileContent,
+}
+
+/// What the controller knows about the files held by each web server.
+///
+/// Filled from `TextFiles`/`MediaFiles` sent by a server, from the `FilesLists` gathered by
+/// web browsers and from the single files browsers receive, so content shows up as soon as
+/// any node transfers it.
+#[derive(Debug, Default)]
+pub struct FileCatalog {
+    servers: BTreeMap<NodeId, BTreeMap<Uuid, CatalogEntry>>,
+}
+
+impl FileCatalog {
+
+    pub fn add_text_files(&mut self, server: NodeId, files: &[TextFile]) {
+        for file in files {
+            self.add_text_file(server, file);
+        }
+    }
+
+    pub fn add_text_file(&mut self, server: NodeId, file: &TextFile) {
+        self.servers.entry(server).or_default().insert(file.id, CatalogEntry {
+            id: file.id,
+            title: file.title.clone(),
+            is_media: false,
+            content: FileContent::Text(file.content.clone()),
+        });
+    }
+
+    pub fn add_media_files(&mut self, server: NodeId, files: &[MediaFile]) {
+        for file in files {
+            self.add_media_file(server, file);
+        }
+    }
+
+    pub fn add_media_file(&mut self, server: NodeId, file: &MediaFile) {
+        self.servers.entry(server).or_default().insert(file.id, CatalogEntry {
+            id: file.id,
+            title: file.title.clone(),
+            is_media: true,
+            content: FileContent::Media(file.content.concat()),
+        });
+    }
+
+    /// Records a file known only by id and title, keeping the content if it is already known.
+    pub fn add_listed(&mut self, server: NodeId, id: Uuid, title: &str, is_media: bool) {
+        self.servers.entry(server).or_default().entry(id).or_insert_with(|| CatalogEntry {
+            id,
+            title: title.to_string(),
+            is_media,
+            content: FileContent::Unknown,
+        });
+    }
+
+    /// Fills the content of a file a browser received, wherever the file is listed.
+    pub fn fill_text(&mut self, file: &TextFile) {
+        for entries in self.servers.values_mut() {
+            if let Some(entry) = entries.get_mut(&file.id) {
+                entry.content = FileContent::Text(file.content.clone());
+            }
+        }
+    }
+
+    pub fn fill_media(&mut self, file: &MediaFile) {
+        for entries in self.servers.values_mut() {
+            if let Some(entry) = entries.get_mut(&file.id) {
+                entry.content = FileContent::Media(file.content.concat());
+            }
+        }
+    }
+
+    pub fn remove(&mut self, server: NodeId, id: &Uuid) {
+        if let Some(entries) = self.servers.get_mut(&server) {
+            entries.remove(id);
+        }
+    }
+
+    pub fn entries(&self, server: NodeId) -> Vec<&CatalogEntry> {
+        self.servers.get(&server).map(|e| e.values().collect()).unwrap_or_default()
+    }
+
+    pub fn get(&self, server: NodeId, id: &Uuid) -> Option<&CatalogEntry> {
+        self.servers.get(&server).and_then(|e| e.get(id))
+    }
+
+    /// Text files of every server, as `(server, entry)`.
+    pub fn text_files(&self) -> Vec<(NodeId, &CatalogEntry)> {
+        self.servers.iter()
+            .flat_map(|(server, entries)| entries.values().filter(|e| !e.is_media).map(move |e| (*server, e)))
+            .collect()
+    }
+
+    /// Server holding `id`, if any server lists it.
+    pub fn location_of(&self, id: &Uuid) -> Option<NodeId> {
+        self.servers.iter().find(|(_, entries)| entries.contains_key(id)).map(|(server, _)| *server)
+    }
+}
+
+thread_local! {
+    /// Images already decoded, by file, `None` for media that are not images.
+    static IMAGES: RefCell<HashMap<Uuid, Option<Image>>> = RefCell::new(HashMap::new());
+}
+
+/// Decodes media bytes as an image, going through the temp directory since Slint loads images from paths.
+///
+/// Each file is decoded once. The temp file is named after the UUID only, the title just gives the
+/// extension Slint guesses the format from.
+pub fn load_image(id: &Uuid, title: &str, bytes: &[u8]) -> Option<Image> {
+    IMAGES.with(|images| {
+        images.borrow_mut().entry(*id).or_insert_with(|| {
+            let extension = Path::new(title).extension()
+                .and_then(|e| e.to_str())
+                .filter(|e| !e.is_empty() && e.chars().all(|c| c.is_ascii_alphanumeric()))
+                .unwrap_or("bin");
+            let path = std::env::temp_dir().join(format!("simulation_controller_{id}.{extension}"));
+            std::fs::write(&path, bytes).ok()?;
+            let image = Image::load_from_path(&path).ok();
+            let _ = std::fs::remove_file(&path);
+            image
+        }).clone()
+    })
+}
+
+/// Redraws the file browser for the server and entry currently selected in it.
+pub fn refresh(main_window: &MainWindow, catalog: &FileCatalog) {
+    let Ok(server) = main_window.get_files_server().parse::<NodeId>() else {
+        return;
+    };
+
+    let entries = catalog.entries(server).into_iter().map(|e| FileEntry {
+        id: e.id.to_string().into(),
+        title: e.title.clone().into(),
+        kind: if e.is_media { "media".into() } else { "text".into() },
+        loaded: e.content != FileContent::Unknown,
+    }).collect::<Vec<_>>();
+    main_window.set_files_entries(Rc::new(VecModel::from(entries)).into());
+
+    let selected = main_window.get_files_selected().parse::<Uuid>().ok().and_then(|id| catalog.get(server, &id));
+
+    let (text, image) = match selected.map(|e| (e, &e.content)) {
+        None => (String::new(), None),
+        Some((_, FileContent::Unknown)) => ("Content not transferred yet, use Get File or Get Media File.".to_string(), None),
+        Some((_, FileContent::Text(content))) => (content.clone(), None),
+        Some((entry, FileContent::Media(bytes))) => match load_image(&entry.id, &entry.title, bytes) {
+            Some(image) => (format!("{} bytes", bytes.len()), Some(image)),
+            None => (format!("{} bytes, cannot be previewed as an image", bytes.len()), None),
+        },
+    };
+
+    main_window.set_files_preview_text(SharedString::from(text));
+    main_window.set_files_has_image(image.is_some());
+    main_window.set_files_preview_image(image.unwrap_or_default());
+}
@@ -0,0 +1,275 @@
+use std::collections::BTreeSet;
+
+use wg_internal::network::NodeId;
+
+use crate::topology::{ClientConfig, DroneConfig, ServerConfig, TopologyConfig};
+use crate::validation;
+
+/// Shape of the drone core, clients and servers are attached afterwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Ring,
+    /// Grid with the given number of columns, the last row may be incomplete.
+    Grid { cols: usize },
+    /// Erdős–Rényi G(n, p), extra links are added afterwards to keep the core connected.
+    ErdosRenyi { p: f64 },
+    /// Barabási–Albert preferential attachment, every new drone links to `m` existing ones.
+    BarabasiAlbert { m: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PdrDistribution {
+    Fixed(f32),
+    Uniform { min: f32, max: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorParams {
+    pub shape: Shape,
+    pub drones: usize,
+    pub clients: usize,
+    pub servers: usize,
+    pub pdr: PdrDistribution,
+    pub seed: u64,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        Self {
+            shape: Shape::Ring,
+            drones: 10,
+            clients: 2,
+            servers: 2,
+            pdr: PdrDistribution::Uniform { min: 0.0, max: 0.2 },
+            seed: 0,
+        }
+    }
+}
+
+impl Shape {
+    /// Parses the names accepted by the CLI and the UI: `ring`, `grid`, `er`, `ba`.
+    pub fn parse(name: &str, param: Option<f64>) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "ring" => Ok(Shape::Ring),
+            "grid" => Ok(Shape::Grid { cols: param.map(|c| c as usize).unwrap_or(4) }),
+            "er" | "erdos-renyi" => Ok(Shape::ErdosRenyi { p: param.unwrap_or(0.3) }),
+            "ba" | "barabasi-albert" => Ok(Shape::BarabasiAlbert { m: param.map(|m| m as usize).unwrap_or(2) }),
+            other => Err(format!("unknown shape '{other}', expected one of: ring, grid, er, ba")),
+        }
+    }
+}
+
+/// SplitMix64, small and stable across platforms and crate versions so a seed always gives the same network.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Generates a network from `params` and runs the pre-start validation on it.
+///
+/// Drones get the ids `1..=drones`, clients and servers follow in this order.
+///
+/// # Errors
+///
+/// Returns the list of problems if the parameters are out of range or the generated network
+/// does not pass `validation::validate_topology`.
+pub fn generate(params: &GeneratorParams) -> Result<TopologyConfig, Vec<String>> {
+    check_params(params)?;
+
+    let mut rng = Rng(params.seed);
+    let n = params.drones;
+    let mut links: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); n];
+
+    match params.shape {
+        Shape::Ring => {
+            for i in 0..n {
+                link(&mut links, i, (i + 1) % n);
+            }
+        }
+        Shape::Grid { cols } => {
+            for i in 0..n {
+                if (i + 1) % cols != 0 && i + 1 < n {
+                    link(&mut links, i, i + 1);
+                }
+                if i + cols < n {
+                    link(&mut links, i, i + cols);
+                }
+            }
+        }
+        Shape::ErdosRenyi { p } => {
+            for i in 0..n {
+                for j in (i + 1)..n {
+                    if rng.next_f64() < p {
+                        link(&mut links, i, j);
+                    }
+                }
+            }
+        }
+        Shape::BarabasiAlbert { m } => {
+            // start from a small clique so the first drones have a degree to attach to
+            let initial = (m + 1).min(n);
+            for i in 0..initial {
+                for j in (i + 1)..initial {
+                    link(&mut links, i, j);
+                }
+            }
+            for i in initial..n {
+                let mut targets = BTreeSet::new();
+                while targets.len() < m.min(i) {
+                    let total: usize = links[..i].iter().map(|l| l.len()).sum();
+                    let mut pick = rng.below(total.max(1));
+                    let mut target = 0;
+                    for (j, l) in links[..i].iter().enumerate() {
+                        if pick < l.len() {
+                            target = j;
+                            break;
+                        }
+                        pick -= l.len();
+                    }
+                    targets.insert(target);
+                }
+                for target in targets {
+                    link(&mut links, i, target);
+                }
+            }
+        }
+    }
+
+    // join the connected components of the core, in order of their lowest drone
+    let components = components(&links);
+    for pair in components.windows(2) {
+        let a = pair[0][rng.below(pair[0].len())];
+        let b = pair[1][rng.below(pair[1].len())];
+        link(&mut links, a, b);
+    }
+
+    let drone_id = |i: usize| (i + 1) as NodeId;
+    let mut config = TopologyConfig::default();
+
+    for i in 0..n {
+        config.drone.push(DroneConfig {
+            id: drone_id(i),
+            connected_node_ids: links[i].iter().map(|j| drone_id(*j)).collect(),
+            pdr: draw_pdr(&mut rng, params.pdr),
+        });
+    }
+
+    let mut next_id = n + 1;
+
+    for _ in 0..params.clients {
+        let id = next_id as NodeId;
+        next_id += 1;
+        let wanted = if n > 1 && rng.next_f64() < 0.5 { 2 } else { 1 };
+        let drones = pick_distinct(&mut rng, n, wanted);
+        attach(&mut config, id, &drones);
+        config.client.push(ClientConfig { id, connected_drone_ids: drones.iter().map(|i| drone_id(*i)).collect() });
+    }
+
+    for _ in 0..params.servers {
+        let id = next_id as NodeId;
+        next_id += 1;
+        let drones = pick_distinct(&mut rng, n, 2);
+        attach(&mut config, id, &drones);
+        config.server.push(ServerConfig { id, connected_drone_ids: drones.iter().map(|i| drone_id(*i)).collect() });
+    }
+
+    validation::validate_topology(&config)?;
+    Ok(config)
+}
+
+fn check_params(params: &GeneratorParams) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+
+    if params.drones < 2 {
+        errors.push("at least 2 drones are needed, servers must be attached to two of them".to_string());
+    }
+    if params.drones + params.clients + params.servers > NodeId::MAX as usize {
+        errors.push(format!("at most {} nodes fit in a NodeId", NodeId::MAX));
+    }
+    match params.shape {
+        Shape::Grid { cols } if cols == 0 => errors.push("grid needs at least one column".to_string()),
+        Shape::ErdosRenyi { p } if !(0.0..=1.0).contains(&p) => errors.push("Erdős–Rényi p must be between 0 and 1".to_string()),
+        Shape::BarabasiAlbert { m } if m == 0 => errors.push("Barabási–Albert m must be at least 1".to_string()),
+        _ => {}
+    }
+    match params.pdr {
+        PdrDistribution::Fixed(pdr) if !(0.0..=1.0).contains(&pdr) => errors.push("PDR must be between 0 and 1".to_string()),
+        PdrDistribution::Uniform { min, max } if !(0.0..=1.0).contains(&min) || !(0.0..=1.0).contains(&max) || min > max => {
+            errors.push("PDR range must satisfy 0 <= min <= max <= 1".to_string())
+        }
+        _ => {}
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn link(links: &mut [BTreeSet<usize>], a: usize, b: usize) {
+    if a != b {
+        links[a].insert(b);
+        links[b].insert(a);
+    }
+}
+
+fn draw_pdr(rng: &mut Rng, pdr: PdrDistribution) -> f32 {
+    let value = match pdr {
+        PdrDistribution::Fixed(pdr) => pdr,
+        PdrDistribution::Uniform { min, max } => min + (max - min) * rng.next_f64() as f32,
+    };
+    // two decimals, like the hand-written configs
+    (value * 100.).round() / 100.
+}
+
+fn pick_distinct(rng: &mut Rng, n: usize, count: usize) -> Vec<usize> {
+    let mut picked = BTreeSet::new();
+    while picked.len() < count.min(n) {
+        picked.insert(rng.below(n));
+    }
+    picked.into_iter().collect()
+}
+
+fn attach(config: &mut TopologyConfig, id: NodeId, drones: &[usize]) {
+    for i in drones {
+        config.drone[*i].connected_node_ids.push(id);
+    }
+}
+
+fn components(links: &[BTreeSet<usize>]) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; links.len()];
+    let mut components = Vec::new();
+
+    for start in 0..links.len() {
+        if seen[start] {
+            continue;
+        }
+        let mut component = Vec::new();
+        let mut stack = vec![start];
+        seen[start] = true;
+        while let Some(i) = stack.pop() {
+            component.push(i);
+            for &j in &links[i] {
+                if !seen[j] {
+                    seen[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+        components.push(component);
+    }
+
+    components
+}
@@ -1,4 +1,6 @@
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 use common::network::{Network, Node};
 use common::types::{Command, NodeType};
@@ -9,101 +11,232 @@ use wg_internal::network::NodeId;
 use wg_internal::controller::DroneCommand;
 
 use std::f64::consts::PI;
-use slint::{ComponentHandle, ModelRc, VecModel, SharedString};
+use slint::{ComponentHandle, Model, ModelRc, VecModel, SharedString};
 
 use wg_internal::packet::NodeType as WGNodeType;
 
 use crate::{MainWindow, Position, Edge};
 
-pub fn generate_graph(
-    main_window: &MainWindow,
-    network: &Network,
-    clients: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>,
-    servers: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>,
-    drones: &HashMap<NodeId, (f32, Sender<DroneCommand>)>,
-) {
+/// Layout of the network graph, kept between redraws so only what changed is touched.
+///
+/// Nodes sit on a circle in id order. Removing nodes leaves the others where they are, only a
+/// node that was not laid out yet, or changed kind, triggers a new layout. Edges are diffed against the model.
+pub struct GraphLayout {
+    positions: HashMap<NodeId, Position>,
+    /// Where the nodes removed since the last layout were drawn, crashed drones keep their spot in exports.
+    departed: HashMap<NodeId, Position>,
+    edge_keys: Vec<(NodeId, NodeId)>,
+    faulty: HashSet<(NodeId, NodeId)>,
+    crashed: HashSet<NodeId>,
+    pub nodes: Rc<VecModel<Position>>,
+    pub edges: Rc<VecModel<Edge>>,
+}
 
-    let mut graph: HashMap<(NodeId, String), HashSet<NodeId>> = HashMap::new();
+impl Default for GraphLayout {
+    fn default() -> Self {
+        Self {
+            positions: HashMap::new(),
+            departed: HashMap::new(),
+            edge_keys: Vec::new(),
+            faulty: HashSet::new(),
+            crashed: HashSet::new(),
+            nodes: Rc::new(VecModel::default()),
+            edges: Rc::new(VecModel::default()),
+        }
+    }
+}
 
-    for node1 in &network.nodes {
-        let node1_id = node1.get_id();
-        let node1_type = match node1.get_node_type() {
-            WGNodeType::Drone => "drone",
-            WGNodeType::Client => "client",
-            WGNodeType::Server => "server",
+impl GraphLayout {
+
+    /// Brings the models in line with `graph`, returns whether the nodes had to be laid out again.
+    pub fn update(&mut self, graph: &HashMap<(NodeId, String), HashSet<NodeId>>) -> bool {
+        let mut ids = graph.keys().map(|(id, kind)| (*id, kind.as_str())).collect::<Vec<_>>();
+        ids.sort();
+
+        let relayout = ids.iter().any(|(id, kind)| self.positions.get(id).map(|p| p.kind.as_str()) != Some(*kind));
+        if relayout {
+            self.layout(&ids);
+        } else if ids.len() != self.positions.len() {
+            let present = ids.iter().map(|(id, _)| *id).collect::<HashSet<_>>();
+            let gone = self.positions.keys().filter(|id| !present.contains(id)).copied().collect::<Vec<_>>();
+            for id in gone {
+                if let Some(position) = self.positions.remove(&id) {
+                    self.departed.insert(id, position);
+                }
+            }
+            for row in (0..self.nodes.row_count()).rev() {
+                if !self.nodes.row_data(row).is_some_and(|p| self.positions.contains_key(&(p.id as NodeId))) {
+                    self.nodes.remove(row);
+                }
+            }
         }
-        .to_string();
-
-        let key1 = (node1_id, node1_type.clone());
-        graph.entry(key1).or_insert_with(HashSet::new);
-
-        for &node2_id in node1.get_adjacents() {
-            graph.entry((node1_id, node1_type.clone()))
-                .or_insert_with(HashSet::new)
-                .insert(node2_id);
-
-            let node2_type = if drones.contains_key(&node2_id) {
-                "drone"
-            } else if clients.contains_key(&node2_id) {
-                "client"
-            } else if servers.contains_key(&node2_id) {
-                "server"
-            } else {
-                "unknown"
-            };
 
-            graph.entry((node2_id, node2_type.to_string()))
-                .or_insert_with(HashSet::new)
-                .insert(node1_id);
+        let mut keys = graph.iter()
+            .flat_map(|((a, _), adjacent)| adjacent.iter().map(move |b| (*a.min(b), *a.max(b))))
+            .filter(|(a, b)| a != b && self.positions.contains_key(a) && self.positions.contains_key(b))
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+
+        if relayout {
+            self.edge_keys.clear();
+            self.edges.set_vec(Vec::new());
         }
+        for row in (0..self.edge_keys.len()).rev() {
+            if keys.binary_search(&self.edge_keys[row]).is_err() {
+                self.edge_keys.remove(row);
+                self.edges.remove(row);
+            }
+        }
+        for key in keys {
+            if !self.edge_keys.contains(&key) {
+                self.edges.push(self.edge(key));
+                self.edge_keys.push(key);
+            }
+        }
+
+        relayout
     }
 
-    let n = graph.len() as f64;
+    pub fn positions(&self) -> &HashMap<NodeId, Position> {
+        &self.positions
+    }
 
-    if n == 0.0 {
-        return;
+    /// Positions of the nodes on the graph and of the ones removed since it was last laid out.
+    pub fn last_positions(&self) -> HashMap<NodeId, Position> {
+        let mut positions = self.departed.clone();
+        positions.extend(self.positions.iter().map(|(id, p)| (*id, p.clone())));
+        positions
     }
 
-    let d = ((624.0 * PI) / (n + 2.0 * PI)) / 1.5;
-    let layout_r = 324.0 - d;
+    fn layout(&mut self, ids: &[(NodeId, &str)]) {
+        self.positions.clear();
+        // every node moves, the old spots mean nothing any more
+        self.departed.clear();
 
-    let mut nodes = Vec::with_capacity(graph.len());
-    let mut positions: HashMap<NodeId, Position> = HashMap::new();
+        let n = ids.len() as f64;
+        if n == 0.0 {
+            self.nodes.set_vec(Vec::new());
+            return;
+        }
 
-    for (i, (node_id, node_type)) in graph.keys().enumerate() {
-        let theta = 2.0 * PI * (i as f64) / n;
-        let x = 324.0 + layout_r * theta.cos();
-        let y = 324.0 + layout_r * theta.sin();
+        let d = ((624.0 * PI) / (n + 2.0 * PI)) / 1.5;
+        let layout_r = 324.0 - d;
+
+        let mut nodes = Vec::with_capacity(ids.len());
+        for (i, (node_id, node_type)) in ids.iter().enumerate() {
+            let theta = 2.0 * PI * (i as f64) / n;
+            let x = 324.0 + layout_r * theta.cos();
+            let y = 324.0 + layout_r * theta.sin();
+
+            let position = Position {
+                id: *node_id as i32,
+                x: x as f32,
+                y: y as f32,
+                size: d as f32,
+                kind: SharedString::from(*node_type),
+                label: SharedString::from(node_id.to_string()),
+                highlighted: false,
+                crashed: self.crashed.contains(node_id),
+            };
 
-        let position = Position {
-            x: x as f32,
-            y: y as f32,
-            size: d as f32,
-            kind: SharedString::from(node_type.as_str()),
-            label: SharedString::from(node_id.to_string()),
-        };
+            self.positions.insert(*node_id, position.clone());
+            nodes.push(position);
+        }
 
-        positions.insert(*node_id, position.clone());
-        nodes.push(position);
+        self.nodes.set_vec(nodes);
     }
 
-    let mut edges = Vec::new();
-    for ((node1_id, _), adjs) in &graph {
-        if let Some(position1) = positions.get(node1_id) {
-            for node2_id in adjs {
-                if let Some(position2) = positions.get(node2_id) {
-                    edges.push(Edge {
-                        from_x: position1.x,
-                        from_y: position1.y,
-                        to_x: position2.x,
-                        to_y: position2.y,
-                        offset: 0.,
-                    });
-                }
+    /// Draws `ids` as crashed nodes, every other node as a running one.
+    fn mark_crashed(&mut self, ids: HashSet<NodeId>) {
+        self.crashed = ids;
+        for row in 0..self.nodes.row_count() {
+            let Some(mut position) = self.nodes.row_data(row) else {
+                continue;
+            };
+            let crashed = self.crashed.contains(&(position.id as NodeId));
+            if position.crashed != crashed {
+                position.crashed = crashed;
+                self.nodes.set_row_data(row, position);
             }
         }
     }
 
-    main_window.set_edges(ModelRc::new(VecModel::from(edges)));
-    main_window.set_nodes(ModelRc::new(VecModel::from(nodes)));
+    fn edge(&self, (a, b): (NodeId, NodeId)) -> Edge {
+        let (position1, position2) = (&self.positions[&a], &self.positions[&b]);
+        Edge {
+            from_x: position1.x,
+            from_y: position1.y,
+            to_x: position2.x,
+            to_y: position2.y,
+            offset: 0.,
+            faulty: self.faulty.contains(&(a, b)),
+        }
+    }
+}
+
+thread_local! {
+    static LAYOUT: RefCell<GraphLayout> = RefCell::new(GraphLayout::default());
+}
+
+/// Outlines `ids` on the graph view, every other node loses its outline.
+pub fn highlight(ids: &[NodeId]) {
+    LAYOUT.with(|layout| {
+        let layout = layout.borrow();
+        for row in 0..layout.nodes.row_count() {
+            let Some(mut position) = layout.nodes.row_data(row) else {
+                continue;
+            };
+            let highlighted = ids.contains(&(position.id as NodeId));
+            if position.highlighted != highlighted {
+                position.highlighted = highlighted;
+                layout.nodes.set_row_data(row, position);
+            }
+        }
+    });
+}
+
+/// Draws `links`, keyed by `(smaller id, larger id)`, as links with faults, every other link as a plain one.
+pub fn mark_faulty(links: HashSet<(NodeId, NodeId)>) {
+    LAYOUT.with(|layout| {
+        let mut layout = layout.borrow_mut();
+        layout.faulty = links;
+        for row in 0..layout.edge_keys.len() {
+            let faulty = layout.faulty.contains(&layout.edge_keys[row]);
+            if let Some(mut edge) = layout.edges.row_data(row).filter(|e| e.faulty != faulty) {
+                edge.faulty = faulty;
+                layout.edges.set_row_data(row, edge);
+            }
+        }
+    });
+}
+
+/// Where the graph view currently draws each node, or last drew it for crashed drones, for exports of what is on screen.
+pub fn current_positions() -> HashMap<NodeId, Position> {
+    LAYOUT.with(|layout| layout.borrow().last_positions())
+}
+
+pub fn generate_graph(
+    main_window: &MainWindow,
+    network: &Network,
+    clients: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>,
+    servers: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>,
+    drones: &HashMap<NodeId, (f32, Sender<DroneCommand>)>,
+) {
+    let graph = crate::utils::generate_generic_network_view(network, clients, servers, drones);
+    // a client or server still in the view without a command channel crashed and was not removed yet
+    let crashed = network.nodes.iter()
+        .map(|node| node.get_id())
+        .filter(|id| !clients.contains_key(id) && !servers.contains_key(id) && !drones.contains_key(id))
+        .collect::<HashSet<_>>();
+
+    LAYOUT.with(|layout| {
+        let mut layout = layout.borrow_mut();
+        layout.update(&graph);
+        layout.mark_crashed(crashed);
+
+        // same models every time, setting them again does not reset the view
+        main_window.set_edges(ModelRc::from(layout.edges.clone()));
+        main_window.set_nodes(ModelRc::from(layout.nodes.clone()));
+    });
 }
@@ -0,0 +1,122 @@
+use std::any::Any;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::Receiver;
+use crossbeam::select;
+use slint::Weak;
+
+use common::types::{ChatCommand, ChatEvent, Event, WebCommand, WebEvent};
+use wg_internal::controller::DroneEvent;
+use wg_internal::network::NodeId;
+
+use crate::SimulationController;
+
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A running network driven without any window, for tests.
+///
+/// Events are only pulled while waiting: every event received is applied to the controller
+/// exactly like the listener thread would, minus the UI, and shortcuts are still delivered.
+pub struct Harness {
+    pub sc: SimulationController,
+    node_events: Receiver<Box<dyn Event>>,
+    drone_events: Receiver<DroneEvent>,
+    output: PathBuf,
+}
+
+impl Harness {
+
+    /// Starts the network of the config at `path`, anything saved goes to a temporary folder.
+    pub fn start(path: &str) -> Self {
+        let mut sc = SimulationController::default();
+        let (seeds, node_events, drone_events) = sc.launch(path).unwrap_or_else(|errors| panic!("{path} cannot start: {errors:?}"));
+        let harness = Self::from_parts(sc, node_events, drone_events);
+
+        for seed in &seeds {
+            harness.sc.seed_from_directory(seed);
+        }
+        harness
+    }
+
+    /// Drives a controller whose nodes were started elsewhere, e.g. a `MockNetwork`.
+    pub fn from_parts(
+        sc: SimulationController,
+        node_events: Receiver<Box<dyn Event>>,
+        drone_events: Receiver<DroneEvent>,
+    ) -> Self {
+        let output = std::env::temp_dir().join(format!("simulation_controller_harness_{}", uuid::Uuid::new_v4()));
+        sc.output.lock().unwrap().set_base(&output);
+        Self { sc, node_events, drone_events, output }
+    }
+
+    pub fn web(&self, node: NodeId, command: WebCommand) {
+        let sender = self.sc.clients.get(&node).or_else(|| self.sc.servers.get(&node)).map(|(_, sender)| sender);
+        sender.expect("no such client or server").send(self.sc.tracked_web(node, command)).unwrap();
+    }
+
+    pub fn chat(&self, node: NodeId, command: ChatCommand) {
+        let sender = self.sc.clients.get(&node).or_else(|| self.sc.servers.get(&node)).map(|(_, sender)| sender);
+        sender.expect("no such client or server").send(self.sc.tracked_chat(node, command)).unwrap();
+    }
+
+    /// Processes events until `matches` returns something, `None` once `timeout` is over.
+    pub fn wait_for<R>(&mut self, timeout: Duration, matches: impl FnMut(&dyn Any) -> Option<R>) -> Option<R> {
+        self.pump(timeout, matches, |_| None)
+    }
+
+    pub fn wait_drone<R>(&mut self, timeout: Duration, matches: impl FnMut(&DroneEvent) -> Option<R>) -> Option<R> {
+        self.pump(timeout, |_| None, matches)
+    }
+
+    fn pump<R>(
+        &mut self,
+        timeout: Duration,
+        mut node_matches: impl FnMut(&dyn Any) -> Option<R>,
+        mut drone_matches: impl FnMut(&DroneEvent) -> Option<R>,
+    ) -> Option<R> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            let found = select! {
+                recv(self.node_events) -> msg => {
+                    let event = msg.ok()?.into_any();
+                    let found = node_matches(event.as_ref());
+                    self.process(event.as_ref());
+                    found
+                }
+                recv(self.drone_events) -> msg => {
+                    let event = msg.ok()?;
+                    let found = drone_matches(&event);
+                    SimulationController::process_drone_event(event, &self.sc.event_context());
+                    found
+                }
+                default(remaining) => return None,
+            };
+
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+
+    pub fn wait_web<R>(&mut self, timeout: Duration, mut matches: impl FnMut(&WebEvent) -> Option<R>) -> Option<R> {
+        self.wait_for(timeout, |event| event.downcast_ref::<WebEvent>().and_then(&mut matches))
+    }
+
+    pub fn wait_chat<R>(&mut self, timeout: Duration, mut matches: impl FnMut(&ChatEvent) -> Option<R>) -> Option<R> {
+        self.wait_for(timeout, |event| event.downcast_ref::<ChatEvent>().and_then(&mut matches))
+    }
+
+    fn process(&self, event: &dyn Any) {
+        SimulationController::process_node_event(event, Weak::default(), &self.sc.event_context());
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.sc.stop_simulation();
+        let _ = std::fs::remove_dir_all(&self.output);
+    }
+}
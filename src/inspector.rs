@@ -0,0 +1,220 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::time::Instant;
+
+use slint::{Color, VecModel};
+
+use common::types::NodeEvent;
+use wg_internal::controller::DroneEvent;
+
+use crate::capture::{self, Disposition, Filter, Payload, Record};
+use crate::observer::EventObserver;
+use crate::{EventContext, MainWindow, PacketRow};
+
+/// Most packets kept, the oldest ones go first.
+pub const CAPACITY: usize = 5000;
+/// Most rows shown at once, the newest matching packets.
+pub const ROWS: usize = 500;
+
+/// The last packets seen by the controller, decoded, for the packet inspector.
+#[derive(Debug)]
+pub struct Inspector {
+    start: Instant,
+    /// Packets with their id, ids keep growing so a selection survives old packets going away.
+    packets: VecDeque<(usize, Record)>,
+    next_id: usize,
+    filter: Filter,
+    changed: bool,
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self { start: Instant::now(), packets: VecDeque::new(), next_id: 0, filter: Filter::default(), changed: true }
+    }
+}
+
+impl Inspector {
+
+    /// Forgets every packet, times now count from here. The filter stays.
+    pub fn restart(&mut self) {
+        *self = Self { filter: std::mem::take(&mut self.filter), ..Self::default() };
+    }
+
+    pub fn record(&mut self, record: Record) {
+        if self.packets.len() == CAPACITY {
+            self.packets.pop_front();
+        }
+        self.packets.push_back((self.next_id, record));
+        self.next_id += 1;
+        self.changed = true;
+    }
+
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Record> {
+        let first = self.packets.front()?.0;
+        self.packets.get(id.checked_sub(first)?).map(|(_, record)| record)
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.changed = true;
+    }
+
+    /// Packets kept by the filter, oldest first.
+    pub fn matching(&self) -> impl DoubleEndedIterator<Item = &(usize, Record)> + '_ {
+        self.packets.iter().filter(|(_, record)| self.filter.matches(record))
+    }
+}
+
+/// Reads the filter fields of the panel, empty fields match everything.
+pub fn parse_filter(session: &str, packet_type: &str, source: &str, destination: &str, hop: &str, disposition: &str) -> Result<Filter, String> {
+    fn field<T: std::str::FromStr>(name: &str, value: &str) -> Result<Option<T>, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        value.parse().map(Some).map_err(|_| format!("invalid {name} '{value}'"))
+    }
+
+    let packet_type = packet_type.trim().to_lowercase();
+    let disposition = disposition.trim().to_lowercase();
+    Ok(Filter {
+        session: field("session", session)?,
+        packet_type: match packet_type.as_str() {
+            "" => None,
+            t if Payload::TYPES.contains(&t) => Some(packet_type.clone()),
+            t => return Err(format!("invalid type '{t}', expected {}", Payload::TYPES.join(", "))),
+        },
+        source: field("source", source)?,
+        destination: field("destination", destination)?,
+        hop: field("hop", hop)?,
+        disposition: match disposition.as_str() {
+            "" => None,
+            d => Some(Disposition::parse(d).ok_or_else(|| format!("invalid disposition '{d}', expected sent, dropped or shortcut"))?),
+        },
+    })
+}
+
+/// The route with the current hop in brackets.
+fn route(record: &Record) -> String {
+    record.hops.iter().enumerate()
+        .map(|(i, id)| if i == record.hop_index { format!("[{id}]") } else { id.to_string() })
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+fn fragment(record: &Record) -> String {
+    match &record.payload {
+        Payload::MsgFragment { fragment_index, total_n_fragments, .. } => format!("{fragment_index}/{total_n_fragments}"),
+        Payload::Ack { fragment_index } | Payload::Nack { fragment_index, .. } => fragment_index.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn color(disposition: Disposition) -> Color {
+    match disposition {
+        Disposition::Sent => Color::from_rgb_u8(123, 132, 150),
+        Disposition::Dropped => Color::from_rgb_u8(255, 94, 160),
+        Disposition::Shortcut => Color::from_rgb_u8(255, 189, 94),
+    }
+}
+
+/// Lines of 16 bytes, offset, hex and printable ASCII, like `hexdump -C`.
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (n, line) in bytes.chunks(16).enumerate() {
+        let hex = line.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" ");
+        let ascii = line.iter().map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '.' }).collect::<String>();
+        let _ = writeln!(out, "{:04x}  {hex:<47}  |{ascii}|", n * 16);
+    }
+    out
+}
+
+/// Every field of a packet, one per line.
+pub fn describe(id: usize, record: &Record) -> String {
+    let mut out = String::new();
+    let observer = record.observer.map(|id| format!(" by {id}")).unwrap_or_default();
+    let _ = writeln!(out, "Packet #{id}, {}{observer} at {:.3}s", record.disposition.name(), record.elapsed_ms as f64 / 1000.);
+    let _ = writeln!(out, "Type:        {}", record.payload.type_name());
+    let _ = writeln!(out, "Session:     {}", record.session_id);
+    let _ = writeln!(out, "Route:       {}", route(record));
+    let _ = writeln!(out, "Hop index:   {}", record.hop_index);
+
+    match &record.payload {
+        Payload::MsgFragment { fragment_index, total_n_fragments, length, data } => {
+            let _ = writeln!(out, "Fragment:    {fragment_index} of {total_n_fragments}");
+            let _ = writeln!(out, "Length:      {length} bytes\n");
+            let bytes = (0..data.len()).step_by(2).filter_map(|i| u8::from_str_radix(data.get(i..i + 2)?, 16).ok()).collect::<Vec<_>>();
+            out.push_str(&hex_dump(&bytes));
+        }
+        Payload::Ack { fragment_index } => {
+            let _ = writeln!(out, "Fragment:    {fragment_index}");
+        }
+        Payload::Nack { fragment_index, nack_type } => {
+            let _ = writeln!(out, "Fragment:    {fragment_index}");
+            let _ = writeln!(out, "Nack type:   {nack_type}");
+        }
+        Payload::FloodRequest { flood_id, initiator_id, path_trace } => {
+            let _ = writeln!(out, "Flood:       {flood_id}");
+            let _ = writeln!(out, "Initiator:   {initiator_id}");
+            let _ = writeln!(out, "Path trace:  {path_trace:?}");
+        }
+        Payload::FloodResponse { flood_id, path_trace } => {
+            let _ = writeln!(out, "Flood:       {flood_id}");
+            let _ = writeln!(out, "Path trace:  {path_trace:?}");
+        }
+    }
+    out
+}
+
+/// Keeps every packet sent by nodes and drones for the inspector, registered with the other built-in observers.
+pub struct InspectorObserver;
+
+impl EventObserver for InspectorObserver {
+    fn on_node_event(&mut self, event: &dyn Any, ctx: &EventContext) -> bool {
+        if let Some(NodeEvent::PacketSent(packet)) = event.downcast_ref::<NodeEvent>() {
+            let mut inspector = ctx.inspector.lock().unwrap();
+            let record = Record::new(packet, capture::sender(packet), Disposition::Sent, inspector.start());
+            inspector.record(record);
+        }
+        false
+    }
+
+    fn on_drone_event(&mut self, event: &DroneEvent, ctx: &EventContext) {
+        let (packet, disposition) = capture::packet_of(event);
+        let mut inspector = ctx.inspector.lock().unwrap();
+        let record = Record::new(packet, capture::reporting_drone(event), disposition, inspector.start());
+        inspector.record(record);
+    }
+}
+
+/// Redraws the packet table if a packet came in or the filter changed since the last time, newest first.
+pub fn refresh(main_window: &MainWindow, inspector: &mut Inspector) {
+    if !inspector.changed {
+        return;
+    }
+    inspector.changed = false;
+
+    let rows = inspector.matching().rev().take(ROWS)
+        .map(|(id, record)| PacketRow {
+            id: *id as i32,
+            time: format!("{:.3}", record.elapsed_ms as f64 / 1000.).into(),
+            kind: record.payload.type_name().into(),
+            session: record.session_id.to_string().into(),
+            fragment: fragment(record).into(),
+            route: route(record).into(),
+            hop: record.hop_index.to_string().into(),
+            disposition: record.disposition.name().into(),
+            color: color(record.disposition),
+        })
+        .collect::<Vec<_>>();
+    let matching = inspector.matching().count();
+
+    main_window.set_inspector_summary(format!("{matching} of {} packets, showing the newest {}", inspector.packets.len(), rows.len()).into());
+    main_window.set_inspector_packets(Rc::new(VecModel::from(rows)).into());
+}
@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
+
+use slint::Color;
+use uuid::Uuid;
+
+use wg_internal::network::NodeId;
+use wg_internal::packet::{Packet, PacketType};
+
+use crate::stats::Stats;
+use crate::utils;
+
+/// CRC-32 (IEEE 802.3), enough to catch fragments that were corrupted or reassembled in the wrong order.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    Verified,
+    Mismatch { expected: u32, actual: u32 },
+    /// No checksum was recorded for the file, it was never listed or added through the controller.
+    Unknown,
+}
+
+/// Fragments of one session sent by its source node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub source: NodeId,
+    pub destination: NodeId,
+    pub session_id: u64,
+    pub total_fragments: u64,
+    /// Times each fragment index was sent.
+    pub sent: BTreeMap<u64, u32>,
+    /// Fragment indexes the destination acknowledged.
+    acked: BTreeSet<u64>,
+    last_seen: u64,
+}
+
+impl Transfer {
+    pub fn is_complete(&self) -> bool {
+        self.acked.len() as u64 >= self.total_fragments
+    }
+
+    pub fn retransmitted(&self) -> u32 {
+        self.sent.values().map(|times| times - 1).sum()
+    }
+}
+
+impl fmt::Display for Transfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "session {} from {} to {}: {} fragment(s), {} retransmitted",
+            self.session_id, self.source, self.destination, self.total_fragments, self.retransmitted()
+        )
+    }
+}
+
+/// Transfers still in flight, the least recently active ones are dropped past this.
+pub const TRANSFERS: usize = 1000;
+/// Completed transfers kept for the report of the file they carried.
+pub const COMPLETED: usize = 64;
+
+/// Checksums of the files held by servers and fragments sent per session.
+///
+/// A checksum is known once a server lists a file with its content, or once a file the controller
+/// added from a path is confirmed by `TextFileAdded`/`MediaFileAdded` (the server picks the UUID, so
+/// the checksum waits under the id of the add request until the event that answers it).
+#[derive(Debug, Default)]
+pub struct IntegrityTracker {
+    checksums: HashMap<Uuid, (u32, NodeId)>,
+    /// Checksums of added files by request id, with the server they were added to.
+    pending_adds: HashMap<u64, (u32, NodeId)>,
+    transfers: HashMap<(NodeId, u64), Transfer>,
+    /// Transfers the destination acknowledged in full, oldest first.
+    completed: VecDeque<Transfer>,
+    seen: u64,
+}
+
+impl IntegrityTracker {
+
+    /// Records the content of a file the controller asked `server` to add with request `request`.
+    pub fn expect_added(&mut self, request: u64, server: NodeId, bytes: &[u8]) {
+        self.pending_adds.insert(request, (crc32(bytes), server));
+    }
+
+    /// `request` was answered with the UUID the server gave the file.
+    pub fn on_added(&mut self, request: u64, uuid: Uuid) {
+        if let Some(pending) = self.pending_adds.remove(&request) {
+            self.checksums.insert(uuid, pending);
+        }
+    }
+
+    /// `request` failed, no file was added.
+    pub fn on_add_failed(&mut self, request: u64) {
+        self.pending_adds.remove(&request);
+    }
+
+    pub fn record(&mut self, server: NodeId, uuid: Uuid, bytes: &[u8]) {
+        self.checksums.insert(uuid, (crc32(bytes), server));
+    }
+
+    pub fn forget(&mut self, uuid: &Uuid) {
+        self.checksums.remove(uuid);
+    }
+
+    pub fn verify(&self, uuid: &Uuid, bytes: &[u8]) -> Verification {
+        match self.checksums.get(uuid) {
+            None => Verification::Unknown,
+            Some((expected, _)) => {
+                let actual = crc32(bytes);
+                if actual == *expected {
+                    Verification::Verified
+                } else {
+                    Verification::Mismatch { expected: *expected, actual }
+                }
+            }
+        }
+    }
+
+    /// Server the recorded checksum of `uuid` comes from.
+    pub fn location(&self, uuid: &Uuid) -> Option<NodeId> {
+        self.checksums.get(uuid).map(|(_, server)| *server)
+    }
+
+    /// Tracks a fragment sent by a client or server, returns whether it was a retransmission.
+    ///
+    /// Acks sent back by the destination complete the transfer once every fragment is acknowledged.
+    pub fn on_packet_sent(&mut self, packet: &Packet) -> bool {
+        let (Some(first), Some(last)) = (packet.routing_header.hops.first(), packet.routing_header.hops.last()) else {
+            return false;
+        };
+        let fragment = match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => fragment,
+            // acks travel back, from the destination to the source
+            PacketType::Ack(ack) => {
+                self.on_ack(*last, packet.session_id, ack.fragment_index);
+                return false;
+            }
+            _ => return false,
+        };
+        let (source, destination) = (first, last);
+
+        self.seen += 1;
+        let transfer = self.transfers.entry((*source, packet.session_id)).or_insert_with(|| Transfer {
+            source: *source,
+            destination: *destination,
+            session_id: packet.session_id,
+            total_fragments: fragment.total_n_fragments,
+            sent: BTreeMap::new(),
+            acked: BTreeSet::new(),
+            last_seen: 0,
+        });
+        transfer.last_seen = self.seen;
+
+        let times = transfer.sent.entry(fragment.fragment_index).or_insert(0);
+        *times += 1;
+        let retransmitted = *times > 1;
+
+        if self.transfers.len() > TRANSFERS {
+            // sessions that never finish, e.g. towards a node that crashed
+            if let Some(key) = self.transfers.iter().min_by_key(|(_, t)| t.last_seen).map(|(key, _)| *key) {
+                self.transfers.remove(&key);
+            }
+        }
+        retransmitted
+    }
+
+    fn on_ack(&mut self, source: NodeId, session_id: u64, fragment_index: u64) {
+        let Some(transfer) = self.transfers.get_mut(&(source, session_id)) else {
+            return;
+        };
+        transfer.acked.insert(fragment_index);
+        if transfer.is_complete() {
+            if let Some(transfer) = self.transfers.remove(&(source, session_id)) {
+                self.completed.push_back(transfer);
+                if self.completed.len() > COMPLETED {
+                    self.completed.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Latest session from `source` to `destination`, the one that carried the file just received.
+    pub fn last_transfer(&self, source: NodeId, destination: NodeId) -> Option<&Transfer> {
+        self.transfers.values()
+            .chain(self.completed.iter())
+            .filter(|t| t.source == source && t.destination == destination)
+            .max_by_key(|t| t.last_seen)
+    }
+
+    /// Sessions still in flight.
+    pub fn in_flight(&self) -> usize {
+        self.transfers.len()
+    }
+}
+
+/// Verifies a file `client` received and logs the outcome with the report of the session that carried it.
+pub fn check_received(tracker: &IntegrityTracker, stats: &mut Stats, client: NodeId, uuid: &Uuid, title: &str, bytes: &[u8]) {
+    let transfer = tracker.location(uuid)
+        .and_then(|server| tracker.last_transfer(server, client))
+        .map(|t| format!(", {t}"))
+        .unwrap_or_default();
+
+    match tracker.verify(uuid, bytes) {
+        Verification::Verified => {
+            stats.files_verified += 1;
+            utils::log(&format!("FILE {title} ({uuid}) RECEIVED BY {client} IS INTACT{transfer}"), Color::from_rgb_u8(123, 132, 150));
+        }
+        Verification::Mismatch { expected, actual } => {
+            stats.integrity_errors += 1;
+            utils::log(
+                &format!("INTEGRITY ERROR: FILE {title} ({uuid}) RECEIVED BY {client} HAS CHECKSUM {actual:08x}, EXPECTED {expected:08x}{transfer}"),
+                Color::from_rgb_u8(255, 94, 160),
+            );
+        }
+        Verification::Unknown => stats.files_unverified += 1,
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use slint::{Model, VecModel};
+
+use crate::LogMessage;
+
+/// Most log lines kept in the view, the oldest ones go first.
+pub const CAPACITY: usize = 2000;
+
+/// How often pending lines are moved into the view, about two frames at 60 Hz.
+pub const FLUSH_INTERVAL: Duration = Duration::from_millis(33);
+
+/// Log lines waiting for the next flush, so a burst of packets costs one model update per frame.
+#[derive(Debug, Default)]
+pub struct LogBuffer {
+    pending: VecDeque<LogMessage>,
+    dropped: u64,
+}
+
+impl LogBuffer {
+
+    pub fn push(&mut self, entry: LogMessage) {
+        if self.pending.len() == CAPACITY {
+            // the view would drop it on the next flush anyway
+            self.pending.pop_front();
+            self.dropped += 1;
+        }
+        self.pending.push_back(entry);
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Lines that never reached the view because newer ones pushed them out.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Empties `model` and forgets the lines not flushed yet, they would bring the old log back.
+    pub fn clear(&mut self, model: &VecModel<LogMessage>) {
+        self.pending.clear();
+        model.clear();
+    }
+
+    /// Moves the pending lines into `model`, keeping at most `CAPACITY` rows. Returns the lines moved.
+    pub fn flush(&mut self, model: &VecModel<LogMessage>) -> usize {
+        let batch = self.pending.drain(..).collect::<Vec<_>>();
+        let moved = batch.len();
+        if moved == 0 {
+            return 0;
+        }
+
+        let total = model.row_count() + moved;
+        if total > CAPACITY {
+            let rows = model.iter().skip(total - CAPACITY).chain(batch).collect::<Vec<_>>();
+            model.set_vec(rows);
+        } else {
+            model.extend(batch);
+        }
+        moved
+    }
+}
@@ -3,21 +3,48 @@
 mod utils;
 mod graph_utils;
 mod validation;
+mod topology;
+mod generator;
+mod cli;
+mod editor;
+mod chat;
+mod file_browser;
+mod browser;
+mod output;
+mod integrity;
+mod stats;
+mod seeding;
+mod requests;
+mod logs;
+mod api;
+mod observer;
+mod metrics;
+mod export;
+mod timeline;
+mod capture;
+mod inspector;
+mod shortcut;
+mod faults;
 
 //mod graph_utils;
 mod test;
+#[cfg(test)]
+mod harness;
+#[cfg(test)]
+mod mock;
 use chrono::{Datelike, Local, Timelike};
 
-use common::file_conversion;
 use common::network::{Network, Node};
 use common::types::{ChatCommand, ChatEvent, Command, Event, MediaReference, Message, NodeCommand, NodeEvent, NodeType, TextFile, WebCommand, WebEvent};
 
 use crossbeam::channel::{Receiver, Sender};
 use crossbeam::select;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::cell::RefCell;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 
 use regex::Regex;
@@ -37,6 +64,33 @@ slint::include_modules!();
 
 //? SIMULATION CONTROLLER DEFINITION
 
+/// An event received by the listener thread, waiting for the next pass of the UI thread.
+enum PendingEvent {
+    Node(Box<dyn Event>),
+    Drone(DroneEvent),
+}
+
+/// What the event handlers and observers share with the controller, cloned into the listener thread.
+#[derive(Clone)]
+pub(crate) struct EventContext {
+    pub nodes: (Vec<(NodeId, String)>, Vec<(NodeId, String)>),
+    pub chats: Arc<Mutex<chat::ChatStore>>,
+    pub files: Arc<Mutex<file_browser::FileCatalog>>,
+    pub pages: Arc<Mutex<browser::BrowserStore>>,
+    pub output: Arc<Mutex<output::OutputSession>>,
+    pub stats: Arc<Mutex<stats::Stats>>,
+    pub integrity: Arc<Mutex<integrity::IntegrityTracker>>,
+    pub requests: Arc<Mutex<requests::RequestTracker>>,
+    pub metrics: Arc<Mutex<metrics::Metrics>>,
+    pub timeline: Arc<Mutex<timeline::Timeline>>,
+    pub inspector: Arc<Mutex<inspector::Inspector>>,
+    pub client_senders: HashMap<NodeId, Sender<Box<dyn Command>>>,
+    /// Clients and servers crashed when the context was taken, a `Shutdown` sent to them is their crash.
+    pub dead: BTreeSet<NodeId>,
+    pub shortcuts: Arc<Mutex<shortcut::ShortcutRouter>>,
+    pub observers: Arc<Mutex<observer::Observers>>,
+}
+
 #[derive(Default)]
 pub(crate) struct SimulationController {
     is_running: Arc<RwLock<bool>>,
@@ -45,15 +99,70 @@ pub(crate) struct SimulationController {
     servers: HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>,
     drones: HashMap<NodeId, (f32, Sender<DroneCommand>)>,
     network_initializer: Option<NetworkInitializer<Running>>,
+    packet_senders: HashMap<NodeId, Sender<Packet>>,
+    /// Drones crashed since the start, kept for exports once they left the network view.
+    crashed: HashSet<NodeId>,
+    /// Clients and servers crashed without notice, left in the network view until they are removed.
+    dead: BTreeMap<NodeId, NodeType>,
+    /// Links the controller interposes on, keyed by `(smaller id, larger id)`.
+    links: HashMap<(NodeId, NodeId), faults::FaultyLink>,
     listener: Option<std::thread::JoinHandle<()>>,
+    chats: Arc<Mutex<chat::ChatStore>>,
+    files: Arc<Mutex<file_browser::FileCatalog>>,
+    pages: Arc<Mutex<browser::BrowserStore>>,
+    output: Arc<Mutex<output::OutputSession>>,
+    stats: Arc<Mutex<stats::Stats>>,
+    integrity: Arc<Mutex<integrity::IntegrityTracker>>,
+    requests: Arc<Mutex<requests::RequestTracker>>,
+    metrics: Arc<Mutex<metrics::Metrics>>,
+    timeline: Arc<Mutex<timeline::Timeline>>,
+    inspector: Arc<Mutex<inspector::Inspector>>,
+    shortcuts: Arc<Mutex<shortcut::ShortcutRouter>>,
+    observers: Arc<Mutex<observer::Observers>>,
 }
 
 impl SimulationController {
 
-    fn start_simulation(&mut self, path: &str, ui_handle: Weak<MainWindow>) {
+    /// Starts the network of the config at `path` and listens to its events.
+    ///
+    /// A config that does not pass validation is not started, its problems go to the log.
+    fn start_simulation(&mut self, path: &str, ui_handle: Weak<MainWindow>) -> Result<(), String> {
         if *self.is_running.read().unwrap() {
-            return;
+            return Ok(());
         }
+
+        let (seeds, node_event_receiver, drone_event_receiver) = match self.launch(path) {
+            Ok(launched) => launched,
+            Err(errors) => {
+                for error in &errors {
+                    utils::log(&format!("{path}: {error}"), Color::from_rgb_u8(255, 94, 160));
+                }
+                return Err(format!("{path} has {} problem(s), the simulation was not started", errors.len()));
+            }
+        };
+
+        let is_running = self.is_running.clone();
+        let ctx = self.event_context();
+        self.listener = Some(std::thread::spawn(move || {
+            Self::listen_to_events(node_event_receiver, drone_event_receiver, is_running, ui_handle, ctx)
+        }));
+
+        for seed in &seeds {
+            self.seed_from_directory(seed);
+        }
+        Ok(())
+    }
+
+    /// Validates the config at `path` and starts its network, without listening to its events.
+    ///
+    /// Returns the seeds of the config and the event receivers,
+    /// which `start_simulation` hands to the listener thread and tests poll directly.
+    /// Nothing is started when the config cannot be read or does not pass validation, every problem is returned.
+    fn launch(&mut self, path: &str) -> Result<(Vec<seeding::SeedConfig>, Receiver<Box<dyn Event>>, Receiver<DroneEvent>), Vec<String>> {
+        let config = topology::TopologyConfig::from_file(path).map_err(|e| vec![e])?;
+        validation::validate_topology(&config)?;
+        let (seeds, links) = (config.seed, config.link);
+
         self.is_running = Arc::new(RwLock::new(true));
 
         let initializer = NetworkInitializer::<Uninitialized>::new(path)
@@ -67,25 +176,95 @@ impl SimulationController {
 
         let node_event_receiver = initializer.get_nodes_event_receiver();
         let drone_event_receiver = initializer.get_drones_event_receiver();
-        let is_running = self.is_running.clone();
-        let comms_channels = initializer.get_comms_channels();
-        let comms_channels: HashMap<NodeId, Sender<Packet>> = comms_channels 
+        self.packet_senders = initializer.get_comms_channels()
             .iter()
             .map(|(id, channel)| (*id, channel.get_sender()))
             .collect();
 
+        self.shortcuts.lock().unwrap().set_senders(self.packet_senders.clone());
         self.network_initializer = Some(initializer);
-        let nodes = self.get_nodes_with_type();
-        self.listener = Some(std::thread::spawn(move || {
-            Self::listen_to_events(
-                node_event_receiver,
-                drone_event_receiver,
-                is_running,
-                nodes,
-                ui_handle,
-                comms_channels
-            )
-        }));
+        for link in links {
+            let [a, b] = link.between;
+            if let Err(e) = self.set_link_fault(a, b, link.fault) {
+                utils::log(&format!("{path}: {e}"), Color::from_rgb_u8(255, 94, 160));
+            }
+        }
+        self.output.lock().unwrap().new_run();
+        self.timeline.lock().unwrap().restart();
+        self.inspector.lock().unwrap().restart();
+
+        Ok((seeds, node_event_receiver, drone_event_receiver))
+    }
+
+    fn event_context(&self) -> EventContext {
+        EventContext {
+            nodes: self.get_nodes_with_type(),
+            chats: self.chats.clone(),
+            files: self.files.clone(),
+            pages: self.pages.clone(),
+            output: self.output.clone(),
+            stats: self.stats.clone(),
+            integrity: self.integrity.clone(),
+            requests: self.requests.clone(),
+            metrics: self.metrics.clone(),
+            timeline: self.timeline.clone(),
+            inspector: self.inspector.clone(),
+            client_senders: self.client_senders(),
+            dead: self.dead.keys().copied().collect(),
+            shortcuts: self.shortcuts.clone(),
+            observers: self.observers.clone(),
+        }
+    }
+
+    /// Registers an observer told about every event and command from now on, after the built-in ones.
+    pub fn observe(&self, observer: Box<dyn observer::EventObserver>) {
+        self.observers.lock().unwrap().register(observer);
+    }
+
+    fn client_senders(&self) -> HashMap<NodeId, Sender<Box<dyn Command>>> {
+        self.clients.iter().map(|(id, (_, sender))| (*id, sender.clone())).collect()
+    }
+
+    /// Uploads the content of a seeded directory, media first so text files never reference missing media.
+    fn seed_from_directory(&self, seed: &seeding::SeedConfig) {
+        let plan = match seeding::plan(seed) {
+            Ok(plan) => plan,
+            Err(e) => {
+                utils::log(&format!("Cannot seed server {} from {}: {e}", seed.server, seed.dir), Color::from_rgb_u8(255, 94, 160));
+                return;
+            }
+        };
+
+        let (Some((_, text_sender)), Some((_, media_sender))) = (self.servers.get(&plan.text_server), self.servers.get(&plan.media_server)) else {
+            utils::log(&format!("Cannot seed from {}: server {} or {} is not running", seed.dir, plan.text_server, plan.media_server), Color::from_rgb_u8(255, 94, 160));
+            return;
+        };
+
+        {
+            // released before sending, observers notified of the commands may read it
+            let mut integrity = self.integrity.lock().unwrap();
+            for file in &plan.media_files {
+                integrity.record(plan.media_server, file.id, &file.content.concat());
+            }
+            for file in &plan.text_files {
+                integrity.record(plan.text_server, file.id, file.content.as_bytes());
+            }
+        }
+        let mut unsent = 0;
+        for file in &plan.media_files {
+            unsent += media_sender.send(self.tracked_web(plan.media_server, WebCommand::AddMediaFile(file.clone()))).is_err() as usize;
+        }
+        for file in &plan.text_files {
+            unsent += text_sender.send(self.tracked_web(plan.text_server, WebCommand::AddTextFile(file.clone()))).is_err() as usize;
+        }
+        if unsent > 0 {
+            utils::log(&format!("Cannot seed from {}: {unsent} file(s) could not be sent", seed.dir), Color::from_rgb_u8(255, 94, 160));
+        }
+
+        utils::log(
+            &format!("Seeded {} text file(s) on server {} and {} media file(s) on server {} from {}", plan.text_files.len(), plan.text_server, plan.media_files.len(), plan.media_server, seed.dir),
+            Color::from_rgb_u8(123, 132, 150),
+        );
     }
 
     fn stop_simulation(&mut self) {
@@ -101,10 +280,17 @@ impl SimulationController {
         if let Some(handle) = self.listener.take() {
             handle.join().expect("Failed to join listener thread");
         }
+        let ctx = self.event_context();
+        self.observers.lock().unwrap().stop(&ctx);
 
         self.clients.clear();
         self.servers.clear();
         self.drones.clear();
+        self.packet_senders.clear();
+        self.links.clear();
+        self.shortcuts.lock().unwrap().set_senders(HashMap::new());
+        self.crashed.clear();
+        self.dead.clear();
         self.network_initializer = None;
     }
 
@@ -112,10 +298,49 @@ impl SimulationController {
         nodes_event_receiver: Receiver<Box<dyn Event>>,
         drone_event_receiver: Receiver<DroneEvent>,
         is_running: Arc<RwLock<bool>>,
-        nodes: (Vec<(NodeId, String)>, Vec<(NodeId, String)>),
         ui_handle: Weak<MainWindow>,
-        comms_channels: HashMap<NodeId, Sender<Packet>>,
+        ctx: EventContext,
     ) {
+        let queue: Arc<Mutex<Vec<PendingEvent>>> = Arc::default();
+        let scheduled = Arc::new(AtomicBool::new(false));
+
+        // handles everything queued since the last pass, then refreshes the counters once
+        let flush = {
+            let queue = queue.clone();
+            let scheduled = scheduled.clone();
+            move || {
+                scheduled.store(false, Ordering::SeqCst);
+                let batch = std::mem::take(&mut *queue.lock().unwrap());
+                for pending in batch {
+                    match pending {
+                        PendingEvent::Node(event) => {
+                            let event = event.into_any();
+                            Self::process_node_event(event.as_ref(), ui_handle.clone(), &ctx);
+                        }
+                        PendingEvent::Drone(event) => Self::process_drone_event(event, &ctx),
+                    }
+                }
+
+                if let Some(mw) = ui_handle.upgrade() {
+                    stats::refresh(&mw, &ctx.stats.lock().unwrap());
+                    requests::refresh(&mw, &mut ctx.requests.lock().unwrap());
+                    timeline::refresh(&mw, &mut ctx.timeline.lock().unwrap());
+                    if mw.get_inspector_open() {
+                        inspector::refresh(&mw, &mut ctx.inspector.lock().unwrap());
+                    }
+                }
+            }
+        };
+
+        // only one pass is scheduled at a time, a burst of packets costs a single trip to the event loop
+        let enqueue = |pending: PendingEvent| {
+            queue.lock().unwrap().push(pending);
+            if !scheduled.swap(true, Ordering::SeqCst) {
+                let flush = flush.clone();
+                let _ = slint::invoke_from_event_loop(move || flush());
+            }
+        };
+
         loop {
             
             if !*is_running.read().unwrap() {
@@ -124,21 +349,18 @@ impl SimulationController {
             select! {
                 recv(nodes_event_receiver) -> msg => {
                     match msg {
-                        Ok(event) => {
-                            Self::handle_node_event(event, ui_handle.clone(), nodes.clone());
-                        }
-                        Err(e) => {
+                        Ok(event) => enqueue(PendingEvent::Node(event)),
+                        Err(_) => {
+                            utils::log("Clients and servers stopped sending events", Color::from_rgb_u8(123, 132, 150));
                             break;
                         }
                     }
                 }
                 recv(drone_event_receiver) -> msg => {
                     match msg {
-                        Ok(event) => {
-                            Self::handle_drone_event(event, comms_channels.clone());
-                        }
-                        Err(e) => {
-                            eprintln!("Error receiving drone event: {:?}", e);
+                        Ok(event) => enqueue(PendingEvent::Drone(event)),
+                        Err(_) => {
+                            utils::log("Drones stopped sending events", Color::from_rgb_u8(123, 132, 150));
                             break;
                         }
                     }
@@ -147,235 +369,202 @@ impl SimulationController {
         }
     }
 
-    fn handle_node_event(event: Box<dyn Event>, ui_handle: Weak<MainWindow>, nodes: (Vec<(NodeId, String)>, Vec<(NodeId, String)>)) {
-        slint::invoke_from_event_loop(move || {
-            let event = event.into_any();
-            if let Some(event) = event.downcast_ref::<WebEvent>() {
-                match event {
-                    WebEvent::CachedFiles {
-                        notification_from,
-                        files,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CACHED FILES RECEIVED: {} files", files.len()));
-                        file_conversion::save_files(notification_from, files);
-                    },
-                    WebEvent::File {
-                        notification_from,
-                        file,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE RECEIVED: {}", file.id.to_string()));
-                        file_conversion::save_file(notification_from, file);
-                    },
-                    WebEvent::TextFiles {
-                        notification_from,
-                        files,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILES RECEIVED: {} files", files.len()));
-                        file_conversion::save_text_files(notification_from, files);
-                    },
-                    WebEvent::TextFile {
-                        notification_from,
-                        file,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILE RECEIVED: {}", file.id.to_string()));
-                        file_conversion::save_text_file(notification_from, file);
-                    },
-                    WebEvent::MediaFiles {
-                        notification_from,
-                        files,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MEDIA FILES RECEIVED: {} files", files.len()));
-                        file_conversion::save_media_files(notification_from, files);
-                    },
-                    WebEvent::MediaFile {
-                        notification_from,
-                        file,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MEDIA FILE RECEIVED: {}", file.id.to_string()));
-                        file_conversion::save_media_file(notification_from, file);
-                    },
-                    WebEvent::FilesListQueried {
-                        notification_from,
-                        from,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILES LIST QUERIED FROM: {from}"));
-                    },
-                    WebEvent::FileNotFound {
-                        notification_from,
-                        uuid,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE NOT FOUND: {uuid}"));
-                    },
-                    WebEvent::TextFileAdded {
-                        notification_from,
-                        uuid,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILE ADDED: {uuid}"));
-                    },
-                    WebEvent::MediaFileAdded {
-                        notification_from,
-                        uuid,
-                    } =>{
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MEDIA FILE ADDED: {uuid}"));
-                    },
-                    
-                    WebEvent::TextFileRemoved {
-                        notification_from,
-                        uuid,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILE REMOVED: {uuid}"));
-                    },
-                    WebEvent::TextFileRemoved {
-                        notification_from,
-                        uuid,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILE REMOVED: {uuid}"));
-                    },
-                    WebEvent::MediaFileRemoved {
-                        notification_from,
-                        uuid,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MEDIA FILE REMOVED: {uuid}"));
-                    },
-                    
-                    WebEvent::FileOperationError {
-                        notification_from,
-                        msg,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE OPERATION ERROR: {msg}"));
-                    },
-                    WebEvent::FileRequested {
-                        notification_from,
-                        from,
-                        uuid,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE REQUESTED FROM: {from}, UUID: {uuid}"));
-                    },
-
-                    WebEvent::BadUuid {
-                        notification_from,
-                        from,
-                        uuid,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, BAD UUID FROM: {from}, UUID: {uuid}"));
-                    },
-                    WebEvent::FileServed {
-                        notification_from,
-                        file,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE SERVED: {file}"));
-                    },
-                    WebEvent::FilesLists { 
-                        notification_from, 
-                        files_map 
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILES LISTS (server_id, files_list): {:?}", files_map));
-                    }
-                
-                }
-            } else if let Some(event) = event.downcast_ref::<ChatEvent>() {
-                match event {
-                    ChatEvent::ChatHistory {
-                        notification_from,
-                        history,
-                    } => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CHAT HISTORY RECEIVED"));
-                        utils::save_chat_history(notification_from, history);
-                    },
-                    ChatEvent::RegisteredClients {
-                        notification_from,
-                        list,
-                    } => {
-                        utils::handle_registered_clients(notification_from, list, ui_handle, nodes.clone());
-                        // TODO to be tested
-                    },
-                    ChatEvent::MessageSent {
-                        notification_from,
-                        to,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MESSAGE SENT TO: {to}")),
-                    ChatEvent::MessageReceived {
-                        notification_from,
-                        msg,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, RECEIVED MESSAGE {:?}", msg)),
-                    ChatEvent::ClientRegistered {
-                        client,
-                        server
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {server}, REGISTERED CLIENT {client}")),
-                    ChatEvent::ClientListQueried {
-                        notification_from,
-                        from,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CLIENT LIST QUERIED BY {from}")),
-                    ChatEvent::ClientNotInList {
-                        notification_from,
-                        id,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CLIENT {id} NOT IN REGISTERED CLIENTS")),
-                    ChatEvent::ErrorClientNotFound {
-                        notification_from,
-                        not_found,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CLIENT {not_found} IS NOT REGISTERED IN SERVER")),
-                    ChatEvent::RegistrationSucceeded {
-                        notification_from,
-                        to,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, SUCCESSFULLY REGISTERED TO SERVER {to}")),
-                }
-            } else if let Some(event) = event.downcast_ref::<NodeEvent>() {
-                match event {
-                    NodeEvent::PacketSent(packet) => {
-                        utils::log(&format!("PACKET SENT: {}", packet), Color::from_rgb_u8( 123, 132, 150));
-                    },
-                    NodeEvent::FloodStarted(flood_counter,node_id) => {
-                        utils::log_default(&format!("NOTIFICATION FROM: {}, FLOOD STARTED {} FLOOD", node_id, flood_counter));
-                    },
-                    NodeEvent::NodeRemoved(node_id) => {
-                        utils::log_default(&format!("REMOVED {} NODE", node_id));
-                    },
-                    NodeEvent::MessageReceived {
-                        notification_from,
-                        from,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MESSAGE RECEIVED FROM: {from}")),
-                    NodeEvent::MessageSent {
-                        notification_from,
-                        to,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MESSAGE SENT TO: {to}")),
-                    NodeEvent::ServerTypeQueried {
-                        notification_from,
-                        from,
-                    } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, SERVER TYPE QUERIED FROM: {from}")),
-                }
-            }
-        });
+    /// Applies a node event to the controller state, and to the UI when `ui_handle` is still alive,
+    /// then hands it to the observers.
+    fn process_node_event(event: &dyn std::any::Any, ui_handle: Weak<MainWindow>, ctx: &EventContext) {
+        Self::apply_node_event(event, ui_handle, ctx);
+        ctx.observers.lock().unwrap().node_event(event, ctx);
     }
 
-    fn handle_drone_event(event: DroneEvent, comms_channels: HashMap<NodeId, Sender<Packet>>) {
-        slint::invoke_from_event_loop(move || {
-            match event { 
-                DroneEvent::PacketSent(packet) => {
-                    if packet.routing_header.len() > 0 {
-                        if packet.routing_header.hop_index > 0 {
-                            if let Some(hop) = packet.routing_header.previous_hop() {
-                                utils::log(&format!("DRONE {} - PACKET SENT: {}", hop, packet), Color::from_rgb_u8(128, 95, 47));
+    fn apply_node_event(event: &dyn std::any::Any, ui_handle: Weak<MainWindow>, ctx: &EventContext) {
+        // sent once the locks below are released, observers notified of them may take those locks
+        let mut media_commands = Vec::new();
+        let mut stats = ctx.stats.lock().unwrap();
+        let mut integrity = ctx.integrity.lock().unwrap();
+        let mut requests = ctx.requests.lock().unwrap();
+        let is_server = |id: &NodeId| ctx.nodes.1.iter().any(|(server, _)| server == id);
+        if let Some(event) = event.downcast_ref::<WebEvent>() {
+            let mut catalog = ctx.files.lock().unwrap();
+            let mut pages = ctx.pages.lock().unwrap();
+            let answered = requests.on_web_event(event);
+            match event {
+                WebEvent::File {
+                    notification_from,
+                    file,
+                } => {
+                    catalog.fill_text(&file.text_file);
+                    for media in &file.media_files {
+                        catalog.fill_media(media);
+                    }
+
+                    integrity::check_received(&integrity, &mut stats, *notification_from, &file.text_file.id, &file.text_file.title, file.text_file.content.as_bytes());
+                    for media in &file.media_files {
+                        integrity::check_received(&integrity, &mut stats, *notification_from, &media.id, &media.title, &media.content.concat());
+                    }
+                },
+                WebEvent::TextFiles {
+                    notification_from,
+                    files,
+                } => {
+                    catalog.add_text_files(*notification_from, files);
+                    if is_server(notification_from) {
+                        for file in files {
+                            integrity.record(*notification_from, file.id, file.content.as_bytes());
+                        }
+                    }
+                },
+                WebEvent::TextFile {
+                    notification_from,
+                    file,
+                } => {
+                    catalog.fill_text(file);
+                    if is_server(notification_from) {
+                        integrity.record(*notification_from, file.id, file.content.as_bytes());
+                    } else {
+                        integrity::check_received(&integrity, &mut stats, *notification_from, &file.id, &file.title, file.content.as_bytes());
+                    }
+
+                    // the open page needs its media, fetched from the server each reference points to
+                    let media_requests = pages.on_text_file(*notification_from, file);
+                    if ctx.client_senders.contains_key(notification_from) {
+                        for (media_id, location) in media_requests {
+                            let command = WebCommand::GetMediaFile { media_id, location };
+                            if let Some(kind) = requests::RequestKind::of_web(&command) {
+                                requests.issue(*notification_from, kind);
                             }
-                        } else {
-                            utils::log(&format!("DRONE {} - PACKET SENT: {}", packet.routing_header.hops[0], packet), Color::from_rgb_u8(128, 95, 47));
+                            media_commands.push((*notification_from, command));
+                        }
+                    }
+                },
+                WebEvent::MediaFiles {
+                    notification_from,
+                    files,
+                } => {
+                    catalog.add_media_files(*notification_from, files);
+                    if is_server(notification_from) {
+                        for file in files {
+                            integrity.record(*notification_from, file.id, &file.content.concat());
                         }
+                    }
+                },
+                WebEvent::MediaFile {
+                    notification_from,
+                    file,
+                } => {
+                    catalog.fill_media(file);
+                    if is_server(notification_from) {
+                        integrity.record(*notification_from, file.id, &file.content.concat());
                     } else {
-                        utils::log(&format!("DRONE - PACKET SENT: {}", packet), Color::from_rgb_u8(128, 95, 47));
+                        integrity::check_received(&integrity, &mut stats, *notification_from, &file.id, &file.title, &file.content.concat());
+                    }
+                    pages.on_media_file(*notification_from, file);
+                },
+                WebEvent::FileNotFound {
+                    notification_from,
+                    uuid,
+                } => {
+                    pages.on_failure(*notification_from, uuid, "file not found");
+                },
+                WebEvent::TextFileAdded {
+                    notification_from,
+                    uuid,
+                } => {
+                    catalog.add_listed(*notification_from, *uuid, "", false);
+                    if let Some(request) = answered {
+                        integrity.on_added(request, *uuid);
                     }
                 },
-                DroneEvent::ControllerShortcut(packet) => {
-                    if let Some(rec) = packet.routing_header.destination() {
-                        if let Some(sender) = comms_channels.get(&rec) {
-                            let _ = sender.send(packet.clone());
+                WebEvent::MediaFileAdded {
+                    notification_from,
+                    uuid,
+                } =>{
+                    catalog.add_listed(*notification_from, *uuid, "", true);
+                    if let Some(request) = answered {
+                        integrity.on_added(request, *uuid);
+                    }
+                },
+                WebEvent::TextFileRemoved {
+                    notification_from,
+                    uuid,
+                } | WebEvent::MediaFileRemoved {
+                    notification_from,
+                    uuid,
+                } => {
+                    catalog.remove(*notification_from, uuid);
+                    integrity.forget(uuid);
+                },
+                WebEvent::FileOperationError { .. } => {
+                    if let Some(request) = answered {
+                        integrity.on_add_failed(request);
+                    }
+                },
+                WebEvent::FilesLists { 
+                    notification_from, 
+                    files_map 
+                } => {
+                    for (server, list) in files_map {
+                        for (uuid, title) in list {
+                            catalog.add_listed(*server, *uuid, title, false);
                         }
                     }
                 },
-                DroneEvent::PacketDropped(packet) => {
-                    let index = packet.routing_header.hop_index;
-                    utils::log(&format!("DRONE {} - PACKET DROPPED: {}", packet.routing_header.hops[index], packet), Color::from_rgb_u8(128, 95, 47));
-                }
-                
+                _ => {},
             }
-        }).expect("Failed to invoke from event loop");
+
+            if let Some(mw) = ui_handle.upgrade() {
+                file_browser::refresh(&mw, &catalog);
+                browser::refresh(&mw, &pages, &catalog);
+            }
+        } else if let Some(event) = event.downcast_ref::<ChatEvent>() {
+            requests.on_chat_event(event);
+            match event {
+                ChatEvent::RegisteredClients {
+                    notification_from,
+                    list,
+                } => {
+                    utils::handle_registered_clients(notification_from, list, ui_handle, ctx.nodes.clone());
+                    // TODO to be tested
+                },
+                ChatEvent::MessageSent {
+                    notification_from,
+                    to,
+                } => {
+                    let mut chats = ctx.chats.lock().unwrap();
+                    chats.on_sent(*notification_from, *to);
+                    if let Some(mw) = ui_handle.upgrade() {
+                        chat::refresh(&mw, &chats);
+                    }
+                },
+                ChatEvent::MessageReceived {
+                    notification_from,
+                    msg,
+                } => {
+                    let mut chats = ctx.chats.lock().unwrap();
+                    chats.on_received(msg);
+                    if let Some(mw) = ui_handle.upgrade() {
+                        chat::refresh(&mw, &chats);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        drop((stats, integrity, requests));
+        for (node, command) in media_commands {
+            ctx.observers.lock().unwrap().command(node, &command, ctx);
+            if ctx.client_senders[&node].send(Box::new(command)).is_err() {
+                utils::log(&format!("Client {node} is not listening, its page misses a media"), Color::from_rgb_u8(255, 94, 160));
+            }
+        }
+    }
+
+    /// Delivers the packets drones hand to the controller as the shortcut policy says, then hands the event to the observers.
+    fn process_drone_event(event: DroneEvent, ctx: &EventContext) {
+        if let DroneEvent::ControllerShortcut(packet) = &event {
+            let outcome = ctx.shortcuts.lock().unwrap().route(packet);
+            shortcut::log(packet, &outcome);
+        }
+        ctx.observers.lock().unwrap().drone_event(&event, ctx);
     }
 
     fn get_drones_pdr(&self) -> Vec<(NodeId, f32)> {
@@ -385,6 +574,307 @@ impl SimulationController {
         sorted
     }
 
+    /// Records a web command as a pending request of `node`, ready to be sent.
+    fn tracked_web(&self, node: NodeId, command: WebCommand) -> Box<dyn Command> {
+        if let Some(kind) = requests::RequestKind::of_web(&command) {
+            self.requests.lock().unwrap().issue(node, kind);
+        }
+        let ctx = self.event_context();
+        self.observers.lock().unwrap().command(node, &command, &ctx);
+        Box::new(command)
+    }
+
+    /// Like `tracked_web` for a file added to a server, the checksum of `bytes` waits for the answer.
+    fn tracked_add(&self, server: NodeId, command: WebCommand, bytes: &[u8]) -> Box<dyn Command> {
+        if let Some(kind) = requests::RequestKind::of_web(&command) {
+            let request = self.requests.lock().unwrap().issue(server, kind);
+            self.integrity.lock().unwrap().expect_added(request, server, bytes);
+        }
+        let ctx = self.event_context();
+        self.observers.lock().unwrap().command(server, &command, &ctx);
+        Box::new(command)
+    }
+
+    fn tracked_chat(&self, node: NodeId, command: ChatCommand) -> Box<dyn Command> {
+        if let Some(kind) = requests::RequestKind::of_chat(&command) {
+            self.requests.lock().unwrap().issue(node, kind);
+        }
+        let ctx = self.event_context();
+        self.observers.lock().unwrap().command(node, &command, &ctx);
+        Box::new(command)
+    }
+
+    /// Asks a web server for the files it holds, the answer fills the file browser.
+    fn request_server_files(&self, server: NodeId) {
+        if let Some((node_type, sender)) = self.servers.get(&server) {
+            let command = if node_type.to_string() == "Media-Server" { WebCommand::GetMediaFiles } else { WebCommand::GetTextFiles };
+            if sender.send(self.tracked_web(server, command)).is_err() {
+                utils::log(&format!("Server {server} is not listening, its files cannot be listed"), Color::from_rgb_u8(255, 94, 160));
+            }
+        }
+    }
+
+    /// The running network, crashed clients and servers are left out.
+    fn generic_graph(&self) -> HashMap<(NodeId, String), HashSet<NodeId>> {
+        let mut graph = utils::generate_generic_network_view(&self.network_view, &self.clients, &self.servers, &self.drones);
+        graph.retain(|(id, _), _| !self.dead.contains_key(id));
+        for adjacent in graph.values_mut() {
+            adjacent.retain(|id| !self.dead.contains_key(id));
+        }
+        graph
+    }
+
+    fn sender_of(&self, node_id: NodeId) -> Result<Sender<Packet>, String> {
+        self.packet_senders.get(&node_id).cloned().ok_or_else(|| format!("Node {node_id} is not running"))
+    }
+
+    /// The channel `from` sends to `to` through, the faulty link between them if there is one.
+    fn link_sender(&self, from: NodeId, to: NodeId) -> Result<Sender<Packet>, String> {
+        match self.links.get(&faults::key(from, to)).and_then(|link| link.sender_to(to)) {
+            Some(sender) => Ok(sender),
+            None => self.sender_of(to),
+        }
+    }
+
+    /// Gives `node` a new channel to `other`, replacing the one it had.
+    fn hand_sender(&self, node: NodeId, other: NodeId, sender: Sender<Packet>) {
+        if self.drones.contains_key(&node) {
+            self.command_drone(node, DroneCommand::AddSender(other, sender));
+        } else {
+            self.command_node(node, NodeCommand::AddSender(other, sender));
+        }
+    }
+
+    fn link_faults(&self) -> BTreeMap<(NodeId, NodeId), faults::LinkFault> {
+        self.links.iter().map(|(key, link)| (*key, link.fault())).collect()
+    }
+
+    /// Adds faults to the link between `a` and `b` by handing both ends a channel through the controller,
+    /// a clear `fault` gives them their direct channels back.
+    fn set_link_fault(&mut self, a: NodeId, b: NodeId, fault: faults::LinkFault) -> Result<(), String> {
+        fault.check()?;
+        let (to_a, to_b) = (self.sender_of(a)?, self.sender_of(b)?);
+        if !self.generic_graph().iter().any(|((id, _), adjacent)| *id == a && adjacent.contains(&b)) {
+            return Err(format!("Nodes {a} and {b} are not linked"));
+        }
+
+        let key = faults::key(a, b);
+        if fault.is_clear() {
+            if self.links.remove(&key).is_some() {
+                self.hand_sender(a, b, to_b);
+                self.hand_sender(b, a, to_a);
+            }
+        } else if let Some(link) = self.links.get(&key) {
+            link.set_fault(fault);
+        } else {
+            let link = faults::FaultyLink::new(a, to_a, b, to_b, fault);
+            for (node, other) in [(a, b), (b, a)] {
+                if let Some(sender) = link.sender_to(other) {
+                    self.hand_sender(node, other, sender);
+                }
+            }
+            self.links.insert(key, link);
+        }
+
+        utils::log(&format!("LINK {} - {}: {fault}", key.0, key.1), Color::from_rgb_u8(123, 132, 150));
+        Ok(())
+    }
+
+    /// Tells every neighbour of `node_id` to stop sending to it.
+    fn detach(&self, node_id: NodeId, kind: &str) {
+        if let Some(nodes) = self.generic_graph().get(&(node_id, kind.to_string())) {
+            for node in nodes.iter() {
+                if self.drones.contains_key(node) {
+                    self.command_drone(*node, DroneCommand::RemoveSender(node_id));
+                } else {
+                    self.command_node(*node, NodeCommand::RemoveSender(node_id));
+                }
+            }
+        }
+    }
+
+    /// Sends `command` to drone `node`, telling the observers.
+    fn command_drone(&self, node: NodeId, command: DroneCommand) {
+        if let Some((_, sender)) = self.drones.get(&node) {
+            let ctx = self.event_context();
+            self.observers.lock().unwrap().command(node, &command, &ctx);
+            if sender.send(command).is_err() {
+                utils::log(&format!("Drone {node} is not listening"), Color::from_rgb_u8(255, 94, 160));
+            }
+        }
+    }
+
+    /// Sends `command` to client or server `node`, telling the observers.
+    fn command_node(&self, node: NodeId, command: NodeCommand) {
+        if let Some((_, sender)) = self.clients.get(&node).or_else(|| self.servers.get(&node)) {
+            if !self.send_node_command(node, sender, command) {
+                utils::log(&format!("Node {node} is not listening"), Color::from_rgb_u8(255, 94, 160));
+            }
+        }
+    }
+
+    /// Tells the observers about `command`, then sends it to `node`. Returns whether `node` was listening.
+    fn send_node_command(&self, node: NodeId, sender: &Sender<Box<dyn Command>>, command: NodeCommand) -> bool {
+        let ctx = self.event_context();
+        self.observers.lock().unwrap().command(node, &command, &ctx);
+        sender.send(Box::new(command)).is_ok()
+    }
+
+    /// Connects `node_id` to `args_node_id`, only drones can be added to clients and servers.
+    fn add_sender(&mut self, node_id: NodeId, args_node_id: NodeId) -> Result<(), String> {
+        if node_id == args_node_id {
+            return Err(format!("Cannot add node {node_id} as a sender of itself"));
+        }
+        let sender2 = self.link_sender(node_id, args_node_id)?;
+
+        if self.drones.contains_key(&node_id) {
+            self.command_drone(node_id, DroneCommand::AddSender(args_node_id, sender2));
+        } else if self.clients.contains_key(&node_id) {
+            if !validation::is_a_drone(&self.generic_graph(), node_id, args_node_id) {
+                return Err(format!("Cannot add sender {args_node_id} to client {node_id}: only drones can be added to clients"));
+            }
+            self.command_node(node_id, NodeCommand::AddSender(args_node_id, sender2));
+        } else if self.servers.contains_key(&node_id) {
+            if !validation::is_a_drone(&self.generic_graph(), node_id, args_node_id) {
+                return Err(format!("Cannot add sender {args_node_id} to server {node_id}: only drones can be added to servers"));
+            }
+            self.command_node(node_id, NodeCommand::AddSender(args_node_id, sender2));
+        } else {
+            return Err(format!("Node {node_id} is not running"));
+        }
+
+        utils::add_edge(node_id, args_node_id, self);
+        Ok(())
+    }
+
+    fn remove_sender(&mut self, node_id: NodeId, args_node_id: NodeId) -> Result<(), String> {
+        let generic_graph = self.generic_graph();
+
+        if self.drones.contains_key(&node_id) {
+            if !validation::can_remove_sender_drone(&generic_graph, node_id, args_node_id, &self.servers) {
+                return Err(format!("Cannot remove sender {args_node_id} from drone {node_id}: a server would be left with less than 2 drones, a client with none, or the network would be split"));
+            }
+            self.command_drone(node_id, DroneCommand::RemoveSender(args_node_id));
+        } else if self.clients.contains_key(&node_id) {
+            if !validation::can_remove_sender_client(&generic_graph, node_id, args_node_id, &self.clients) {
+                return Err(format!("Cannot remove sender {args_node_id} from client {node_id}: this client is attached to only 1 drone or the network would be split"));
+            }
+            self.command_node(node_id, NodeCommand::RemoveSender(args_node_id));
+        } else if self.servers.contains_key(&node_id) {
+            if !validation::can_remove_sender_server(&generic_graph, node_id, args_node_id, &self.servers) {
+                return Err(format!("Cannot remove sender {args_node_id} from server {node_id}: this server is attached to only 2 drones, the sender is not connected with the server or the network would be split"));
+            }
+            self.command_node(node_id, NodeCommand::RemoveSender(args_node_id));
+        } else {
+            return Err(format!("Node {node_id} is not running"));
+        }
+
+        // the faulty link goes with the edge, its threads stop when it is dropped
+        self.links.remove(&faults::key(node_id, args_node_id));
+        utils::remove_edge(node_id, args_node_id, self);
+        Ok(())
+    }
+
+    /// Detaches a client or a server from its neighbours, then shuts it down.
+    fn shutdown(&mut self, node_id: NodeId) -> Result<(), String> {
+        if self.clients.contains_key(&node_id) {
+            self.detach(node_id, "client");
+            self.command_node(node_id, NodeCommand::Shutdown);
+        } else if self.servers.contains_key(&node_id) {
+            self.detach(node_id, "server");
+            self.command_node(node_id, NodeCommand::Shutdown);
+        } else {
+            return Err(format!("Node {node_id} is not a running client or server"));
+        }
+        self.shortcuts.lock().unwrap().remove(node_id);
+        self.links.retain(|(a, b), _| *a != node_id && *b != node_id);
+
+        utils::remove_node(node_id, self);
+        Ok(())
+    }
+
+    /// Detaches a drone from its neighbours, then crashes it.
+    ///
+    /// Clients and servers crash without notice, see `fail`.
+    fn crash(&mut self, node_id: NodeId) -> Result<(), String> {
+        if let Some((node_type, sender)) = self.clients.remove(&node_id).or_else(|| self.servers.remove(&node_id)) {
+            self.fail(node_id, node_type, sender);
+            return Ok(());
+        }
+        if !self.drones.contains_key(&node_id) {
+            return Err(format!("Node {node_id} is not running"));
+        }
+        if !validation::can_remove_drone(&self.generic_graph(), node_id, &self.servers) {
+            return Err(format!("Cannot remove drone {node_id}: each server must keep at least two drones, each client one, and the network must stay connected"));
+        }
+
+        self.detach(node_id, "drone");
+        self.command_drone(node_id, DroneCommand::Crash);
+        self.crashed.insert(node_id);
+        self.shortcuts.lock().unwrap().remove(node_id);
+        self.links.retain(|(a, b), _| *a != node_id && *b != node_id);
+
+        utils::remove_node(node_id, self);
+        Ok(())
+    }
+
+    /// Stops client or server `node_id` and drops every channel the controller had to it.
+    ///
+    /// Unlike `shutdown` its neighbours are not told: they keep sending to it and find out from the errors.
+    /// The node stays in the network view, drawn as crashed, until `remove_dead` takes it out.
+    fn fail(&mut self, node_id: NodeId, node_type: NodeType, sender: Sender<Box<dyn Command>>) {
+        // marked first, so the observers of the `Shutdown` below see a crash
+        self.dead.insert(node_id, node_type);
+        // the node stops reading its packets, the command channel goes with `sender`
+        if !self.send_node_command(node_id, &sender, NodeCommand::Shutdown) {
+            utils::log(&format!("Node {node_id} was not listening, it is marked as crashed anyway"), Color::from_rgb_u8(255, 94, 160));
+        }
+        self.packet_senders.remove(&node_id);
+        self.shortcuts.lock().unwrap().remove(node_id);
+        self.links.retain(|(a, b), _| *a != node_id && *b != node_id);
+
+        utils::log(&format!("NODE {node_id} CRASHED: its neighbours were not told"), Color::from_rgb_u8(255, 94, 160));
+    }
+
+    /// Takes a crashed client or server out of the network, its neighbours stop sending to it.
+    fn remove_dead(&mut self, node_id: NodeId) -> Result<(), String> {
+        if self.dead.remove(&node_id).is_none() {
+            return Err(format!("Node {node_id} did not crash"));
+        }
+
+        let neighbours = self.network_view.nodes.iter()
+            .find(|n| n.get_id() == node_id)
+            .map(|n| n.get_adjacents().iter().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        for node in neighbours {
+            if self.drones.contains_key(&node) {
+                self.command_drone(node, DroneCommand::RemoveSender(node_id));
+            } else {
+                self.command_node(node, NodeCommand::RemoveSender(node_id));
+            }
+        }
+
+        utils::remove_node(node_id, self);
+        utils::log(&format!("Node {node_id} removed"), Color::from_rgb_u8(123, 132, 150));
+        Ok(())
+    }
+
+    /// Sets the PDR of a drone, `pdr` is a probability in `[0, 1]`.
+    fn set_packet_drop_rate(&mut self, node_id: NodeId, pdr: f32) -> Result<(), String> {
+        if pdr < 0. || pdr > 1. {
+            return Err("Packet Drop Rate must be between 0 and 100".to_string());
+        }
+        if !self.drones.contains_key(&node_id) {
+            return Err(format!("Node {node_id} is not a running drone"));
+        }
+
+        self.command_drone(node_id, DroneCommand::SetPacketDropRate(pdr));
+        if let Some((value, _)) = self.drones.get_mut(&node_id) {
+            *value = pdr;
+        }
+        Ok(())
+    }
+
     fn get_nodes_with_type(&self) -> (Vec<(NodeId, String)>, Vec<(NodeId, String)>){
         let mut unsorted = self.clients
             .iter()
@@ -418,16 +908,77 @@ impl SimulationController {
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
 
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
+    let shortcut_policy = match cli::shortcut_policy(&args) {
+        Some(Ok(policy)) => policy,
+        Some(Err(e)) => cli::exit_with_usage(&e),
+        None => shortcut::Policy::default(),
+    };
+
     let main_window = MainWindow::new()?;
 
+    // Log
+    let logs_model: Rc<VecModel<LogMessage>> = Rc::new(VecModel::from(Vec::<LogMessage>::new()));
+    main_window.set_logs(logs_model.clone().into());
+
+    // lines logged from any thread wait in the buffer, the timer moves them into the view once per frame
+    let log_buffer = Arc::new(Mutex::new(logs::LogBuffer::default()));
+    {
+        let log_buffer = log_buffer.clone();
+        utils::set_logger(Box::new(move |entry: LogMessage| {
+            log_buffer.lock().unwrap().push(entry);
+        }));
+    }
+
+    {
+        let log_buffer = log_buffer.clone();
+        main_window.on_add_log(move |entry: LogMessage| {
+            log_buffer.lock().unwrap().push(entry);
+        });
+    }
+
+    let logs_timer = slint::Timer::default();
+    {
+        let log_buffer = log_buffer.clone();
+        let logs_model = logs_model.clone();
+        logs_timer.start(slint::TimerMode::Repeated, logs::FLUSH_INTERVAL, move || {
+            log_buffer.lock().unwrap().flush(&logs_model);
+        });
+    }
+
+    {
+        let logs_model = logs_model.clone();
+        main_window.on_clear_logs(move || {
+            log_buffer.lock().unwrap().clear(&logs_model);
+        });
+    }
+
     let mut simulation_controller = SimulationController::default();
+    if let Some(dir) = cli::output_dir(&args) {
+        simulation_controller.output.lock().unwrap().set_base(dir);
+    }
+    main_window.set_output_dir(simulation_controller.output.lock().unwrap().base().display().to_string().into());
+    simulation_controller.shortcuts.lock().unwrap().policy = shortcut_policy;
+    if let Some(file) = cli::capture_file(&args) {
+        match capture::CaptureWriter::create(&file) {
+            Ok(writer) => simulation_controller.observe(Box::new(writer)),
+            Err(e) => eprintln!("cannot capture into {file}: {e}"),
+        }
+    }
 
-    simulation_controller.start_simulation(
+    if let Err(e) = simulation_controller.start_simulation(
         "./config/star.toml",
         main_window.as_weak(),
-    );
+    ) {
+        utils::log(&e, Color::from_rgb_u8(255, 94, 160));
+    }
 
     graph_utils::generate_graph(&main_window, &simulation_controller.network_view, &simulation_controller.clients, &simulation_controller.servers, &simulation_controller.drones);
+    graph_utils::mark_faulty(simulation_controller.link_faults().keys().copied().collect());
     
     // Drones
     let mut drones = simulation_controller
@@ -448,39 +999,111 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let servers = Rc::new(VecModel::from(servers.iter().map(|(node_id, node_type)| Server { title: format!("Server {node_id}").into(), subtitle: node_type.into(), id: node_id.to_string().into(), kind: node_type.into() }).collect::<Vec<_>>()));
     main_window.set_servers(servers.clone().into());
 
-    // Log
-    let logs_model: Rc<VecModel<LogMessage>> = Rc::new(VecModel::from(Vec::<LogMessage>::new()));
-    main_window.set_logs(logs_model.clone().into());
+    let simulation_controller = Arc::new(Mutex::new(simulation_controller));
+
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let ui_handle = main_window.as_weak();
+        main_window.on_timeline_open(move || {
+            if let Some(mw) = ui_handle.upgrade() {
+                timeline::refresh(&mw, &mut sc.lock().unwrap().timeline.lock().unwrap());
+            }
+        });
+    }
 
     {
+        let sc = Arc::clone(&simulation_controller);
+        let ui_handle = main_window.as_weak();
         let logs_model = logs_model.clone();
-        main_window.on_add_log(move |entry: LogMessage| {
-            logs_model.push(entry);
+        main_window.on_timeline_select(move |id| {
+            let Some(mw) = ui_handle.upgrade() else {
+                return;
+            };
+            let sc = sc.lock().unwrap();
+            let timeline = sc.timeline.lock().unwrap();
+            let Some(moment) = timeline.moment(id as u64) else {
+                return;
+            };
+
+            graph_utils::highlight(&moment.nodes);
+            match timeline::Timeline::log_row(moment, &logs_model) {
+                Some(row) => {
+                    mw.set_log_highlight(row as i32);
+                    mw.invoke_scroll_logs_to(row as i32);
+                }
+                None => mw.set_log_highlight(-1),
+            }
         });
     }
 
     {
-        let mw_weak = main_window.as_weak();
-        utils::set_logger(Box::new(move |entry: LogMessage| {
-            let mw_weak = mw_weak.clone();
-            let _ = slint::invoke_from_event_loop(move || {
-                if let Some(mw) = mw_weak.upgrade() {
-                    mw.invoke_add_log(entry);
+        let sc = Arc::clone(&simulation_controller);
+        let ui_handle = main_window.as_weak();
+        main_window.on_inspector_filter(move |session, packet_type, source, destination, hop, disposition| {
+            let Some(mw) = ui_handle.upgrade() else {
+                return;
+            };
+            match inspector::parse_filter(&session, &packet_type, &source, &destination, &hop, &disposition) {
+                Ok(filter) => {
+                    let inspector = sc.lock().unwrap().inspector.clone();
+                    let mut inspector = inspector.lock().unwrap();
+                    inspector.set_filter(filter);
+                    inspector::refresh(&mw, &mut inspector);
                 }
-            });
-        }));
+                Err(e) => mw.set_inspector_summary(e.into()),
+            }
+        });
     }
 
     {
-        let logs_model = logs_model.clone();
-        main_window.on_clear_logs(move || {
-            logs_model.clear();
+        let sc = Arc::clone(&simulation_controller);
+        let ui_handle = main_window.as_weak();
+        main_window.on_inspector_select(move |id| {
+            let Some(mw) = ui_handle.upgrade() else {
+                return;
+            };
+            let inspector = sc.lock().unwrap().inspector.clone();
+            let inspector = inspector.lock().unwrap();
+            let detail = inspector.get(id as usize)
+                .map(|record| inspector::describe(id as usize, record))
+                .unwrap_or_else(|| "This packet is no longer kept.".to_string());
+            mw.set_inspector_detail(detail.into());
         });
     }
 
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let ui_handle = main_window.as_weak();
+        main_window.on_inspector_refresh(move || {
+            if let Some(mw) = ui_handle.upgrade() {
+                let inspector = sc.lock().unwrap().inspector.clone();
+                inspector::refresh(&mw, &mut inspector.lock().unwrap());
+            }
+        });
+    }
 
+    if let Some(port) = cli::api_port(&args) {
+        let sc = Arc::clone(&simulation_controller);
+        let main_window_weak = main_window.as_weak();
+        // commands come from the API threads, the window is redrawn from its own
+        let on_change: api::OnChange = Arc::new(move || {
+            let sc = sc.clone();
+            let main_window_weak = main_window_weak.clone();
+            let _ = slint::invoke_from_event_loop(move || {
+                if let Some(mw) = main_window_weak.upgrade() {
+                    let sc = sc.lock().unwrap();
+                    utils::draw_menu(&mw, &sc);
+                    graph_utils::generate_graph(&mw, &sc.network_view, &sc.clients, &sc.servers, &sc.drones);
+                    graph_utils::mark_faulty(sc.link_faults().keys().copied().collect());
+                }
+            });
+        });
 
-    let simulation_controller = Arc::new(Mutex::new(simulation_controller));
+        match api::serve(port, Arc::clone(&simulation_controller), on_change) {
+            Ok(server) => utils::log(format!("Control API listening on http://{}", server.addr()), Color::from_rgb_u8(123, 132, 150)),
+            Err(e) => utils::log(format!("Cannot start the control API on port {port}: {e}"), Color::from_rgb_u8(255, 94, 160)),
+        }
+    }
 
     {
         let sc = Arc::clone(&simulation_controller);
@@ -507,63 +1130,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             };
 
-            let sender2 = {
-                let sc = sc.lock().unwrap();
-                sc.network_initializer
-                    .as_ref()
-                    .unwrap()
-                    .get_comms_channels()
-                    .get(&args_node_id)
-                    .unwrap()
-                    .get_sender()
-                    .clone()
-            };
+            let mut sc = sc.lock().unwrap();
+            if let Err(e) = sc.add_sender(node_id, args_node_id) {
+                utils::log(&e, Color::from_rgb_u8(255, 94, 160));
+                return;
+            }
 
-            {
-                let sc = sc.lock().unwrap();
-                let generic_graph = utils::generate_generic_network_view(
+            if let Some(mw) = main_window_weak.upgrade() {
+                graph_utils::generate_graph(
+                    &mw,
                     &sc.network_view,
                     &sc.clients,
                     &sc.servers,
                     &sc.drones,
                 );
-                match node_type {
-                    SimulationControllerType::Drone => {
-                        let sender1 = &sc.drones.get(&node_id).unwrap().1;
-                        sender1.send(DroneCommand::AddSender(args_node_id.clone(), sender2.clone()));
-                    }
-                    SimulationControllerType::ChatClient | SimulationControllerType::WebBrowser => {
-                        let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                        sender1.send(Box::new(NodeCommand::AddSender(args_node_id.clone(), sender2.clone())));
-                    }
-                    SimulationControllerType::ChatServer | SimulationControllerType::WebServer => {
-                        if !validation::is_a_drone(&generic_graph,node_id, args_node_id) {
-                            utils::log(&format!("Cannot add sender {args_node_id} to server {node_id}: only drones can be added to servers"), Color::from_rgb_u8(255, 94, 160));
-                            return;
-                        }
-                        println!("adding sender {} to server {}", args_node_id, node_id);
-                        let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                        sender1.send(Box::new(NodeCommand::AddSender(args_node_id.clone(), sender2.clone())));
-                    }
-                }
             }
-
-            {
-                let mut sc = sc.lock().unwrap();
-                utils::add_edge(node_id, args_node_id, &mut sc);
-
-                if let Some(mw) = main_window_weak.upgrade() {
-
-                    graph_utils::generate_graph(
-                        &mw,
-                        &sc.network_view,
-                        &sc.clients,
-                        &sc.servers,
-                        &sc.drones,
-                    );
-                }
-            }
-
         });
     }
 
@@ -593,55 +1174,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             };
 
-            {
-                let sc = sc.lock().unwrap();
-                let generic_graph = utils::generate_generic_network_view(
-                &sc.network_view,
+            let mut sc = sc.lock().unwrap();
+            if let Err(e) = sc.remove_sender(node_id, args_node_id) {
+                utils::log(&e, Color::from_rgb_u8(255, 94, 160));
+                return;
+            }
+
+            if let Some(mw) = main_window_weak.upgrade() {
+                graph_utils::generate_graph(
+                    &mw,
+                    &sc.network_view,
                     &sc.clients,
                     &sc.servers,
                     &sc.drones,
                 );
-                match node_type {
-                    SimulationControllerType::Drone => {
-                        if !validation::can_remove_sender_drone(&generic_graph, node_id, args_node_id, &sc.servers) {
-                            utils::log(&format!("Cannot remove sender {args_node_id} from drone {node_id}: this server is attached to only 2 drones"), Color::from_rgb_u8(255, 94, 160));
-                            return;
-                        }
-                        let sender1 = &sc.drones.get(&node_id).unwrap().1;
-                        sender1.send(DroneCommand::RemoveSender(args_node_id));
-                    }
-                    SimulationControllerType::ChatClient | SimulationControllerType::WebBrowser => {
-                        if !validation::can_remove_sender_client(&generic_graph, node_id, args_node_id, &sc.clients) {
-                            utils::log(&format!("Cannot remove sender {args_node_id} from client {node_id}: this client is attached to only 1 drone"), Color::from_rgb_u8(255, 94, 160));
-                            return;
-                        }
-                        let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                        sender1.send(Box::new(NodeCommand::RemoveSender(args_node_id.clone())));
-                    }
-                    SimulationControllerType::ChatServer | SimulationControllerType::WebServer => {
-                        if !validation::can_remove_sender_server(&generic_graph, node_id, args_node_id, &sc.servers) {
-                            utils::log(&format!("Cannot remove sender {args_node_id} from server {node_id}: this server is attached to only 2 drones or the sender is not connected with the server"), Color::from_rgb_u8(255, 94, 160));
-                            return;
-                        }
-                        let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                        sender1.send(Box::new(NodeCommand::RemoveSender(args_node_id.clone())));
-                    }
-                }
-            }
-
-            {
-                let mut sc = sc.lock().unwrap();
-                utils::remove_edge(node_id, args_node_id, &mut sc);
-
-                if let Some(mw) = main_window_weak.upgrade() {
-                    graph_utils::generate_graph(
-                        &mw,
-                        &sc.network_view,
-                        &sc.clients,
-                        &sc.servers,
-                        &sc.drones,
-                    );
-                }
+                graph_utils::mark_faulty(sc.link_faults().keys().copied().collect());
             }
         });
     }
@@ -663,84 +1210,63 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             };
 
-            {
-                let sc = sc.lock().unwrap();
-                let generic_graph = utils::generate_generic_network_view(
-            &sc.network_view,
+            let mut sc = sc.lock().unwrap();
+            if let Err(e) = sc.shutdown(node_id) {
+                utils::log(&e, Color::from_rgb_u8(255, 94, 160));
+                return;
+            }
+
+            if let Some(mw) = main_window_weak.upgrade() {
+
+                utils::draw_menu(&mw, &sc);
+                
+                graph_utils::generate_graph(
+                    &mw,
+                    &sc.network_view,
                     &sc.clients,
                     &sc.servers,
                     &sc.drones,
                 );
-                
-                match node_type {
-                    SimulationControllerType::ChatClient | SimulationControllerType::WebBrowser => {
-                        let sender1 = &sc.clients.get(&node_id).unwrap().1;
-
-                        if let Some(nodes) = generic_graph.get(&(node_id, "client".to_string())) {
-
-                            for node in nodes.iter() {
-
-                                if sc.drones.contains_key(node) {
-                                    let sender2 = &sc.drones.get(&node).unwrap().1;
-                                    sender2.send(DroneCommand::RemoveSender(node_id));
-                                }
-                                else if sc.clients.contains_key(node) {
-                                    let sender2 = &sc.clients.get(node).unwrap().1;
-                                    sender2.send(Box::new(NodeCommand::RemoveSender(node_id)));
-                                }
-                                else if sc.servers.contains_key(node) {
-                                    let sender2 = &sc.servers.get(node).unwrap().1;
-                                    sender2.send(Box::new(NodeCommand::RemoveSender(node_id)));
-                                }
-                            }
-                        }
-
-                        sender1.send(Box::new(NodeCommand::Shutdown));
-                    }
-                    SimulationControllerType::ChatServer | SimulationControllerType::WebServer => {
-                        let sender1 = &sc.servers.get(&node_id).unwrap().1;
-
-                        if let Some(nodes) = generic_graph.get(&(node_id, "server".to_string())) {
-
-                            for node in nodes.iter() {
+            }
+        });
+    }
 
-                                if sc.drones.contains_key(node) {
-                                    let sender2 = &sc.drones.get(&node).unwrap().1;
-                                    sender2.send(DroneCommand::RemoveSender(node_id));
-                                }
-                                else if sc.clients.contains_key(node) {
-                                    let sender2 = &sc.clients.get(node).unwrap().1;
-                                    sender2.send(Box::new(NodeCommand::RemoveSender(node_id)));
-                                }
-                                else if sc.servers.contains_key(node) {
-                                    let sender2 = &sc.servers.get(node).unwrap().1;
-                                    sender2.send(Box::new(NodeCommand::RemoveSender(node_id)));
-                                }
-                            }
-                        }
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let main_window_weak = main_window.as_weak();
+      
+        main_window.on_crash(move |node_command: SimulationControllerCommand,
+                                node_type: SimulationControllerType,
+                                node_id: SharedString| {
+            println!("crash {:?}", node_id);
 
-                        sender1.send(Box::new(NodeCommand::Shutdown));
-                    }
-                    _ => {}
+            let node_id = match node_id.parse::<NodeId>() {
+                Ok(id) => id,
+                Err(_) => {
+                    utils::log(&format!("Invalid node ID: {}, valid range:0-256", node_id), Color::from_rgb_u8(255, 94, 160));
+                    return;
                 }
-            }
+            };
 
-            {
-                let mut sc = sc.lock().unwrap();
-                utils::remove_node(node_id, &mut sc);
+            let mut sc = sc.lock().unwrap();
+            if let Err(e) = sc.crash(node_id) {
+                utils::log(&e, Color::from_rgb_u8(255, 94, 160));
+                return;
+            }
 
-                if let Some(mw) = main_window_weak.upgrade() {
+            if let Some(mw) = main_window_weak.upgrade() {
 
-                    utils::draw_menu(&mw, &sc);
-                    
-                    graph_utils::generate_graph(
-                        &mw,
-                        &sc.network_view,
-                        &sc.clients,
-                        &sc.servers,
-                        &sc.drones,
-                    );
-                }
+                utils::draw_menu(&mw, &sc);
+                
+                graph_utils::generate_graph(
+                    &mw,
+                    &sc.network_view,
+                    &sc.clients,
+                    &sc.servers,
+                    &sc.drones,
+                );
+                // links to a crashed client or server are plain again
+                graph_utils::mark_faulty(sc.link_faults().keys().copied().collect());
             }
         });
     }
@@ -749,11 +1275,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let sc = Arc::clone(&simulation_controller);
         let main_window_weak = main_window.as_weak();
       
-        main_window.on_crash(move |node_command: SimulationControllerCommand,
-                                node_type: SimulationControllerType,
+        main_window.on_remove(move |_node_command: SimulationControllerCommand,
+                                _node_type: SimulationControllerType,
                                 node_id: SharedString| {
-            println!("crash {:?}", node_id);
-
             let node_id = match node_id.parse::<NodeId>() {
                 Ok(id) => id,
                 Err(_) => {
@@ -762,63 +1286,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             };
 
-            {
-                let sc = sc.lock().unwrap();
-                let generic_graph = utils::generate_generic_network_view(
-            &sc.network_view,
+            let mut sc = sc.lock().unwrap();
+            if let Err(e) = sc.remove_dead(node_id) {
+                utils::log(&e, Color::from_rgb_u8(255, 94, 160));
+                return;
+            }
+
+            if let Some(mw) = main_window_weak.upgrade() {
+
+                utils::draw_menu(&mw, &sc);
+                
+                graph_utils::generate_graph(
+                    &mw,
+                    &sc.network_view,
                     &sc.clients,
                     &sc.servers,
                     &sc.drones,
                 );
-                match node_type {
-                    SimulationControllerType::Drone => {
-                        if !validation::can_remove_drone(&generic_graph, node_id, &sc.servers) {
-                            utils::log(&format!("Cannot remove drone {node_id}: each server must have at least two drones"), Color::from_rgb_u8(255, 94, 160));
-                            return;
-                        }
-                        let sender1 = &sc.drones.get(&node_id).unwrap().1;
-
-                        if let Some(nodes) = generic_graph.get(&(node_id, "drone".to_string())) {
-
-                            for node in nodes.iter() {
-
-                                if sc.drones.contains_key(node) {
-                                    let sender2 = &sc.drones.get(&node).unwrap().1;
-                                    sender2.send(DroneCommand::RemoveSender(node_id));
-                                }
-                                else if sc.clients.contains_key(node) {
-                                    let sender2 = &sc.clients.get(node).unwrap().1;
-                                    sender2.send(Box::new(NodeCommand::RemoveSender(node_id)));
-                                }
-                                else if sc.servers.contains_key(node) {
-                                    let sender2 = &sc.servers.get(node).unwrap().1;
-                                    sender2.send(Box::new(NodeCommand::RemoveSender(node_id)));
-                                }
-                            }
-                        }
-
-                        sender1.send(DroneCommand::Crash);
-                    }
-                    _ => {}
-                }
-            }
-
-            {
-                let mut sc = sc.lock().unwrap();
-                utils::remove_node(node_id, &mut sc);
-
-                if let Some(mw) = main_window_weak.upgrade() {
-
-                    utils::draw_menu(&mw, &sc);
-                    
-                    graph_utils::generate_graph(
-                        &mw,
-                        &sc.network_view,
-                        &sc.clients,
-                        &sc.servers,
-                        &sc.drones,
-                    );
-                }
             }
         });
     }
@@ -843,32 +1327,38 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 };
                 let args_pdr = args.pdr.parse::<f32>().unwrap() / 100.;
 
-                {
-                    if args_pdr < 0. || args_pdr > 1. {
-                        utils::log(&format!("Packet Drop Rate must be between 0 and 100"), Color::from_rgb_u8(255, 94, 160));
-                        return;
-                    }
-                    let sc = sc.lock().unwrap();
-                    match node_type {
-                        SimulationControllerType::Drone => {
-                            let sender1 = &sc.drones.get(&node_id).unwrap().1;
-                            sender1.send(DroneCommand::SetPacketDropRate(args_pdr.clone()));
-                        }
-                        _ => {}
-                    }
+                let mut sc = sc.lock().unwrap();
+                if let Err(e) = sc.set_packet_drop_rate(node_id, args_pdr) {
+                    utils::log(&e, Color::from_rgb_u8(255, 94, 160));
+                    return;
                 }
 
-                {
-                    let mut sc = sc.lock().unwrap();
+                if let Some(mw) = main_window_weak.upgrade() {
 
-                    if let Some((value, _)) = sc.drones.get_mut(&node_id) {
-                        *value = args_pdr;
-                    }
+                    utils::draw_menu(&mw, &sc);
+                }
+            },
+        );
+    }
 
-                    if let Some(mw) = main_window_weak.upgrade() {
+    {
+        let sc = Arc::clone(&simulation_controller);
 
-                        utils::draw_menu(&mw, &sc);
-                    }
+        main_window.on_set_link_faults(
+            move |node_command: SimulationControllerCommand,
+                node_type: SimulationControllerType,
+                node_id: SharedString,
+                args: SetLinkFaults| {
+                let (Ok(node_id), Ok(neighbour)) = (node_id.parse::<NodeId>(), args.neighbour.parse::<NodeId>()) else {
+                    utils::log(&format!("Invalid node ID: {} or {}, valid range:0-256", node_id, args.neighbour), Color::from_rgb_u8(255, 94, 160));
+                    return;
+                };
+
+                let result = faults::LinkFault::from_fields(&args.latency, &args.jitter, &args.reorder, &args.duplicate, &args.cut)
+                    .and_then(|fault| sc.lock().unwrap().set_link_fault(node_id, neighbour, fault));
+                match result {
+                    Ok(()) => graph_utils::mark_faulty(sc.lock().unwrap().link_faults().keys().copied().collect()),
+                    Err(e) => utils::log(&e, Color::from_rgb_u8(255, 94, 160)),
                 }
             },
         );
@@ -895,11 +1385,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::ChatClient => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(ChatCommand::GetChatsHistory));
+                            sender1.send(sc.tracked_chat(node_id, ChatCommand::GetChatsHistory));
                         }
                         SimulationControllerType::ChatServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(ChatCommand::GetChatsHistory));
+                            sender1.send(sc.tracked_chat(node_id, ChatCommand::GetChatsHistory));
                         }
                         _ => {}
                     }
@@ -930,11 +1420,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::ChatClient => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(ChatCommand::GetRegisteredClients));
+                            sender1.send(sc.tracked_chat(node_id, ChatCommand::GetRegisteredClients));
                         }
                         SimulationControllerType::ChatServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(ChatCommand::GetRegisteredClients));
+                            sender1.send(sc.tracked_chat(node_id, ChatCommand::GetRegisteredClients));
                         }
                         _ => {}
                     }
@@ -962,11 +1452,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::ChatClient => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(ChatCommand::SendMessage(Message { from, to, text })));
+                            sc.chats.lock().unwrap().on_command(&Message { from, to, text: text.clone() });
+                            sender1.send(sc.tracked_chat(node_id, ChatCommand::SendMessage(Message { from, to, text })));
                         }
                         SimulationControllerType::ChatServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(ChatCommand::SendMessage(Message { from, to, text })));
+                            sender1.send(sc.tracked_chat(node_id, ChatCommand::SendMessage(Message { from, to, text })));
                         }
                         _ => {}
                     }
@@ -1006,11 +1497,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::ChatClient => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(ChatCommand::RegisterToServer(args_node_id)));
+                            sender1.send(sc.tracked_chat(node_id, ChatCommand::RegisterToServer(args_node_id)));
                         }
                         SimulationControllerType::ChatServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(ChatCommand::RegisterToServer(args_node_id)));
+                            sender1.send(sc.tracked_chat(node_id, ChatCommand::RegisterToServer(args_node_id)));
                         }
                         _ => {}
                     }
@@ -1040,11 +1531,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetCachedFiles));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetCachedFiles));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetCachedFiles));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetCachedFiles));
                         }
                         _ => {}
                     }
@@ -1070,11 +1561,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetFile(uuid)));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetFile(uuid)));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetFile(uuid)));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetFile(uuid)));
                         }
                         _ => {}
                     }
@@ -1099,11 +1590,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetTextFiles));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetTextFiles));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetTextFiles));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetTextFiles));
                         }
                         _ => {}
                     }
@@ -1130,11 +1621,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetTextFile(uuid)));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetTextFile(uuid)));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetTextFile(uuid)));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetTextFile(uuid)));
                         }
                         _ => {}
                     }
@@ -1159,11 +1650,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetMediaFiles));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetMediaFiles));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetMediaFiles));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetMediaFiles));
                         }
                         _ => {}
                     }
@@ -1191,11 +1682,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetMediaFile { media_id, location }));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetMediaFile { media_id, location }));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetMediaFile { media_id, location }));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetMediaFile { media_id, location }));
                         }
                         _ => {}
                     }
@@ -1233,11 +1724,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::AddTextFile(TextFile::new(title.clone(), content.clone(), media_refs.clone()))));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::AddTextFile(TextFile::new(title.clone(), content.clone(), media_refs.clone()))));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::AddTextFile(TextFile::new(title, content, media_refs))));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::AddTextFile(TextFile::new(title, content, media_refs))));
                         }
                         _ => {}
                     }
@@ -1263,11 +1754,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::AddTextFileFromPath(file_path.clone())));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::AddTextFileFromPath(file_path.clone())));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::AddTextFileFromPath(file_path)));
+                            // checksum of what the server is about to hold, bound to its UUID on the Added event
+                            match std::fs::read(&file_path) {
+                                Ok(bytes) => sender1.send(sc.tracked_add(node_id, WebCommand::AddTextFileFromPath(file_path), &bytes)),
+                                Err(e) => {
+                                    utils::log(&format!("Cannot read {file_path} for its checksum: {e}"), Color::from_rgb_u8(255, 94, 160));
+                                    sender1.send(sc.tracked_web(node_id, WebCommand::AddTextFileFromPath(file_path)))
+                                }
+                            };
                         }
                         _ => {}
                     }
@@ -1294,11 +1792,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::AddMediaFileFromPath(file_path.clone())));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::AddMediaFileFromPath(file_path.clone())));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::AddMediaFileFromPath(file_path)));
+                            // checksum of what the server is about to hold, bound to its UUID on the Added event
+                            match std::fs::read(&file_path) {
+                                Ok(bytes) => sender1.send(sc.tracked_add(node_id, WebCommand::AddMediaFileFromPath(file_path), &bytes)),
+                                Err(e) => {
+                                    utils::log(&format!("Cannot read {file_path} for its checksum: {e}"), Color::from_rgb_u8(255, 94, 160));
+                                    sender1.send(sc.tracked_web(node_id, WebCommand::AddMediaFileFromPath(file_path)))
+                                }
+                            };
                         }
                         _ => {}
                     }
@@ -1325,11 +1830,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::RemoveTextFile(uuid)));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::RemoveTextFile(uuid)));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::RemoveTextFile(uuid)));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::RemoveTextFile(uuid)));
                         }
                         _ => {}
                     }
@@ -1356,11 +1861,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::RemoveMediaFile(uuid)));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::RemoveMediaFile(uuid)));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::RemoveMediaFile(uuid)));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::RemoveMediaFile(uuid)));
                         }
                         _ => {}
                     }
@@ -1385,11 +1890,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::QueryTextFilesList));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::QueryTextFilesList));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::QueryTextFilesList));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::QueryTextFilesList));
                         }
                         _ => {}
                     }
@@ -1413,11 +1918,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     match node_type {
                         SimulationControllerType::WebBrowser => {
                             let sender1 = &sc.clients.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetTextFilesList));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetTextFilesList));
                         }
                         SimulationControllerType::WebServer => {
                             let sender1 = &sc.servers.get(&node_id).unwrap().1;
-                            sender1.send(Box::new(WebCommand::GetTextFilesList));
+                            sender1.send(sc.tracked_web(node_id, WebCommand::GetTextFilesList));
                         }
                         _ => {}
                     }
@@ -1443,6 +1948,457 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
 
+    {
+        let sc = Arc::clone(&simulation_controller);
+
+        main_window.on_export_topology(move || {
+            let sc = sc.lock().unwrap();
+            let path = format!("./config/export_{}.toml", Local::now().format("%Y%m%d_%H%M%S"));
+
+            match topology::TopologyConfig::from_simulation_controller(&sc).save(&path) {
+                Ok(()) => utils::log(&format!("Topology exported to {path}"), Color::from_rgb_u8(123, 132, 150)),
+                Err(e) => utils::log(&format!("Cannot export topology to {path}: {e}"), Color::from_rgb_u8(255, 94, 160)),
+            }
+        });
+    }
+
+    {
+        let sc = Arc::clone(&simulation_controller);
+        main_window.on_export_graph(move || {
+            let sc = sc.lock().unwrap();
+            let graph = export::ExportGraph::of_controller(&sc);
+            let root = match sc.output.lock().unwrap().root() {
+                Ok(root) => root,
+                Err(e) => {
+                    utils::log(&format!("Cannot export the graph: {e}"), Color::from_rgb_u8(255, 94, 160));
+                    return;
+                }
+            };
+
+            let stamp = Local::now().format("%H%M%S");
+            let svg = graph.to_svg(&graph_utils::current_positions());
+            for (format, content) in [(export::Format::Dot, graph.to_dot()), (export::Format::GraphMl, graph.to_graphml()), (export::Format::Svg, svg)] {
+                let path = root.join(format!("graph_{stamp}.{}", format.extension()));
+                match std::fs::write(&path, content) {
+                    Ok(()) => utils::log(&format!("Graph exported to {}", path.display()), Color::from_rgb_u8(123, 132, 150)),
+                    Err(e) => utils::log(&format!("Cannot export the graph to {}: {e}", path.display()), Color::from_rgb_u8(255, 94, 160)),
+                }
+            }
+        });
+    }
+
+    {
+        let sc = Arc::clone(&simulation_controller);
+        main_window.on_seed_directory(
+            move |node_command: SimulationControllerCommand,
+                node_type: SimulationControllerType,
+                node_id: SharedString,
+                args: SeedDirectory| {
+                let Ok(server) = node_id.parse::<NodeId>() else {
+                    return;
+                };
+                let globs = |list: &SharedString| list.split(',').map(|g| g.trim().to_string()).filter(|g| !g.is_empty()).collect::<Vec<_>>();
+
+                let seed = seeding::SeedConfig {
+                    server,
+                    dir: if args.dir.is_empty() { "./files".to_string() } else { args.dir.to_string() },
+                    recursive: args.recursive,
+                    include: globs(&args.include),
+                    exclude: globs(&args.exclude),
+                    media_server: args.media_server.parse::<NodeId>().ok(),
+                    manifest: if args.manifest.is_empty() { None } else { Some(args.manifest.to_string()) },
+                };
+
+                sc.lock().unwrap().seed_from_directory(&seed);
+            },
+        );
+    }
+
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_set_output_dir(move |dir| {
+            let dir = if dir.trim().is_empty() { output::DEFAULT_BASE.to_string() } else { dir.trim().to_string() };
+            sc.lock().unwrap().output.lock().unwrap().set_base(&dir);
+
+            if let Some(mw) = main_window_weak.upgrade() {
+                mw.set_output_dir(dir.clone().into());
+            }
+            utils::log(&format!("Saved files now go to {dir}/<timestamp>/"), Color::from_rgb_u8(123, 132, 150));
+        });
+    }
+
+    main_window.on_generate_topology(move |args: GenerateTopology| {
+        // reuse the CLI parser, empty fields keep the CLI defaults
+        let mut flags: Vec<String> = Vec::new();
+        let percent = |value: &SharedString| value.parse::<f32>().map(|v| (v / 100.).to_string()).unwrap_or_else(|_| value.to_string());
+
+        for (flag, value) in [
+            ("--shape", args.shape.to_string()),
+            ("--param", args.param.to_string()),
+            ("--drones", args.drones.to_string()),
+            ("--clients", args.clients.to_string()),
+            ("--servers", args.servers.to_string()),
+            ("--pdr-min", if args.pdr_min.is_empty() { String::new() } else { percent(&args.pdr_min) }),
+            ("--pdr-max", if args.pdr_max.is_empty() { String::new() } else { percent(&args.pdr_max) }),
+            ("--seed", args.seed.to_string()),
+            ("--out", args.path.to_string()),
+        ] {
+            if !value.trim().is_empty() {
+                flags.push(flag.to_string());
+                flags.push(value.trim().to_string());
+            }
+        }
+
+        let (params, path) = match cli::parse_generate(&flags) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                utils::log(&format!("Cannot generate topology: {e}"), Color::from_rgb_u8(255, 94, 160));
+                return;
+            }
+        };
+
+        match generator::generate(&params) {
+            Ok(config) => match config.save(&path) {
+                Ok(()) => utils::log(&format!("Generated topology saved to {path} (seed {})", params.seed), Color::from_rgb_u8(123, 132, 150)),
+                Err(e) => utils::log(&format!("Cannot write {path}: {e}"), Color::from_rgb_u8(255, 94, 160)),
+            },
+            Err(errors) => {
+                for error in errors {
+                    utils::log(&format!("Cannot generate topology: {error}"), Color::from_rgb_u8(255, 94, 160));
+                }
+            }
+        }
+    });
+
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let editor = Rc::new(RefCell::new(editor::Editor::default()));
+        let main_window_weak = main_window.as_weak();
+
+        let report = |mw: &MainWindow, result: Result<(), Vec<String>>, ok: &str| {
+            match result {
+                Ok(()) => mw.set_editor_status(ok.into()),
+                Err(errors) => mw.set_editor_status(errors.join("\n").into()),
+            }
+        };
+
+        {
+            let editor = editor.clone();
+            let sc = Arc::clone(&sc);
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_editor_open(move || {
+                // start from the live network, so the current session can be tweaked and restarted
+                let config = topology::TopologyConfig::from_simulation_controller(&sc.lock().unwrap());
+                editor.borrow_mut().load(config);
+                if let Some(mw) = main_window_weak.upgrade() {
+                    editor.borrow().render(&mw);
+                }
+            });
+        }
+
+        {
+            let editor = editor.clone();
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_editor_canvas_clicked(move |tool, x, y| {
+                let Some(mw) = main_window_weak.upgrade() else { return; };
+                let mut editor = editor.borrow_mut();
+                let result = match tool.as_str() {
+                    "drone" | "client" | "server" => editor.place(tool.as_str(), x, y).map(|_| ()).map_err(|e| vec![e]),
+                    _ => Ok(()),
+                };
+                editor.render(&mw);
+                if result.is_err() {
+                    report(&mw, result, "");
+                }
+            });
+        }
+
+        {
+            let editor = editor.clone();
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_editor_node_clicked(move |tool, node_id| {
+                let Some(mw) = main_window_weak.upgrade() else { return; };
+                let Ok(node_id) = node_id.parse::<NodeId>() else { return; };
+                let mut editor = editor.borrow_mut();
+                let result = match tool.as_str() {
+                    "delete" => {
+                        editor.remove(node_id);
+                        Ok(())
+                    }
+                    "link" => editor.click(node_id, true).map_err(|e| vec![e]),
+                    _ => editor.click(node_id, false).map_err(|e| vec![e]),
+                };
+                editor.render(&mw);
+                if result.is_err() {
+                    report(&mw, result, "");
+                }
+            });
+        }
+
+        {
+            let editor = editor.clone();
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_editor_set_pdr(move |node_id, pdr| {
+                let Some(mw) = main_window_weak.upgrade() else { return; };
+                let result = match (node_id.parse::<NodeId>(), pdr.parse::<f32>()) {
+                    (Ok(node_id), Ok(pdr)) => editor.borrow_mut().set_pdr(node_id, pdr / 100.).map_err(|e| vec![e]),
+                    (Err(_), _) => Err(vec!["Select a drone first".to_string()]),
+                    (_, Err(_)) => Err(vec![format!("Invalid Packet Drop Rate: {pdr}")]),
+                };
+                editor.borrow().render(&mw);
+                if result.is_err() {
+                    report(&mw, result, "");
+                }
+            });
+        }
+
+        {
+            let editor = editor.clone();
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_editor_validate(move || {
+                if let Some(mw) = main_window_weak.upgrade() {
+                    report(&mw, editor.borrow().validate(), "The network is valid");
+                }
+            });
+        }
+
+        {
+            let editor = editor.clone();
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_editor_save(move |path| {
+                let path = if path.is_empty() { "./config/editor.toml".to_string() } else { path.to_string() };
+                if let Some(mw) = main_window_weak.upgrade() {
+                    report(&mw, editor.borrow().save(&path), &format!("Saved to {path}"));
+                }
+            });
+        }
+
+        {
+            let editor = editor.clone();
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_editor_start(move |path| {
+                let path = if path.is_empty() { "./config/editor.toml".to_string() } else { path.to_string() };
+                let Some(mw) = main_window_weak.upgrade() else { return; };
+
+                if let Err(errors) = editor.borrow().save(&path) {
+                    for error in errors {
+                        utils::log(&format!("Cannot start edited network: {error}"), Color::from_rgb_u8(255, 94, 160));
+                    }
+                    return;
+                }
+
+                let mut sc = sc.lock().unwrap();
+                utils::load_simulation(&path, &mut sc, &mw);
+            });
+        }
+    }
+
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let main_window_weak = main_window.as_weak();
+
+        {
+            let sc = Arc::clone(&sc);
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_chat_open(move |_client| {
+                if let Some(mw) = main_window_weak.upgrade() {
+                    let chats = sc.lock().unwrap().chats.clone();
+                    chat::refresh(&mw, &chats.lock().unwrap());
+                }
+            });
+        }
+
+        {
+            let sc = Arc::clone(&sc);
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_chat_select_peer(move |_peer| {
+                if let Some(mw) = main_window_weak.upgrade() {
+                    let chats = sc.lock().unwrap().chats.clone();
+                    chat::refresh(&mw, &chats.lock().unwrap());
+                }
+            });
+        }
+
+        main_window.on_chat_send(move |client, to, text| {
+            let (Ok(from), Ok(to)) = (client.parse::<NodeId>(), to.parse::<NodeId>()) else {
+                utils::log(&format!("Invalid chat recipient: {to}"), Color::from_rgb_u8(255, 94, 160));
+                return;
+            };
+            if text.is_empty() {
+                return;
+            }
+
+            let sc = sc.lock().unwrap();
+            let Some((_, sender)) = sc.clients.get(&from) else {
+                utils::log(&format!("Client {from} is not running"), Color::from_rgb_u8(255, 94, 160));
+                return;
+            };
+
+            let message = Message { from, to, text: text.to_string() };
+            let mut chats = sc.chats.lock().unwrap();
+            chats.on_command(&message);
+            sender.send(sc.tracked_chat(from, ChatCommand::SendMessage(message)));
+
+            if let Some(mw) = main_window_weak.upgrade() {
+                if mw.get_chat_peer().is_empty() {
+                    mw.set_chat_peer(to.to_string().into());
+                }
+                chat::refresh(&mw, &chats);
+            }
+        });
+    }
+
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let main_window_weak = main_window.as_weak();
+
+        {
+            let sc = Arc::clone(&sc);
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_files_open(move |server| {
+                let Ok(server) = server.parse::<NodeId>() else {
+                    return;
+                };
+                let sc = sc.lock().unwrap();
+
+                if let Some(mw) = main_window_weak.upgrade() {
+                    if mw.get_files_client().is_empty() {
+                        let browser = sc.get_nodes_with_type().0.into_iter().find(|(_, kind)| kind != "Chat-Client");
+                        if let Some((id, _)) = browser {
+                            mw.set_files_client(id.to_string().into());
+                        }
+                    }
+                    file_browser::refresh(&mw, &sc.files.lock().unwrap());
+                }
+
+                sc.request_server_files(server);
+            });
+        }
+
+        {
+            let sc = Arc::clone(&sc);
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_files_select(move |_id| {
+                if let Some(mw) = main_window_weak.upgrade() {
+                    let files = sc.lock().unwrap().files.clone();
+                    file_browser::refresh(&mw, &files.lock().unwrap());
+                }
+            });
+        }
+
+        {
+            let sc = Arc::clone(&sc);
+            main_window.on_files_refresh(move |server| {
+                if let Ok(server) = server.parse::<NodeId>() {
+                    sc.lock().unwrap().request_server_files(server);
+                }
+            });
+        }
+
+        {
+            let sc = Arc::clone(&sc);
+            main_window.on_files_get_file(move |client, id| {
+                let (Ok(client), Ok(uuid)) = (client.parse::<NodeId>(), id.parse::<Uuid>()) else {
+                    utils::log("Select a file and a web browser first", Color::from_rgb_u8(255, 94, 160));
+                    return;
+                };
+                let sc = sc.lock().unwrap();
+                match sc.clients.get(&client) {
+                    Some((_, sender)) => { sender.send(sc.tracked_web(client, WebCommand::GetFile(uuid))); }
+                    None => utils::log(&format!("Web browser {client} is not running"), Color::from_rgb_u8(255, 94, 160)),
+                }
+            });
+        }
+
+        {
+            let sc = Arc::clone(&sc);
+            main_window.on_files_get_media_file(move |client, id, server| {
+                let (Ok(client), Ok(media_id), Ok(location)) = (client.parse::<NodeId>(), id.parse::<Uuid>(), server.parse::<NodeId>()) else {
+                    utils::log("Select a file and a web browser first", Color::from_rgb_u8(255, 94, 160));
+                    return;
+                };
+                let sc = sc.lock().unwrap();
+                match sc.clients.get(&client) {
+                    Some((_, sender)) => { sender.send(sc.tracked_web(client, WebCommand::GetMediaFile { media_id, location })); }
+                    None => utils::log(&format!("Web browser {client} is not running"), Color::from_rgb_u8(255, 94, 160)),
+                }
+            });
+        }
+
+        main_window.on_files_remove(move |server, id| {
+            let (Ok(server), Ok(uuid)) = (server.parse::<NodeId>(), id.parse::<Uuid>()) else {
+                utils::log("Select a file first", Color::from_rgb_u8(255, 94, 160));
+                return;
+            };
+            let sc = sc.lock().unwrap();
+            let is_media = sc.files.lock().unwrap().get(server, &uuid).is_some_and(|e| e.is_media);
+            if let Some((_, sender)) = sc.servers.get(&server) {
+                if is_media {
+                    sender.send(sc.tracked_web(server, WebCommand::RemoveMediaFile(uuid)));
+                } else {
+                    sender.send(sc.tracked_web(server, WebCommand::RemoveTextFile(uuid)));
+                }
+            }
+        });
+    }
+
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let main_window_weak = main_window.as_weak();
+
+        {
+            let sc = Arc::clone(&sc);
+            let main_window_weak = main_window_weak.clone();
+            main_window.on_browser_open(move |_client| {
+                if let Some(mw) = main_window_weak.upgrade() {
+                    let sc = sc.lock().unwrap();
+                    browser::refresh(&mw, &sc.pages.lock().unwrap(), &sc.files.lock().unwrap());
+                }
+            });
+        }
+
+        main_window.on_browser_navigate(move |client, uuid| {
+            let (Ok(client), Ok(uuid)) = (client.parse::<NodeId>(), uuid.trim().parse::<Uuid>()) else {
+                utils::log(&format!("Invalid text file UUID: {uuid}"), Color::from_rgb_u8(255, 94, 160));
+                return;
+            };
+
+            let sc = sc.lock().unwrap();
+            let Some((_, sender)) = sc.clients.get(&client) else {
+                utils::log(&format!("Web browser {client} is not running"), Color::from_rgb_u8(255, 94, 160));
+                return;
+            };
+
+            let mut pages = sc.pages.lock().unwrap();
+            pages.open(client, uuid);
+            sender.send(sc.tracked_web(client, WebCommand::GetTextFile(uuid)));
+
+            if let Some(mw) = main_window_weak.upgrade() {
+                browser::refresh(&mw, &pages, &sc.files.lock().unwrap());
+            }
+        });
+    }
+
+    // Pending requests time out even when no event comes in
+    let requests_timer = slint::Timer::default();
+    {
+        let sc = Arc::clone(&simulation_controller);
+        let main_window_weak = main_window.as_weak();
+        requests_timer.start(slint::TimerMode::Repeated, std::time::Duration::from_secs(1), move || {
+            let requests = sc.lock().unwrap().requests.clone();
+            let mut requests = requests.lock().unwrap();
+            let expired = requests.expire(std::time::Instant::now());
+            if expired > 0 {
+                utils::log(&format!("{expired} request(s) timed out"), Color::from_rgb_u8(255, 94, 160));
+            }
+            if let Some(mw) = main_window_weak.upgrade() {
+                requests::refresh(&mw, &mut requests);
+            }
+        });
+    }
+
+
     // Initial log
     utils::log("Simulation Controller started", Color::from_rgb_u8(123, 132, 150));
 
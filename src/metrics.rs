@@ -0,0 +1,204 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use common::types::{ChatEvent, NodeEvent, WebEvent};
+use wg_internal::controller::DroneEvent;
+use wg_internal::network::NodeId;
+use wg_internal::packet::{Packet, PacketType};
+
+use crate::observer::EventObserver;
+use crate::requests::RequestStatus;
+use crate::{EventContext, SimulationController};
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Counters only the metrics export needs, the totals shown in the UI stay in `Stats`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metrics {
+    pub drone_sent: BTreeMap<NodeId, u64>,
+    pub drone_dropped: BTreeMap<NodeId, u64>,
+    pub drone_shortcuts: BTreeMap<NodeId, u64>,
+    /// Controller shortcuts by type of the packet delivered.
+    pub shortcuts_by_type: BTreeMap<&'static str, u64>,
+    pub chat_messages_delivered: u64,
+    /// Files received by clients, `text` or `media`.
+    pub files_transferred: BTreeMap<&'static str, u64>,
+    /// Packets sent over each link, keyed by `(smaller id, larger id)`.
+    pub edge_packets: BTreeMap<(NodeId, NodeId), u64>,
+}
+
+/// The link a packet was just sent over, the sender already moved it to its next hop.
+fn link_of(packet: &Packet) -> Option<(NodeId, NodeId)> {
+    let header = &packet.routing_header;
+    let index = header.hop_index.max(1);
+    let (a, b) = (*header.hops.get(index - 1)?, *header.hops.get(index)?);
+    Some((a.min(b), a.max(b)))
+}
+
+pub fn packet_type(packet: &Packet) -> &'static str {
+    match packet.pack_type {
+        PacketType::MsgFragment(_) => "msg_fragment",
+        PacketType::Ack(_) => "ack",
+        PacketType::Nack(_) => "nack",
+        PacketType::FloodRequest(_) => "flood_request",
+        PacketType::FloodResponse(_) => "flood_response",
+    }
+}
+
+/// Feeds `Metrics` from the events, registered with the other built-in observers.
+pub struct MetricsObserver;
+
+impl EventObserver for MetricsObserver {
+    fn on_node_event(&mut self, event: &dyn Any, ctx: &EventContext) -> bool {
+        let is_client = |id: &NodeId| ctx.nodes.0.iter().any(|(client, _)| client == id);
+        let mut metrics = ctx.metrics.lock().unwrap();
+
+        if let Some(event) = event.downcast_ref::<WebEvent>() {
+            let received = match event {
+                WebEvent::File { notification_from, file } if is_client(notification_from) => Some((1, file.media_files.len() as u64)),
+                WebEvent::TextFile { notification_from, .. } if is_client(notification_from) => Some((1, 0)),
+                WebEvent::MediaFile { notification_from, .. } if is_client(notification_from) => Some((0, 1)),
+                _ => None,
+            };
+            if let Some((text, media)) = received {
+                *metrics.files_transferred.entry("text").or_default() += text;
+                *metrics.files_transferred.entry("media").or_default() += media;
+            }
+            true
+        } else if let Some(event) = event.downcast_ref::<ChatEvent>() {
+            if let ChatEvent::MessageReceived { .. } = event {
+                metrics.chat_messages_delivered += 1;
+            }
+            true
+        } else if let Some(event) = event.downcast_ref::<NodeEvent>() {
+            if let NodeEvent::PacketSent(packet) = event {
+                if let Some(link) = link_of(packet) {
+                    *metrics.edge_packets.entry(link).or_default() += 1;
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn on_drone_event(&mut self, event: &DroneEvent, ctx: &EventContext) {
+        let mut metrics = ctx.metrics.lock().unwrap();
+        match event {
+            DroneEvent::PacketSent(packet) => {
+                // the drone already moved the packet to its next hop
+                let header = &packet.routing_header;
+                if let Some(drone) = header.previous_hop().or_else(|| header.hops.first().copied()) {
+                    *metrics.drone_sent.entry(drone).or_default() += 1;
+                }
+                if let Some(link) = link_of(packet) {
+                    *metrics.edge_packets.entry(link).or_default() += 1;
+                }
+            },
+            DroneEvent::PacketDropped(packet) => {
+                if let Some(drone) = packet.routing_header.hops.get(packet.routing_header.hop_index) {
+                    *metrics.drone_dropped.entry(*drone).or_default() += 1;
+                }
+            },
+            DroneEvent::ControllerShortcut(packet) => {
+                if let Some(drone) = packet.routing_header.hops.get(packet.routing_header.hop_index) {
+                    *metrics.drone_shortcuts.entry(*drone).or_default() += 1;
+                }
+                *metrics.shortcuts_by_type.entry(packet_type(packet)).or_default() += 1;
+            },
+        }
+    }
+}
+
+/// One metric family, its samples are added with `sample`.
+struct Family<'a> {
+    out: &'a mut String,
+    name: &'static str,
+    counter: bool,
+}
+
+impl Family<'_> {
+    fn new<'a>(out: &'a mut String, name: &'static str, kind: &str, help: &str) -> Family<'a> {
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        let _ = writeln!(out, "# HELP {name} {help}");
+        Family { out, name, counter: kind == "counter" }
+    }
+
+    fn sample(&mut self, labels: &[(&str, String)], value: impl std::fmt::Display) {
+        let suffix = if self.counter { "_total" } else { "" };
+        let labels = labels.iter().map(|(k, v)| format!("{k}=\"{v}\"")).collect::<Vec<_>>().join(",");
+        if labels.is_empty() {
+            let _ = writeln!(self.out, "{}{suffix} {value}", self.name);
+        } else {
+            let _ = writeln!(self.out, "{}{suffix}{{{labels}}} {value}", self.name);
+        }
+    }
+}
+
+/// The controller metrics in OpenMetrics text format, as served on `/metrics`.
+pub fn render(sc: &SimulationController) -> String {
+    let metrics = sc.metrics.lock().unwrap().clone();
+    let stats = sc.stats.lock().unwrap().clone();
+    let pending = sc.requests.lock().unwrap().requests().iter().filter(|r| r.status == RequestStatus::Pending).count();
+    let drones = sc.get_drones_pdr();
+    let mut out = String::new();
+
+    let per_drone = |map: &BTreeMap<NodeId, u64>, id: NodeId| map.get(&id).copied().unwrap_or(0);
+    for (name, help, map) in [
+        ("simulation_drone_packets_sent", "Packets sent by each drone.", &metrics.drone_sent),
+        ("simulation_drone_packets_dropped", "Packets dropped by each drone.", &metrics.drone_dropped),
+        ("simulation_drone_controller_shortcuts", "Packets each drone handed to the controller.", &metrics.drone_shortcuts),
+    ] {
+        let mut family = Family::new(&mut out, name, "counter", help);
+        // crashed drones keep their counts
+        let ids = drones.iter().map(|(id, _)| *id).chain(map.keys().copied()).collect::<std::collections::BTreeSet<_>>();
+        for id in ids {
+            family.sample(&[("drone", id.to_string())], per_drone(map, id));
+        }
+    }
+
+    let mut family = Family::new(&mut out, "simulation_drone_pdr", "gauge", "Configured packet drop rate of each running drone.");
+    for (id, pdr) in &drones {
+        family.sample(&[("drone", id.to_string())], pdr);
+    }
+
+    let mut family = Family::new(&mut out, "simulation_controller_shortcuts", "counter", "Packets delivered by the controller, by packet type.");
+    for kind in ["msg_fragment", "ack", "nack", "flood_request", "flood_response"] {
+        family.sample(&[("packet_type", kind.to_string())], metrics.shortcuts_by_type.get(kind).copied().unwrap_or(0));
+    }
+
+    let shortcuts = sc.shortcuts.lock().unwrap();
+    let mut family = Family::new(&mut out, "simulation_controller_shortcut_outcomes", "counter", "What happened to the packets handed to the controller, by packet type.");
+    for outcome in ["delivered", "delayed", "dropped", "failed"] {
+        for kind in ["msg_fragment", "ack", "nack", "flood_request", "flood_response"] {
+            family.sample(&[("outcome", outcome.to_string()), ("packet_type", kind.to_string())], shortcuts.count(outcome, kind));
+        }
+    }
+    drop(shortcuts);
+
+    let mut family = Family::new(&mut out, "simulation_nodes", "gauge", "Running nodes by type.");
+    family.sample(&[("type", "drone".to_string())], drones.len());
+    let mut by_type = BTreeMap::<String, usize>::new();
+    for (_, (node_type, _)) in sc.clients.iter().chain(sc.servers.iter()) {
+        *by_type.entry(node_type.to_string()).or_default() += 1;
+    }
+    for (node_type, count) in by_type {
+        family.sample(&[("type", node_type)], count);
+    }
+
+    Family::new(&mut out, "simulation_pending_requests", "gauge", "Requests still waiting for an answer.").sample(&[], pending);
+    Family::new(&mut out, "simulation_chat_messages_delivered", "counter", "Chat messages received by clients.").sample(&[], metrics.chat_messages_delivered);
+
+    let mut family = Family::new(&mut out, "simulation_files_transferred", "counter", "Files received by clients.");
+    for kind in ["text", "media"] {
+        family.sample(&[("kind", kind.to_string())], metrics.files_transferred.get(kind).copied().unwrap_or(0));
+    }
+
+    Family::new(&mut out, "simulation_packets_sent", "counter", "Packets sent by all nodes and drones.").sample(&[], stats.packets_sent);
+    Family::new(&mut out, "simulation_packets_dropped", "counter", "Packets dropped by all drones.").sample(&[], stats.packets_dropped);
+    Family::new(&mut out, "simulation_fragments_retransmitted", "counter", "Fragments sent again by clients and servers.").sample(&[], stats.fragments_retransmitted);
+
+    out.push_str("# EOF\n");
+    out
+}
@@ -0,0 +1,164 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use common::network::Node;
+use common::types::{Command, Event, NodeCommand, NodeType};
+use wg_internal::controller::{DroneCommand, DroneEvent};
+use wg_internal::network::NodeId;
+use wg_internal::packet::{NodeType as WGNodeType, Packet};
+
+use crate::harness::Harness;
+use crate::topology::TopologyConfig;
+use crate::SimulationController;
+
+/// What a mock client or server answers to a command, the command is the downcastable `Command`.
+pub type NodeScript = Box<dyn FnMut(&dyn Any) -> Vec<Box<dyn Event>> + Send>;
+pub type DroneScript = Box<dyn FnMut(&DroneCommand) -> Vec<DroneEvent> + Send>;
+
+/// Fake drones, clients and servers speaking the same channels as the real ones.
+///
+/// Each node runs on its own thread and answers every command with the events of its script.
+/// `NodeCommand::Shutdown` and `DroneCommand::Crash` stop the node after the script ran.
+///
+/// ```ignore
+/// let network = MockNetwork::new()
+///     .server(6, NodeType::TextServer, Box::new(|_| vec![]))
+///     .drone(1, 0.1, MockNetwork::silent_drone());
+/// let harness = network.into_harness();
+/// ```
+pub struct MockNetwork {
+    sc: SimulationController,
+    node_events: (Sender<Box<dyn Event>>, Receiver<Box<dyn Event>>),
+    drone_events: (Sender<DroneEvent>, Receiver<DroneEvent>),
+    packets: HashMap<NodeId, (Sender<Packet>, Receiver<Packet>)>,
+}
+
+impl MockNetwork {
+
+    pub fn new() -> Self {
+        Self {
+            sc: SimulationController::default(),
+            node_events: unbounded(),
+            drone_events: unbounded(),
+            packets: HashMap::new(),
+        }
+    }
+
+    /// Silent nodes for every node of `config`, with the network view the controller would get from it.
+    ///
+    /// Clients are web browsers and servers text servers, only the shape of the network matters.
+    pub fn from_topology(config: &TopologyConfig) -> Self {
+        let mut network = Self::new();
+        let graph = config.to_generic_graph();
+        let adjacents = |id: NodeId, kind: &str| graph.get(&(id, kind.to_string())).map(|a| a.iter().copied().collect::<Vec<_>>()).unwrap_or_default();
+
+        for drone in &config.drone {
+            network.sc.network_view.nodes.push(Node::new(drone.id, WGNodeType::Drone, adjacents(drone.id, "drone")));
+            network = network.drone(drone.id, drone.pdr, Self::silent_drone());
+        }
+        for client in &config.client {
+            network.sc.network_view.nodes.push(Node::new(client.id, WGNodeType::Client, adjacents(client.id, "client")));
+            network = network.client(client.id, NodeType::WebBrowser, Self::silent_node());
+        }
+        for server in &config.server {
+            network.sc.network_view.nodes.push(Node::new(server.id, WGNodeType::Server, adjacents(server.id, "server")));
+            network = network.server(server.id, NodeType::TextServer, Self::silent_node());
+        }
+        network
+    }
+
+    pub fn silent_node() -> NodeScript {
+        Box::new(|_| Vec::new())
+    }
+
+    pub fn silent_drone() -> DroneScript {
+        Box::new(|_| Vec::new())
+    }
+
+    pub fn client(mut self, id: NodeId, node_type: NodeType, script: NodeScript) -> Self {
+        let sender = self.spawn_node(id, script);
+        self.sc.clients.insert(id, (node_type, sender));
+        self
+    }
+
+    pub fn server(mut self, id: NodeId, node_type: NodeType, script: NodeScript) -> Self {
+        let sender = self.spawn_node(id, script);
+        self.sc.servers.insert(id, (node_type, sender));
+        self
+    }
+
+    pub fn drone(mut self, id: NodeId, pdr: f32, mut script: DroneScript) -> Self {
+        let (sender, receiver) = unbounded::<DroneCommand>();
+        let events = self.drone_events.0.clone();
+        self.packets.insert(id, unbounded());
+
+        std::thread::spawn(move || {
+            while let Ok(command) = receiver.recv() {
+                for event in script(&command) {
+                    let _ = events.send(event);
+                }
+                if matches!(command, DroneCommand::Crash) {
+                    break;
+                }
+            }
+        });
+
+        self.sc.drones.insert(id, (pdr, sender));
+        self
+    }
+
+    fn spawn_node(&mut self, id: NodeId, mut script: NodeScript) -> Sender<Box<dyn Command>> {
+        let (sender, receiver) = unbounded::<Box<dyn Command>>();
+        let events = self.node_events.0.clone();
+        self.packets.insert(id, unbounded());
+
+        std::thread::spawn(move || {
+            while let Ok(command) = receiver.recv() {
+                let command = command.into_any();
+                for event in script(command.as_ref()) {
+                    let _ = events.send(event);
+                }
+                if matches!(command.downcast_ref::<NodeCommand>(), Some(NodeCommand::Shutdown)) {
+                    break;
+                }
+            }
+        });
+
+        sender
+    }
+
+    /// Emits node events as if they came from the network, without any command.
+    pub fn node_events(&self) -> Sender<Box<dyn Event>> {
+        self.node_events.0.clone()
+    }
+
+    pub fn drone_events(&self) -> Sender<DroneEvent> {
+        self.drone_events.0.clone()
+    }
+
+    /// Packets delivered to `node`, e.g. by the controller on a shortcut.
+    pub fn packets(&self, node: NodeId) -> Receiver<Packet> {
+        self.packets[&node].1.clone()
+    }
+
+    pub fn into_harness(mut self) -> Harness {
+        self.sc.packet_senders = self.comms_channels();
+        self.sc.shortcuts.lock().unwrap().set_senders(self.comms_channels());
+        *self.sc.is_running.write().unwrap() = true;
+        Harness::from_parts(self.sc, self.node_events.1, self.drone_events.1)
+    }
+
+    /// The controller alone, for code driving it from other threads. Events are not read.
+    pub fn into_controller(mut self) -> SimulationController {
+        self.sc.packet_senders = self.comms_channels();
+        self.sc.shortcuts.lock().unwrap().set_senders(self.comms_channels());
+        *self.sc.is_running.write().unwrap() = true;
+        self.sc
+    }
+
+    fn comms_channels(&self) -> HashMap<NodeId, Sender<Packet>> {
+        self.packets.iter().map(|(id, (sender, _))| (*id, sender.clone())).collect()
+    }
+}
@@ -0,0 +1,303 @@
+use std::any::{Any, TypeId};
+
+use slint::Color;
+
+use common::types::{ChatEvent, NodeEvent, WebEvent};
+use wg_internal::controller::DroneEvent;
+use wg_internal::network::NodeId;
+
+use crate::inspector::InspectorObserver;
+use crate::metrics::MetricsObserver;
+use crate::timeline::TimelineObserver;
+use crate::{output, utils, EventContext};
+
+/// Something told about every event reaching the controller and every command it sends.
+///
+/// Observers run after the controller state was updated, in the order they were registered,
+/// and can read that state through the context.
+pub trait EventObserver: Send {
+    /// Returns whether the observer knows the type of `event`, events nobody knows go to the fallback.
+    fn on_node_event(&mut self, _event: &dyn Any, _ctx: &EventContext) -> bool {
+        false
+    }
+
+    fn on_drone_event(&mut self, _event: &DroneEvent, _ctx: &EventContext) {}
+
+    /// `command` is a `WebCommand`, `ChatCommand`, `NodeCommand` or `DroneCommand` sent to `node`.
+    fn on_command(&mut self, _node: NodeId, _command: &dyn Any, _ctx: &EventContext) {}
+
+    /// The simulation stopped, nothing more comes until the next start.
+    fn on_stop(&mut self, _ctx: &EventContext) {}
+}
+
+/// The observers of a controller: the log, the counters, the metrics, the timeline, the packet inspector and the recording, then anything registered.
+pub struct Observers {
+    list: Vec<Box<dyn EventObserver>>,
+    fallback: Box<dyn EventObserver>,
+}
+
+impl Default for Observers {
+    fn default() -> Self {
+        Self {
+            list: vec![Box::new(LogObserver), Box::new(StatsObserver), Box::new(MetricsObserver), Box::new(TimelineObserver), Box::new(InspectorObserver), Box::new(RecordingObserver)],
+            fallback: Box::new(UnknownEventObserver::default()),
+        }
+    }
+}
+
+impl Observers {
+
+    pub fn register(&mut self, observer: Box<dyn EventObserver>) {
+        self.list.push(observer);
+    }
+
+    /// Replaces what happens to node events no observer knows, they are logged by default.
+    pub fn set_fallback(&mut self, observer: Box<dyn EventObserver>) {
+        self.fallback = observer;
+    }
+
+    pub fn node_event(&mut self, event: &dyn Any, ctx: &EventContext) {
+        let mut known = false;
+        for observer in &mut self.list {
+            known |= observer.on_node_event(event, ctx);
+        }
+        if !known {
+            self.fallback.on_node_event(event, ctx);
+        }
+    }
+
+    pub fn drone_event(&mut self, event: &DroneEvent, ctx: &EventContext) {
+        for observer in &mut self.list {
+            observer.on_drone_event(event, ctx);
+        }
+    }
+
+    pub fn command(&mut self, node: NodeId, command: &dyn Any, ctx: &EventContext) {
+        for observer in &mut self.list {
+            observer.on_command(node, command, ctx);
+        }
+    }
+
+    pub fn stop(&mut self, ctx: &EventContext) {
+        for observer in &mut self.list {
+            observer.on_stop(ctx);
+        }
+    }
+}
+
+/// Writes a line to the event log for every event.
+pub struct LogObserver;
+
+impl EventObserver for LogObserver {
+    fn on_node_event(&mut self, event: &dyn Any, ctx: &EventContext) -> bool {
+        if let Some(event) = event.downcast_ref::<WebEvent>() {
+            log_web_event(event);
+        } else if let Some(event) = event.downcast_ref::<ChatEvent>() {
+            log_chat_event(event);
+        } else if let Some(event) = event.downcast_ref::<NodeEvent>() {
+            log_node_event(event);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn on_drone_event(&mut self, event: &DroneEvent, ctx: &EventContext) {
+        match event {
+            DroneEvent::PacketSent(packet) => {
+                if packet.routing_header.len() > 0 {
+                    if packet.routing_header.hop_index > 0 {
+                        if let Some(hop) = packet.routing_header.previous_hop() {
+                            utils::log(&format!("DRONE {} - PACKET SENT: {}", hop, packet), Color::from_rgb_u8(128, 95, 47));
+                        }
+                    } else {
+                        utils::log(&format!("DRONE {} - PACKET SENT: {}", packet.routing_header.hops[0], packet), Color::from_rgb_u8(128, 95, 47));
+                    }
+                } else {
+                    utils::log(&format!("DRONE - PACKET SENT: {}", packet), Color::from_rgb_u8(128, 95, 47));
+                }
+            },
+            DroneEvent::ControllerShortcut(_) => {},
+            DroneEvent::PacketDropped(packet) => {
+                let index = packet.routing_header.hop_index;
+                utils::log(&format!("DRONE {} - PACKET DROPPED: {}", packet.routing_header.hops[index], packet), Color::from_rgb_u8(128, 95, 47));
+            }
+        }
+    }
+}
+
+fn log_web_event(event: &WebEvent) {
+    match event {
+        WebEvent::CachedFiles { notification_from, files } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CACHED FILES RECEIVED: {} files", files.len()));
+        },
+        WebEvent::File { notification_from, file } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE RECEIVED: {}", file.id.to_string()));
+        },
+        WebEvent::TextFiles { notification_from, files } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILES RECEIVED: {} files", files.len()));
+        },
+        WebEvent::TextFile { notification_from, file } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILE RECEIVED: {}", file.id.to_string()));
+        },
+        WebEvent::MediaFiles { notification_from, files } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MEDIA FILES RECEIVED: {} files", files.len()));
+        },
+        WebEvent::MediaFile { notification_from, file } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MEDIA FILE RECEIVED: {}", file.id.to_string()));
+        },
+        WebEvent::FilesListQueried { notification_from, from } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILES LIST QUERIED FROM: {from}"));
+        },
+        WebEvent::FileNotFound { notification_from, uuid } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE NOT FOUND: {uuid}"));
+        },
+        WebEvent::TextFileAdded { notification_from, uuid } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILE ADDED: {uuid}"));
+        },
+        WebEvent::MediaFileAdded { notification_from, uuid } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MEDIA FILE ADDED: {uuid}"));
+        },
+        WebEvent::TextFileRemoved { notification_from, uuid } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, TEXT FILE REMOVED: {uuid}"));
+        },
+        WebEvent::MediaFileRemoved { notification_from, uuid } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MEDIA FILE REMOVED: {uuid}"));
+        },
+        WebEvent::FileOperationError { notification_from, msg } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE OPERATION ERROR: {msg}"));
+        },
+        WebEvent::FileRequested { notification_from, from, uuid } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE REQUESTED FROM: {from}, UUID: {uuid}"));
+        },
+        WebEvent::BadUuid { notification_from, from, uuid } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, BAD UUID FROM: {from}, UUID: {uuid}"));
+        },
+        WebEvent::FileServed { notification_from, file } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILE SERVED: {file}"));
+        },
+        WebEvent::FilesLists { notification_from, files_map } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, FILES LISTS (server_id, files_list): {:?}", files_map));
+        },
+    }
+}
+
+fn log_chat_event(event: &ChatEvent) {
+    match event {
+        ChatEvent::ChatHistory { notification_from, .. } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CHAT HISTORY RECEIVED"));
+        },
+        // shown in the servers list instead
+        ChatEvent::RegisteredClients { .. } => {},
+        ChatEvent::MessageSent { notification_from, to } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MESSAGE SENT TO: {to}"));
+        },
+        ChatEvent::MessageReceived { notification_from, msg } => {
+            utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, RECEIVED MESSAGE {:?}", msg));
+        },
+        ChatEvent::ClientRegistered { client, server } => utils::log_default(&format!("NOTIFICATION FROM: {server}, REGISTERED CLIENT {client}")),
+        ChatEvent::ClientListQueried { notification_from, from } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CLIENT LIST QUERIED BY {from}")),
+        ChatEvent::ClientNotInList { notification_from, id } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CLIENT {id} NOT IN REGISTERED CLIENTS")),
+        ChatEvent::ErrorClientNotFound { notification_from, not_found } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, CLIENT {not_found} IS NOT REGISTERED IN SERVER")),
+        ChatEvent::RegistrationSucceeded { notification_from, to } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, SUCCESSFULLY REGISTERED TO SERVER {to}")),
+    }
+}
+
+fn log_node_event(event: &NodeEvent) {
+    match event {
+        NodeEvent::PacketSent(packet) => utils::log(&format!("PACKET SENT: {}", packet), Color::from_rgb_u8(123, 132, 150)),
+        NodeEvent::FloodStarted(flood_counter, node_id) => utils::log_default(&format!("NOTIFICATION FROM: {}, FLOOD STARTED {} FLOOD", node_id, flood_counter)),
+        NodeEvent::NodeRemoved(node_id) => utils::log_default(&format!("REMOVED {} NODE", node_id)),
+        NodeEvent::MessageReceived { notification_from, from } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MESSAGE RECEIVED FROM: {from}")),
+        NodeEvent::MessageSent { notification_from, to } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, MESSAGE SENT TO: {to}")),
+        NodeEvent::ServerTypeQueried { notification_from, from } => utils::log_default(&format!("NOTIFICATION FROM: {notification_from}, SERVER TYPE QUERIED FROM: {from}")),
+    }
+}
+
+/// Keeps the packet counters of the stats bar.
+pub struct StatsObserver;
+
+impl EventObserver for StatsObserver {
+    fn on_node_event(&mut self, event: &dyn Any, ctx: &EventContext) -> bool {
+        if let Some(NodeEvent::PacketSent(packet)) = event.downcast_ref::<NodeEvent>() {
+            let mut stats = ctx.stats.lock().unwrap();
+            stats.packets_sent += 1;
+            if ctx.integrity.lock().unwrap().on_packet_sent(packet) {
+                stats.fragments_retransmitted += 1;
+            }
+        }
+        event.is::<NodeEvent>()
+    }
+
+    fn on_drone_event(&mut self, event: &DroneEvent, ctx: &EventContext) {
+        let mut stats = ctx.stats.lock().unwrap();
+        match event {
+            DroneEvent::PacketSent(_) => stats.packets_sent += 1,
+            DroneEvent::ControllerShortcut(_) => stats.controller_shortcuts += 1,
+            DroneEvent::PacketDropped(_) => stats.packets_dropped += 1,
+        }
+    }
+}
+
+/// Saves the files and chat histories received into the output folder of the run.
+pub struct RecordingObserver;
+
+impl EventObserver for RecordingObserver {
+    fn on_node_event(&mut self, event: &dyn Any, ctx: &EventContext) -> bool {
+        if let Some(event) = event.downcast_ref::<WebEvent>() {
+            let mut output = ctx.output.lock().unwrap();
+            match event {
+                WebEvent::CachedFiles { notification_from, files } => {
+                    for file in files {
+                        output::report("cached files", output.save_file(*notification_from, &file.text_file, &file.media_files));
+                    }
+                },
+                WebEvent::File { notification_from, file } => output::report("file", output.save_file(*notification_from, &file.text_file, &file.media_files)),
+                WebEvent::TextFiles { notification_from, files } => output::report("text files", output.save_text_files(*notification_from, files)),
+                WebEvent::TextFile { notification_from, file } => output::report("text file", output.save_text_file(*notification_from, file)),
+                WebEvent::MediaFiles { notification_from, files } => output::report("media files", output.save_media_files(*notification_from, files)),
+                WebEvent::MediaFile { notification_from, file } => output::report("media file", output.save_media_file(*notification_from, file)),
+                _ => {},
+            }
+            true
+        } else if let Some(event) = event.downcast_ref::<ChatEvent>() {
+            if let ChatEvent::ChatHistory { notification_from, history } = event {
+                output::report("chat history", ctx.output.lock().unwrap().save_chat_history(*notification_from, history));
+            }
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Reports node events of a type the controller does not know, instead of dropping them.
+///
+/// Events reach the controller as `dyn Any`, which has no name for its type, so each unknown type
+/// is numbered in the order it first showed up and counted.
+#[derive(Default)]
+pub struct UnknownEventObserver {
+    /// Unknown types in the order they were seen, with how many events of each arrived.
+    seen: Vec<(TypeId, usize)>,
+}
+
+impl EventObserver for UnknownEventObserver {
+    fn on_node_event(&mut self, event: &dyn Any, _ctx: &EventContext) -> bool {
+        let type_id = (*event).type_id();
+        let index = match self.seen.iter().position(|(seen, _)| *seen == type_id) {
+            Some(index) => index,
+            None => {
+                self.seen.push((type_id, 0));
+                self.seen.len() - 1
+            }
+        };
+        self.seen[index].1 += 1;
+
+        let count = self.seen[index].1;
+        utils::log(
+            &format!("UNKNOWN EVENT: a client or server sent an event of unknown type #{} ({count} so far), no observer handles it", index + 1),
+            Color::from_rgb_u8(255, 94, 160),
+        );
+        true
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use slint::Color;
+
+use common::types::{MediaFile, Message, TextFile};
+use uuid::Uuid;
+use wg_internal::network::NodeId;
+
+pub const DEFAULT_BASE: &str = "./runs";
+const MANIFEST: &str = "manifest.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Artifact {
+    ChatHistory,
+    TextFile,
+    MediaFile,
+}
+
+impl Artifact {
+    fn dir_name(self) -> &'static str {
+        match self {
+            Artifact::ChatHistory => "chat_history",
+            Artifact::TextFile => "text_files",
+            Artifact::MediaFile => "media_files",
+        }
+    }
+}
+
+/// Where everything a simulation run saves ends up.
+///
+/// Each run gets its own `<base>/<timestamp>/` root, with one folder per node and one
+/// subfolder per artifact type, e.g. `runs/20250101_120000/node_5/media_files/`.
+/// Every saved file is appended to `manifest.toml` at the root of the run.
+///
+/// The root is only created on the first save, so runs that save nothing leave nothing behind.
+#[derive(Debug)]
+pub struct OutputSession {
+    base: PathBuf,
+    root: Option<PathBuf>,
+}
+
+impl Default for OutputSession {
+    fn default() -> Self {
+        Self::new(DEFAULT_BASE)
+    }
+}
+
+impl OutputSession {
+
+    pub fn new(base: impl AsRef<Path>) -> Self {
+        Self { base: base.as_ref().to_path_buf(), root: None }
+    }
+
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Changes the base folder, the next save starts a new run in it.
+    pub fn set_base(&mut self, base: impl AsRef<Path>) {
+        self.base = base.as_ref().to_path_buf();
+        self.root = None;
+    }
+
+    /// Closes the current run, the next save starts a new one.
+    pub fn new_run(&mut self) {
+        self.root = None;
+    }
+
+    /// Root of the current run, created on first use.
+    pub fn root(&mut self) -> io::Result<PathBuf> {
+        if let Some(root) = &self.root {
+            return Ok(root.clone());
+        }
+
+        let stamp = Local::now().format("%Y%m%d_%H%M%S").to_string();
+        let mut root = self.base.join(&stamp);
+        let mut n = 1;
+        // two runs started in the same second must not share a folder
+        while root.exists() {
+            n += 1;
+            root = self.base.join(format!("{stamp}_{n}"));
+        }
+
+        fs::create_dir_all(&root)?;
+        self.root = Some(root.clone());
+        Ok(root)
+    }
+
+    pub fn dir(&mut self, node: NodeId, artifact: Artifact) -> io::Result<PathBuf> {
+        let dir = self.root()?.join(format!("node_{node}")).join(artifact.dir_name());
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    pub fn save_text_file(&mut self, node: NodeId, file: &TextFile) -> io::Result<PathBuf> {
+        let path = self.dir(node, Artifact::TextFile)?.join(file_name(&file.id, &file.title));
+        fs::write(&path, &file.content)?;
+        self.record(node, Artifact::TextFile, &path)?;
+        Ok(path)
+    }
+
+    pub fn save_text_files(&mut self, node: NodeId, files: &[TextFile]) -> io::Result<()> {
+        for file in files {
+            self.save_text_file(node, file)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_media_file(&mut self, node: NodeId, file: &MediaFile) -> io::Result<PathBuf> {
+        let path = self.dir(node, Artifact::MediaFile)?.join(file_name(&file.id, &file.title));
+        fs::write(&path, file.content.concat())?;
+        self.record(node, Artifact::MediaFile, &path)?;
+        Ok(path)
+    }
+
+    pub fn save_media_files(&mut self, node: NodeId, files: &[MediaFile]) -> io::Result<()> {
+        for file in files {
+            self.save_media_file(node, file)?;
+        }
+        Ok(())
+    }
+
+    /// Saves a `File` sent as a whole: its text file and every media it references.
+    pub fn save_file(&mut self, node: NodeId, text_file: &TextFile, media_files: &[MediaFile]) -> io::Result<()> {
+        self.save_text_file(node, text_file)?;
+        self.save_media_files(node, media_files)
+    }
+
+    pub fn save_chat_history(&mut self, node: NodeId, history: &HashMap<NodeId, Vec<Message>>) -> io::Result<Vec<PathBuf>> {
+        let dir = self.dir(node, Artifact::ChatHistory)?;
+        let paths = crate::utils::save_chat_history(&dir, &node, history)?;
+        for path in &paths {
+            self.record(node, Artifact::ChatHistory, path)?;
+        }
+        Ok(paths)
+    }
+
+    /// Appends a saved file to the manifest, paths are relative to the run root.
+    fn record(&mut self, node: NodeId, artifact: Artifact, path: &Path) -> io::Result<()> {
+        let root = self.root()?;
+        let relative = path.strip_prefix(&root).unwrap_or(path);
+
+        let mut manifest = OpenOptions::new().create(true).append(true).open(root.join(MANIFEST))?;
+        writeln!(manifest, "[[artifact]]")?;
+        writeln!(manifest, "node = {node}")?;
+        writeln!(manifest, "kind = \"{}\"", artifact.dir_name())?;
+        writeln!(manifest, "path = {}", crate::topology::format_str(&relative.display().to_string()))?;
+        writeln!(manifest, "saved_at = \"{}\"", Local::now().format("%Y-%m-%d %H:%M:%S"))?;
+        writeln!(manifest)
+    }
+}
+
+/// `{id}_{title}` with only the last component of the title, which comes from the servers.
+/// A title like `../../.bashrc` must not write outside the run directory.
+fn file_name(id: &Uuid, title: &str) -> String {
+    let name = title.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name = if name == "." || name == ".." { "" } else { name };
+    format!("{id}_{name}")
+}
+
+/// Logs a failed save in the UI, saving never stops the simulation.
+pub fn report<T>(what: &str, result: io::Result<T>) {
+    if let Err(e) = result {
+        crate::utils::log(&format!("Cannot save {what}: {e}"), Color::from_rgb_u8(255, 94, 160));
+    }
+}
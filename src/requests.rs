@@ -0,0 +1,344 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use slint::{Color, Model, VecModel};
+use uuid::Uuid;
+
+use common::types::{ChatCommand, ChatEvent, WebCommand, WebEvent};
+use wg_internal::network::NodeId;
+
+use crate::{MainWindow, RequestRow};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most requests kept, the oldest ones go first.
+pub const CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestKind {
+    GetCachedFiles,
+    GetFile(Uuid),
+    GetTextFiles,
+    GetTextFile(Uuid),
+    GetMediaFiles,
+    GetMediaFile(Uuid),
+    AddTextFile,
+    AddMediaFile,
+    RemoveTextFile(Uuid),
+    RemoveMediaFile(Uuid),
+    QueryTextFilesList,
+    GetTextFilesList,
+    GetChatsHistory,
+    GetRegisteredClients,
+    SendMessage { to: NodeId },
+    RegisterToServer(NodeId),
+}
+
+impl RequestKind {
+    /// `None` for the commands no event answers, they are not tracked.
+    pub fn of_web(command: &WebCommand) -> Option<Self> {
+        Some(match command {
+            WebCommand::GetCachedFiles => RequestKind::GetCachedFiles,
+            WebCommand::GetFile(uuid) => RequestKind::GetFile(*uuid),
+            WebCommand::GetTextFiles => RequestKind::GetTextFiles,
+            WebCommand::GetTextFile(uuid) => RequestKind::GetTextFile(*uuid),
+            WebCommand::GetMediaFiles => RequestKind::GetMediaFiles,
+            WebCommand::GetMediaFile { media_id, .. } => RequestKind::GetMediaFile(*media_id),
+            WebCommand::AddTextFile(_) | WebCommand::AddTextFileFromPath(_) => RequestKind::AddTextFile,
+            WebCommand::AddMediaFile(_) | WebCommand::AddMediaFileFromPath(_) => RequestKind::AddMediaFile,
+            WebCommand::RemoveTextFile(uuid) => RequestKind::RemoveTextFile(*uuid),
+            WebCommand::RemoveMediaFile(uuid) => RequestKind::RemoveMediaFile(*uuid),
+            WebCommand::QueryTextFilesList => RequestKind::QueryTextFilesList,
+            WebCommand::GetTextFilesList => RequestKind::GetTextFilesList,
+            _ => return None,
+        })
+    }
+
+    /// `None` for the commands no event answers, they are not tracked.
+    pub fn of_chat(command: &ChatCommand) -> Option<Self> {
+        Some(match command {
+            ChatCommand::GetChatsHistory => RequestKind::GetChatsHistory,
+            ChatCommand::GetRegisteredClients => RequestKind::GetRegisteredClients,
+            ChatCommand::SendMessage(message) => RequestKind::SendMessage { to: message.to },
+            ChatCommand::RegisterToServer(server) => RequestKind::RegisterToServer(*server),
+            _ => return None,
+        })
+    }
+
+    /// File the request is about, used to match `FileNotFound` and `BadUuid`.
+    fn uuid(&self) -> Option<Uuid> {
+        match self {
+            RequestKind::GetFile(uuid)
+            | RequestKind::GetTextFile(uuid)
+            | RequestKind::GetMediaFile(uuid)
+            | RequestKind::RemoveTextFile(uuid)
+            | RequestKind::RemoveMediaFile(uuid) => Some(*uuid),
+            _ => None,
+        }
+    }
+
+    fn is_file_operation(&self) -> bool {
+        !matches!(
+            self,
+            RequestKind::GetChatsHistory | RequestKind::GetRegisteredClients | RequestKind::SendMessage { .. } | RequestKind::RegisterToServer(_)
+        )
+    }
+}
+
+impl fmt::Display for RequestKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestKind::GetFile(uuid) => write!(f, "GetFile {uuid}"),
+            RequestKind::GetTextFile(uuid) => write!(f, "GetTextFile {uuid}"),
+            RequestKind::GetMediaFile(uuid) => write!(f, "GetMediaFile {uuid}"),
+            RequestKind::RemoveTextFile(uuid) => write!(f, "RemoveTextFile {uuid}"),
+            RequestKind::RemoveMediaFile(uuid) => write!(f, "RemoveMediaFile {uuid}"),
+            RequestKind::SendMessage { to } => write!(f, "SendMessage to {to}"),
+            RequestKind::RegisterToServer(server) => write!(f, "RegisterToServer {server}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestStatus {
+    Pending,
+    Completed,
+    Failed(String),
+    TimedOut,
+}
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub id: u64,
+    pub node: NodeId,
+    pub kind: RequestKind,
+    pub status: RequestStatus,
+    pub sent: Instant,
+    pub finished: Option<Instant>,
+    /// The answer came after the request had already timed out.
+    pub late: bool,
+}
+
+impl Request {
+    pub fn duration(&self, now: Instant) -> Duration {
+        self.finished.unwrap_or(now).duration_since(self.sent)
+    }
+}
+
+/// Commands sent by the controller, matched with the events that answer them.
+///
+/// Requests are matched oldest first on the node that got the command, so two identical commands
+/// sent in a row are answered in order. An answer to a timed-out request still completes it, flagged as late.
+#[derive(Debug)]
+pub struct RequestTracker {
+    requests: VecDeque<Request>,
+    next_id: u64,
+    timeout: Duration,
+    /// Requests finished since the last refresh.
+    finished: BTreeSet<u64>,
+}
+
+impl Default for RequestTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl RequestTracker {
+
+    pub fn new(timeout: Duration) -> Self {
+        Self { requests: VecDeque::new(), next_id: 0, timeout, finished: BTreeSet::new() }
+    }
+
+    pub fn issue(&mut self, node: NodeId, kind: RequestKind) -> u64 {
+        self.issue_at(node, kind, Instant::now())
+    }
+
+    pub fn issue_at(&mut self, node: NodeId, kind: RequestKind, now: Instant) -> u64 {
+        if self.requests.len() == CAPACITY {
+            self.requests.pop_front();
+        }
+        self.next_id += 1;
+        self.requests.push_back(Request {
+            id: self.next_id,
+            node,
+            kind,
+            status: RequestStatus::Pending,
+            sent: now,
+            finished: None,
+            late: false,
+        });
+        self.next_id
+    }
+
+    pub fn requests(&self) -> &VecDeque<Request> {
+        &self.requests
+    }
+
+    /// Ids are given in order, so a request is found by its distance from the oldest one kept.
+    pub fn get(&self, id: u64) -> Option<&Request> {
+        let first = self.requests.front()?.id;
+        self.requests.get(usize::try_from(id.checked_sub(first)?).ok()?)
+    }
+
+    /// Marks pending requests older than the timeout, returns how many timed out.
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let mut expired = 0;
+        for request in self.requests.iter_mut().filter(|r| r.status == RequestStatus::Pending) {
+            if now.duration_since(request.sent) >= self.timeout {
+                request.status = RequestStatus::TimedOut;
+                request.finished = Some(now);
+                self.finished.insert(request.id);
+                expired += 1;
+            }
+        }
+        expired
+    }
+
+    fn resolve(&mut self, node: NodeId, matches: impl Fn(&RequestKind) -> bool, status: RequestStatus) -> Option<u64> {
+        let open = |r: &Request, state: RequestStatus| r.node == node && r.status == state && matches(&r.kind);
+
+        let index = self.requests.iter().position(|r| open(r, RequestStatus::Pending))
+            .or_else(|| self.requests.iter().position(|r| open(r, RequestStatus::TimedOut)))?;
+
+        let request = &mut self.requests[index];
+        request.late = request.status == RequestStatus::TimedOut;
+        request.status = status;
+        request.finished = Some(Instant::now());
+        self.finished.insert(request.id);
+        Some(request.id)
+    }
+
+    /// Matches a web event with the request it answers, returns the id of that request.
+    pub fn on_web_event(&mut self, event: &WebEvent) -> Option<u64> {
+        use RequestKind as K;
+        use RequestStatus::{Completed, Failed};
+
+        match event {
+            WebEvent::CachedFiles { notification_from, .. } => self.resolve(*notification_from, |k| *k == K::GetCachedFiles, Completed),
+            WebEvent::File { notification_from, file } => self.resolve(*notification_from, |k| *k == K::GetFile(file.id), Completed),
+            WebEvent::TextFiles { notification_from, .. } => self.resolve(*notification_from, |k| *k == K::GetTextFiles, Completed),
+            WebEvent::TextFile { notification_from, file } => self.resolve(*notification_from, |k| *k == K::GetTextFile(file.id), Completed),
+            WebEvent::MediaFiles { notification_from, .. } => self.resolve(*notification_from, |k| *k == K::GetMediaFiles, Completed),
+            WebEvent::MediaFile { notification_from, file } => self.resolve(*notification_from, |k| *k == K::GetMediaFile(file.id), Completed),
+            WebEvent::TextFileAdded { notification_from, .. } => self.resolve(*notification_from, |k| *k == K::AddTextFile, Completed),
+            WebEvent::MediaFileAdded { notification_from, .. } => self.resolve(*notification_from, |k| *k == K::AddMediaFile, Completed),
+            WebEvent::TextFileRemoved { notification_from, uuid } => self.resolve(*notification_from, |k| *k == K::RemoveTextFile(*uuid), Completed),
+            WebEvent::MediaFileRemoved { notification_from, uuid } => self.resolve(*notification_from, |k| *k == K::RemoveMediaFile(*uuid), Completed),
+            WebEvent::FilesLists { notification_from, .. } => {
+                self.resolve(*notification_from, |k| matches!(k, K::GetTextFilesList | K::QueryTextFilesList), Completed)
+            }
+            WebEvent::FileNotFound { notification_from, uuid } => {
+                self.resolve(*notification_from, |k| k.uuid() == Some(*uuid), Failed(format!("file {uuid} not found")))
+            }
+            WebEvent::BadUuid { from, uuid, .. } => self.resolve(*from, |k| k.uuid().is_some(), Failed(format!("bad uuid {uuid}"))),
+            WebEvent::FileOperationError { notification_from, msg } => {
+                self.resolve(*notification_from, |k| k.is_file_operation(), Failed(msg.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Matches a chat event with the request it answers, returns the id of that request.
+    pub fn on_chat_event(&mut self, event: &ChatEvent) -> Option<u64> {
+        use RequestKind as K;
+        use RequestStatus::{Completed, Failed};
+
+        match event {
+            ChatEvent::ChatHistory { notification_from, .. } => self.resolve(*notification_from, |k| *k == K::GetChatsHistory, Completed),
+            ChatEvent::RegisteredClients { notification_from, .. } => self.resolve(*notification_from, |k| *k == K::GetRegisteredClients, Completed),
+            ChatEvent::MessageSent { notification_from, to } => self.resolve(*notification_from, |k| *k == K::SendMessage { to: *to }, Completed),
+            ChatEvent::RegistrationSucceeded { notification_from, to } => {
+                self.resolve(*notification_from, |k| *k == K::RegisterToServer(*to), Completed)
+            }
+            ChatEvent::ErrorClientNotFound { notification_from, not_found } => {
+                self.resolve(*notification_from, |k| *k == K::SendMessage { to: *not_found }, Failed(format!("client {not_found} not found")))
+            }
+            ChatEvent::ClientNotInList { notification_from, id } => {
+                self.resolve(*notification_from, |k| *k == K::SendMessage { to: *id }, Failed(format!("client {id} is not registered")))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2} s", duration.as_secs_f32())
+}
+
+fn row(r: &Request, now: Instant) -> RequestRow {
+    let (status, color) = match &r.status {
+        RequestStatus::Pending => ("pending".to_string(), Color::from_rgb_u8(255, 189, 94)),
+        RequestStatus::Completed if r.late => ("completed after timeout".to_string(), Color::from_rgb_u8(255, 189, 94)),
+        RequestStatus::Completed => ("completed".to_string(), Color::from_rgb_u8(94, 255, 108)),
+        RequestStatus::Failed(reason) => (format!("failed: {reason}"), Color::from_rgb_u8(255, 94, 160)),
+        RequestStatus::TimedOut => ("timed out".to_string(), Color::from_rgb_u8(255, 94, 160)),
+    };
+    RequestRow {
+        node: r.node.to_string().into(),
+        command: r.kind.to_string().into(),
+        status: status.into(),
+        duration: format_duration(r.duration(now)).into(),
+        color,
+    }
+}
+
+/// The request list, lives on the UI thread.
+#[derive(Default)]
+struct RequestsView {
+    /// One row per request kept, newest first.
+    rows: Rc<VecModel<RequestRow>>,
+    /// Id of the newest request drawn.
+    newest: u64,
+}
+
+thread_local! {
+    static VIEW: RefCell<Option<RequestsView>> = RefCell::new(None);
+}
+
+/// Updates the request list, newest first: new requests go on top, evicted ones leave at the bottom,
+/// and only the pending rows and the ones finished since the last refresh are rewritten.
+pub fn refresh(main_window: &MainWindow, tracker: &mut RequestTracker) {
+    let now = Instant::now();
+
+    VIEW.with(|view| {
+        let mut view = view.borrow_mut();
+        let view = view.get_or_insert_with(|| {
+            let view = RequestsView::default();
+            main_window.set_requests(view.rows.clone().into());
+            view
+        });
+
+        let newest = view.newest;
+        for request in tracker.requests.iter().skip_while(|r| r.id <= newest) {
+            view.rows.insert(0, row(request, now));
+        }
+        while view.rows.row_count() > tracker.requests.len() {
+            view.rows.remove(view.rows.row_count() - 1);
+        }
+        view.newest = tracker.next_id;
+
+        let last = tracker.requests.len().saturating_sub(1);
+        for (i, request) in tracker.requests.iter().enumerate() {
+            if request.status == RequestStatus::Pending || tracker.finished.contains(&request.id) {
+                view.rows.set_row_data(last - i, row(request, now));
+            }
+        }
+    });
+    tracker.finished.clear();
+
+    let count = |status: &RequestStatus| tracker.requests().iter().filter(|r| r.status == *status).count();
+    let failed = tracker.requests().iter().filter(|r| matches!(r.status, RequestStatus::Failed(_))).count();
+    main_window.set_requests_summary(
+        format!(
+            "{} pending · {} completed · {failed} failed · {} timed out",
+            count(&RequestStatus::Pending),
+            count(&RequestStatus::Completed),
+            count(&RequestStatus::TimedOut)
+        ).into(),
+    );
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use common::types::{MediaFile, MediaReference, TextFile};
+use wg_internal::network::NodeId;
+
+/// Name of the manifest looked up in the seeded directory when none is given.
+pub const DEFAULT_MANIFEST: &str = "seed.toml";
+
+const TEXT_EXTENSIONS: [&str; 7] = ["txt", "md", "html", "htm", "json", "csv", "toml"];
+
+/// A `[[seed]]` table of a network file: content uploaded to a server when the simulation starts.
+///
+/// ```toml
+/// [[seed]]
+/// server = 5
+/// dir = "./files"
+/// recursive = true
+/// include = ["*.txt", "**/*.png"]
+/// media_server = 6
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SeedConfig {
+    pub server: NodeId,
+    pub dir: String,
+    #[serde(default)]
+    pub recursive: bool,
+    /// Globs on the path relative to `dir`, every file is taken when empty.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Server receiving the media files, `server` itself when not set.
+    #[serde(default)]
+    pub media_server: Option<NodeId>,
+    /// Manifest linking text files to media, relative to `dir` (default: `seed.toml`).
+    #[serde(default)]
+    pub manifest: Option<String>,
+}
+
+/// Manifest of a seeded directory.
+///
+/// ```toml
+/// [[page]]
+/// text = "index.txt"
+/// media = ["colors.png", "img/logo.png"]
+/// ```
+///
+/// Text files that are not listed are uploaded without media references.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct SeedManifest {
+    #[serde(default)]
+    pub page: Vec<SeedPage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SeedPage {
+    pub text: String,
+    #[serde(default)]
+    pub media: Vec<String>,
+}
+
+/// Files to upload, built from a directory before anything is sent.
+#[derive(Debug, Clone)]
+pub struct SeedPlan {
+    pub text_server: NodeId,
+    pub media_server: NodeId,
+    pub text_files: Vec<TextFile>,
+    pub media_files: Vec<MediaFile>,
+}
+
+/// Glob match on `/` separated paths: `*` and `?` stay within a path segment, `**` spans segments.
+///
+/// A pattern without `/` is matched against the file name only, so `*.png` matches at any depth.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    if !pattern.contains('/') {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        return match_segment(pattern.as_bytes(), name.as_bytes());
+    }
+
+    let pattern = pattern.split('/').collect::<Vec<_>>();
+    let path = path.split('/').collect::<Vec<_>>();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path_rest)) => match_segment(first.as_bytes(), segment.as_bytes()) && match_segments(rest, path_rest),
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+fn is_text(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Files of `dir` matching the seed filters, as paths relative to `dir` with `/` separators, sorted.
+pub fn collect_files(seed: &SeedConfig) -> Result<Vec<String>, String> {
+    let root = Path::new(&seed.dir);
+    let manifest = seed.manifest.as_deref().unwrap_or(DEFAULT_MANIFEST);
+
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let entries = fs::read_dir(root.join(&relative)).map_err(|e| format!("cannot read {}: {e}", root.join(&relative).display()))?;
+        for entry in entries.filter_map(|e| e.ok()) {
+            let relative = relative.join(entry.file_name());
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            // linked directories are skipped, one pointing to a parent would be walked forever
+            if file_type.is_dir() || (file_type.is_symlink() && path.is_dir()) {
+                if seed.recursive && file_type.is_dir() {
+                    pending.push(relative);
+                }
+                continue;
+            }
+
+            let name = relative.to_string_lossy().replace('\\', "/");
+            let included = seed.include.is_empty() || seed.include.iter().any(|p| glob_match(p, &name));
+            let excluded = seed.exclude.iter().any(|p| glob_match(p, &name));
+            if name != manifest && included && !excluded {
+                files.push(name);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Reads the directory of `seed` and builds the files to upload, with media references resolved.
+pub fn plan(seed: &SeedConfig) -> Result<SeedPlan, String> {
+    let root = Path::new(&seed.dir);
+    let media_server = seed.media_server.unwrap_or(seed.server);
+
+    let manifest_path = root.join(seed.manifest.as_deref().unwrap_or(DEFAULT_MANIFEST));
+    let manifest = match fs::read_to_string(&manifest_path) {
+        Ok(content) => toml::from_str::<SeedManifest>(&content).map_err(|e| format!("{}: {e}", manifest_path.display()))?,
+        Err(_) if seed.manifest.is_none() => SeedManifest::default(),
+        Err(e) => return Err(format!("cannot read {}: {e}", manifest_path.display())),
+    };
+
+    let files = collect_files(seed)?;
+
+    let mut media_files = Vec::new();
+    let mut media_ids: HashMap<String, Uuid> = HashMap::new();
+    for name in files.iter().filter(|f| !is_text(Path::new(f))) {
+        let bytes = fs::read(root.join(name)).map_err(|e| format!("cannot read {name}: {e}"))?;
+        let file = MediaFile { id: Uuid::new_v4(), title: name.clone(), content: vec![bytes] };
+        media_ids.insert(name.clone(), file.id);
+        media_files.push(file);
+    }
+
+    let mut text_files = Vec::new();
+    for name in files.iter().filter(|f| is_text(Path::new(f))) {
+        let content = fs::read_to_string(root.join(name)).map_err(|e| format!("cannot read {name}: {e}"))?;
+
+        let mut media_refs = Vec::new();
+        for page in manifest.page.iter().filter(|p| &p.text == name) {
+            for media in &page.media {
+                let id = media_ids.get(media).ok_or_else(|| format!("{name} references {media}, which is not seeded"))?;
+                media_refs.push(MediaReference { location: media_server, id: *id });
+            }
+        }
+
+        text_files.push(TextFile::new(name.clone(), content, media_refs));
+    }
+
+    for page in &manifest.page {
+        if !files.contains(&page.text) {
+            return Err(format!("the manifest lists {}, which is not seeded", page.text));
+        }
+    }
+
+    Ok(SeedPlan { text_server: seed.server, media_server, text_files, media_files })
+}
@@ -0,0 +1,214 @@
+use std::collections::{BinaryHeap, BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use slint::Color;
+
+use wg_internal::network::NodeId;
+use wg_internal::packet::Packet;
+
+use crate::faults::Delayed;
+use crate::metrics::packet_type;
+use crate::utils;
+
+/// What the controller does with the packets drones hand to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Policy {
+    /// Straight to the destination, as the protocol expects.
+    #[default]
+    Deliver,
+    /// Never delivered, as if the backup path was down.
+    Drop,
+    /// Delivered after a fixed delay.
+    Delay(Duration),
+}
+
+impl Policy {
+    /// `deliver`, `drop` or `delay:<ms>`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.trim() {
+            "deliver" => Ok(Policy::Deliver),
+            "drop" => Ok(Policy::Drop),
+            other => {
+                let ms = other.strip_prefix("delay:").ok_or_else(|| format!("unknown shortcut policy '{other}', expected deliver, drop or delay:<ms>"))?;
+                let ms = ms.parse::<u64>().map_err(|_| format!("invalid delay '{ms}', expected milliseconds"))?;
+                Ok(Policy::Delay(Duration::from_millis(ms)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::Deliver => write!(f, "deliver"),
+            Policy::Drop => write!(f, "drop"),
+            Policy::Delay(delay) => write!(f, "delay:{}", delay.as_millis()),
+        }
+    }
+}
+
+/// Why a shortcut could not be delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    NoDestination,
+    Unknown(NodeId),
+    /// Crashed or shut down.
+    Gone(NodeId),
+    /// The node is known but stopped reading its channel.
+    Disconnected(NodeId),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::NoDestination => write!(f, "the packet has no destination"),
+            Failure::Unknown(id) => write!(f, "node {id} is not in the network"),
+            Failure::Gone(id) => write!(f, "node {id} crashed or was shut down"),
+            Failure::Disconnected(id) => write!(f, "node {id} is not reading its channel"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Delivered(NodeId),
+    Delayed(NodeId, Duration),
+    /// Dropped by the policy.
+    Dropped(NodeId),
+    Failed(Failure),
+}
+
+impl Outcome {
+    /// Label of the outcome in the metrics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::Delivered(_) => "delivered",
+            Outcome::Delayed(..) => "delayed",
+            Outcome::Dropped(_) => "dropped",
+            Outcome::Failed(_) => "failed",
+        }
+    }
+}
+
+/// Delivers `ControllerShortcut` packets to their destination, following the policy, and counts what happened to them.
+#[derive(Debug, Default)]
+pub struct ShortcutRouter {
+    pub policy: Policy,
+    senders: HashMap<NodeId, Sender<Packet>>,
+    /// Nodes crashed or shut down since the network started.
+    gone: HashSet<NodeId>,
+    /// Shortcuts by outcome and packet type.
+    counts: BTreeMap<(&'static str, &'static str), u64>,
+    /// Channel to the thread delivering delayed shortcuts, started with the first one.
+    delayed: Option<Sender<(Instant, Sender<Packet>, Packet)>>,
+}
+
+impl ShortcutRouter {
+
+    /// The channels of a network that just started, nothing is gone yet.
+    pub fn set_senders(&mut self, senders: HashMap<NodeId, Sender<Packet>>) {
+        self.senders = senders;
+        self.gone.clear();
+    }
+
+    /// Shortcuts to `node` fail from now on.
+    pub fn remove(&mut self, node: NodeId) {
+        self.senders.remove(&node);
+        self.gone.insert(node);
+    }
+
+    pub fn counts(&self) -> &BTreeMap<(&'static str, &'static str), u64> {
+        &self.counts
+    }
+
+    pub fn count(&self, outcome: &str, packet_type: &str) -> u64 {
+        self.counts.get(&(outcome, packet_type)).copied().unwrap_or(0)
+    }
+
+    pub fn route(&mut self, packet: &Packet) -> Outcome {
+        let outcome = self.deliver(packet);
+        *self.counts.entry((outcome.name(), packet_type(packet))).or_default() += 1;
+        outcome
+    }
+
+    fn deliver(&mut self, packet: &Packet) -> Outcome {
+        let Some(destination) = packet.routing_header.destination() else {
+            return Outcome::Failed(Failure::NoDestination);
+        };
+        let Some(sender) = self.senders.get(&destination) else {
+            return Outcome::Failed(if self.gone.contains(&destination) { Failure::Gone(destination) } else { Failure::Unknown(destination) });
+        };
+
+        match self.policy {
+            Policy::Deliver => match sender.send(packet.clone()) {
+                Ok(()) => Outcome::Delivered(destination),
+                Err(_) => Outcome::Failed(Failure::Disconnected(destination)),
+            },
+            Policy::Drop => Outcome::Dropped(destination),
+            Policy::Delay(delay) => {
+                let item = (Instant::now() + delay, sender.clone(), packet.clone());
+                let worker = self.delayed.get_or_insert_with(|| {
+                    let (sender, receiver) = unbounded();
+                    std::thread::spawn(move || deliver_delayed(receiver));
+                    sender
+                });
+                match worker.send(item) {
+                    Ok(()) => Outcome::Delayed(destination, delay),
+                    Err(_) => Outcome::Failed(Failure::Disconnected(destination)),
+                }
+            }
+        }
+    }
+}
+
+/// Sends each delayed shortcut once it is due, earliest first.
+///
+/// Stops when the router is gone and every shortcut it delayed was sent.
+fn deliver_delayed(from: Receiver<(Instant, Sender<Packet>, Packet)>) {
+    let mut queue = BinaryHeap::<Delayed<(Sender<Packet>, Packet)>>::new();
+    let mut seq = 0;
+    let mut open = true;
+
+    while open || !queue.is_empty() {
+        let wait = queue.peek().map(|d| d.due.saturating_duration_since(Instant::now()));
+        let received = match (open, wait) {
+            (true, Some(wait)) => from.recv_timeout(wait),
+            (true, None) => from.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            (false, wait) => {
+                std::thread::sleep(wait.unwrap_or_default());
+                Err(RecvTimeoutError::Timeout)
+            }
+        };
+
+        match received {
+            Ok((due, to, packet)) => {
+                queue.push(Delayed { due, seq, item: (to, packet) });
+                seq += 1;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => open = false,
+        }
+
+        let now = Instant::now();
+        while queue.peek().is_some_and(|d| d.due <= now) {
+            if let Some(Delayed { item: (to, packet), .. }) = queue.pop() {
+                // the destination may have gone in the meantime
+                let _ = to.send(packet);
+            }
+        }
+    }
+}
+
+/// Logs what happened to a shortcut, failures as errors.
+pub fn log(packet: &Packet, outcome: &Outcome) {
+    let kind = packet_type(packet);
+    let session = packet.session_id;
+    match outcome {
+        Outcome::Delivered(to) => utils::log(&format!("CONTROLLER SHORTCUT: {kind} of session {session} delivered to {to}"), Color::from_rgb_u8(128, 95, 47)),
+        Outcome::Delayed(to, delay) => utils::log(&format!("CONTROLLER SHORTCUT: {kind} of session {session} delivered to {to} in {} ms", delay.as_millis()), Color::from_rgb_u8(128, 95, 47)),
+        Outcome::Dropped(to) => utils::log(&format!("CONTROLLER SHORTCUT: {kind} of session {session} for {to} dropped by policy"), Color::from_rgb_u8(123, 132, 150)),
+        Outcome::Failed(failure) => utils::log(&format!("CONTROLLER SHORTCUT FAILED: {kind} of session {session}, {failure}"), Color::from_rgb_u8(255, 94, 160)),
+    }
+}
@@ -0,0 +1,34 @@
+use serde::Serialize;
+
+use crate::MainWindow;
+
+/// Counters of what happened since the controller started.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    pub packets_sent: u64,
+    pub packets_dropped: u64,
+    pub controller_shortcuts: u64,
+    pub fragments_retransmitted: u64,
+    pub files_verified: u64,
+    pub files_unverified: u64,
+    pub integrity_errors: u64,
+}
+
+impl Stats {
+    pub fn summary(&self) -> String {
+        format!(
+            "sent {} · dropped {} · shortcuts {} · retransmitted {} · files verified {} / corrupted {} / unchecked {}",
+            self.packets_sent,
+            self.packets_dropped,
+            self.controller_shortcuts,
+            self.fragments_retransmitted,
+            self.files_verified,
+            self.integrity_errors,
+            self.files_unverified
+        )
+    }
+}
+
+pub fn refresh(main_window: &MainWindow, stats: &Stats) {
+    main_window.set_stats(stats.summary().into());
+}
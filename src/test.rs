@@ -13,7 +13,7 @@ mod tests {
     use std::fs;
     use std::path::Path;
     use uuid::Uuid;
-    use crate::{MainWindow, SimulationController};
+    use crate::{utils, MainWindow, SimulationController};
     use std::sync::Arc;
     use slint::Weak;
     use common::file_conversion;
@@ -34,30 +34,46 @@ mod tests {
             title: file_name.to_string(),
             content: vec![file_data.clone()],
         };
-        let server_id = &1;
+        let server_id = 1;
+        let base = std::env::temp_dir().join(format!("simulation_controller_test_{}", Uuid::new_v4()));
+        let mut output = crate::output::OutputSession::new(&base);
         // atomic testing save media files
-        file_conversion::save_media_file(server_id,&media_file).expect("Failed to save media file");
+        let saved = output.save_media_file(server_id, &media_file).expect("Failed to save media file");
 
-        // Testable when UI active
-        // Simulate receiving a WebEvent::MediaFile
-        // let event = WebEvent::MediaFile {
-        //     notification_from: 2,
-        //     file: media_file.clone(),
-        // };
-        // Call the handler directly
-        // SimulationController::handle_node_event(Box::new(event), Weak::default());
+        // events going through the whole controller are covered by the harness tests
 
         // Check that the file was saved
-        let fmt_path = format!("./cached_files_{}/{}_{}", server_id,media_file.id, media_file.title);
-        println!("Checking saved media file at: {}", fmt_path);
-        let path = Path::new(&fmt_path);
+        let root = output.root().unwrap();
+        let fmt_path = root.join(format!("node_{}/media_files/{}_{}", server_id, media_file.id, media_file.title));
+        println!("Checking saved media file at: {}", fmt_path.display());
+        assert_eq!(saved, fmt_path);
+        let path = fmt_path.as_path();
         println!("Reading saved media file at: {}", path.display());
         assert!(path.exists(), "Media file was not saved by SimulationController");
         let saved_data = fs::read(&path).expect("Failed to read saved media file");
         assert_eq!(saved_data, file_data, "Saved media file contents do not match");
 
+        let manifest = fs::read_to_string(root.join("manifest.toml")).expect("Manifest was not written");
+        assert!(manifest.contains(&format!("path = \"node_1/media_files/{}_{}\"", media_file.id, media_file.title)));
+
+        // titles with quotes still give a manifest TOML can read
+        let odd = MediaFile { id: Uuid::new_v4(), title: "say \"hi\" it's.png".to_string(), content: vec![file_data.clone()] };
+        output.save_media_file(server_id, &odd).expect("Failed to save media file");
+        let manifest: toml::Value = toml::from_str(&fs::read_to_string(root.join("manifest.toml")).unwrap()).expect("Manifest is not valid TOML");
+        let paths = manifest["artifact"].as_array().unwrap().iter().map(|a| a["path"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(paths[1], format!("node_1/media_files/{}_{}", odd.id, odd.title));
+
+        // titles come from the servers and must not leave the run directory
+        let sneaky = common::types::TextFile::new("../x".to_string(), "Hello".to_string(), vec![]);
+        let saved = output.save_text_file(server_id, &sneaky).expect("Failed to save text file");
+        assert_eq!(saved, root.join(format!("node_1/text_files/{}_x", sneaky.id)));
+        assert!(saved.canonicalize().unwrap().starts_with(root.canonicalize().unwrap()));
+        let sneaky = MediaFile { id: Uuid::new_v4(), title: "..\\..\\..".to_string(), content: vec![file_data.clone()] };
+        let saved = output.save_media_file(server_id, &sneaky).expect("Failed to save media file");
+        assert_eq!(saved, root.join(format!("node_1/media_files/{}_", sneaky.id)));
+
         // Clean up
-        let _ = fs::remove_file(&path);
+        let _ = fs::remove_dir_all(&base);
     }
 
     
@@ -78,23 +94,17 @@ mod tests {
         history.insert(other_client, messages.clone());
 
 
-        //IF USING UI
-        // Simulate receiving a ChatEvent::ChatHistory
-        // let event = ChatEvent::ChatHistory {
-        //     notification_from: server_id,
-        //     history: history.clone(),
-        // };
-        // Call the handler directly
-        //SimulationController::handle_node_event(Box::new(event), Weak::default());
+        // events going through the whole controller are covered by the harness tests
 
 
         //atomic test
-        utils::save_chat_history(&server_id, &history).expect("Failed to save chat history");
+        let dir = std::env::temp_dir().join(format!("simulation_controller_test_{}", uuid::Uuid::new_v4()));
+        utils::save_chat_history(&dir, &server_id, &history).expect("Failed to save chat history");
 
         // Check that the chat history was saved in the correct file
-        let chat_file_path = format!("./chats_history_{}/clients_{}_{}.txt",server_id, server_id, other_client);
-        println!("Checking saved chat history at: {}", chat_file_path);
-        let path = Path::new(&chat_file_path);
+        let chat_file_path = dir.join(format!("clients_{}_{}.txt", server_id, other_client));
+        println!("Checking saved chat history at: {}", chat_file_path.display());
+        let path = chat_file_path.as_path();
         println!("Reading saved chat history at: {}", path.display());
         assert!(path.exists(), "Chat history file was not saved by SimulationController");
         let saved_data = fs::read_to_string(&path).expect("Failed to read saved chat history file");
@@ -102,5 +112,1443 @@ mod tests {
         assert_eq!(saved_data, expected, "Saved chat history contents do not match");
 
         // Clean up
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_topology_export_round_trip() {
+        let config = crate::topology::TopologyConfig::from_file("./config/config.toml").expect("Failed to parse config.toml");
+
+        let exported = config.to_toml();
+        let reloaded = crate::topology::TopologyConfig::from_toml(&exported).expect("Exported topology is not valid TOML");
+
+        assert_eq!(config, reloaded, "Exported topology does not match the original one");
+        assert!(exported.starts_with("[[drone]]\nid = 1\nconnected_node_ids = [2, 3, 5]\npdr = 0.05"));
+
+        // the export of a running network follows what was changed while it ran
+        let mut sc = crate::mock::MockNetwork::from_topology(&config).into_controller();
+        sc.set_packet_drop_rate(3, 0.5).expect("Failed to set the PDR of drone 3");
+        sc.remove_sender(2, 3).expect("Failed to remove the link between drones 2 and 3");
+        sc.crash(5).expect("Failed to crash client 5");
+
+        let saved = crate::topology::TopologyConfig::from_simulation_controller(&sc);
+        let drone = |id| saved.drone.iter().find(|d| d.id == id).unwrap();
+        assert_eq!(drone(1).connected_node_ids, vec![2, 3], "Crashed client 5 is not a neighbour anymore");
+        assert_eq!(drone(2).connected_node_ids, vec![1, 4, 6]);
+        assert_eq!(drone(3).connected_node_ids, vec![1, 4, 6]);
+        assert_eq!(drone(3).pdr, 0.5);
+        assert_eq!(saved.client.iter().map(|c| c.id).collect::<Vec<_>>(), vec![4]);
+
+        let exported = saved.to_toml();
+        assert!(exported.contains("[[drone]]\nid = 3\nconnected_node_ids = [1, 4, 6]\npdr = 0.5\n"), "{exported}");
+        assert!(!exported.contains("id = 5"), "{exported}");
+        let reloaded = crate::topology::TopologyConfig::from_toml(&exported).expect("Exported topology is not valid TOML");
+        assert_eq!(saved, reloaded);
+        crate::validation::validate_topology(&reloaded).expect("Exported topology does not pass validation");
+
+        let path = std::env::temp_dir().join(format!("simulation_controller_export_{}.toml", Uuid::new_v4()));
+        saved.save(&path).unwrap();
+        let _ = network_initializer::network_initializer::NetworkInitializer::<network_initializer::network_initializer::Uninitialized>::new(path.to_str().unwrap())
+            .initialize();
         let _ = fs::remove_file(&path);
     }
+
+    #[test]
+    fn test_generator_is_reproducible_and_valid() {
+        use crate::generator::{generate, GeneratorParams, PdrDistribution, Shape};
+
+        for shape in [Shape::Ring, Shape::Grid { cols: 4 }, Shape::ErdosRenyi { p: 0.1 }, Shape::BarabasiAlbert { m: 2 }] {
+            let params = GeneratorParams { shape, drones: 30, clients: 5, servers: 4, pdr: PdrDistribution::Uniform { min: 0.0, max: 0.5 }, seed: 42 };
+
+            let first = generate(&params).expect("Generated network does not pass validation");
+            let second = generate(&params).expect("Generated network does not pass validation");
+            assert_eq!(first, second, "Same seed produced two different networks for {:?}", shape);
+
+            assert_eq!(first.drone.len(), 30);
+            assert_eq!(first.client.len(), 5);
+            assert_eq!(first.server.len(), 4);
+            assert!(first.drone.iter().all(|d| (0.0..=0.5).contains(&d.pdr)));
+            assert!(crate::validation::validate_topology(&first).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_editor_links_are_symmetric() {
+        let mut editor = crate::editor::Editor::default();
+
+        let d1 = editor.place("drone", 10., 10.).unwrap();
+        let d2 = editor.place("drone", 50., 10.).unwrap();
+        let client = editor.place("client", 10., 50.).unwrap();
+        let server = editor.place("server", 50., 50.).unwrap();
+
+        editor.toggle_link(d1, d2).unwrap();
+        editor.toggle_link(client, d1).unwrap();
+        editor.toggle_link(server, d1).unwrap();
+        assert!(editor.validate().is_err(), "A server with a single drone must not validate");
+
+        editor.toggle_link(server, d2).unwrap();
+        assert!(editor.toggle_link(client, server).is_err(), "Clients cannot be linked to servers");
+        assert!(editor.validate().is_ok());
+
+        assert!(editor.config.drone[0].connected_node_ids.contains(&client));
+        assert_eq!(editor.config.client[0].connected_drone_ids, vec![d1]);
+
+        // a second toggle removes the link from both ends
+        editor.toggle_link(d1, client).unwrap();
+        assert!(!editor.config.drone[0].connected_node_ids.contains(&client));
+        assert!(editor.config.client[0].connected_drone_ids.is_empty());
+    }
+
+    #[test]
+    fn test_config_linter_diagnostics() {
+        use crate::validation::{fix_symmetric_links, lint_file, lint_str};
+
+        let content = "\
+[[drone]]
+id = 1
+connected_node_ids = [2, 3, 3, 1]
+pdr = 0.1
+
+[[drone]]
+id = 2
+connected_node_ids = [9]
+pdr = 0.1
+
+[[client]]
+id = 3
+connected_drone_ids = [1, 4]
+
+[[server]]
+id = 4
+connected_drone_ids = [2]
+";
+        let diagnostics = lint_str("inline.toml", content).expect("Failed to lint inline config");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        let expect = |needle: &str| assert!(messages.iter().any(|m| m.contains(needle)), "missing '{needle}' in {messages:#?}");
+        expect("inline.toml:3: error: drone 1 lists 2, but drone 2 does not list 1");
+        expect("inline.toml:3: warning: drone 1 lists 3 more than once");
+        expect("inline.toml:3: error: drone 1 is linked to itself");
+        expect("inline.toml:8: error: drone 2 lists 9, which is not declared");
+        expect("inline.toml:13: error: client 3 is linked to server 4");
+        expect("inline.toml:16: error: server 4 is linked to 1 drone(s)");
+
+        // shipped config where drone 5 lists both servers but they do not list it back
+        let diagnostics = lint_file("./config/test_config.toml").expect("Failed to lint test_config.toml");
+        assert_eq!(diagnostics.iter().filter(|d| d.fixable).count(), 2);
+
+        let config = crate::topology::TopologyConfig::from_file("./config/test_config.toml").unwrap();
+        let fixed = fix_symmetric_links(&config).to_toml();
+        let diagnostics = lint_str("fixed.toml", &fixed).unwrap();
+        assert!(diagnostics.is_empty(), "Fixed config still has diagnostics: {diagnostics:#?}");
+
+        // the file is patched in place, only the link arrays that were wrong change
+        let content = format!("# hand-written, keep this comment\n{}", fs::read_to_string("./config/test_config.toml").unwrap());
+        let patched = crate::validation::fix_symmetric_links_in(&content).unwrap();
+        assert!(lint_str("patched.toml", &patched).unwrap().is_empty());
+        assert!(patched.starts_with("# hand-written, keep this comment\n[[drone]]\nid = 9\n"));
+        assert!(patched.contains("connected_drone_ids = [1, 5]"), "{patched}");
+        let changed = content.lines().zip(patched.lines()).filter(|(a, b)| a != b).count();
+        assert_eq!((changed, content.lines().count()), (2, patched.lines().count()), "{patched}");
+        let untouched = fs::read_to_string("./config/config.toml").unwrap();
+        assert_eq!(crate::validation::fix_symmetric_links_in(&untouched).unwrap(), untouched);
+    }
+
+    #[test]
+    fn test_chat_store_marks_undelivered_messages() {
+        use crate::chat::{ChatStore, Delivery};
+        use common::types::Message;
+
+        let mut chats = ChatStore::default();
+
+        chats.on_command(&Message::new(1, 2, "Hello".to_string()));
+        chats.on_command(&Message::new(1, 2, "Lost".to_string()));
+        chats.on_sent(1, 2);
+        chats.on_sent(1, 2);
+        chats.on_received(&Message::new(1, 2, "Hello".to_string()));
+        chats.on_received(&Message::new(2, 1, "Hi back".to_string()));
+
+        let thread = chats.thread(2, 1);
+        assert_eq!(thread.len(), 3);
+        assert_eq!(thread[0].delivery, Delivery::Received);
+        assert_eq!(thread[1].delivery, Delivery::Sent, "Message sent but never received must stay marked");
+        assert_eq!(thread[2].text, "Hi back");
+
+        assert_eq!(chats.peers_of(1), vec![2]);
+        assert_eq!(chats.undelivered(), 1);
+    }
+
+    #[test]
+    fn test_file_catalog_fills_listed_files() {
+        use crate::file_browser::{FileCatalog, FileContent};
+        use common::types::TextFile;
+
+        let mut catalog = FileCatalog::default();
+        let file = TextFile::new("index".to_string(), "Hello".to_string(), vec![]);
+
+        catalog.add_listed(5, file.id, "index", false);
+        assert_eq!(catalog.get(5, &file.id).unwrap().content, FileContent::Unknown);
+
+        // a browser receiving the file fills the entry of the server listing it
+        catalog.fill_text(&file);
+        assert_eq!(catalog.get(5, &file.id).unwrap().content, FileContent::Text("Hello".to_string()));
+        assert_eq!(catalog.location_of(&file.id), Some(5));
+
+        catalog.remove(5, &file.id);
+        assert!(catalog.entries(5).is_empty());
+    }
+
+    #[test]
+    fn test_load_image_stays_in_the_temp_dir() {
+        use crate::file_browser::load_image;
+
+        // the title comes from a server, only its extension is used
+        let id = uuid::Uuid::new_v4();
+        let escaped = std::env::temp_dir().join("../simulation_controller_escape.png");
+        assert!(load_image(&id, "../simulation_controller_escape.png", b"not an image").is_none());
+        assert!(!escaped.exists());
+        assert!(!std::env::temp_dir().join(format!("simulation_controller_{id}.png")).exists());
+    }
+
+    #[test]
+    fn test_browser_page_requests_and_marks_media() {
+        use crate::browser::{BrowserStore, MediaStatus};
+        use common::types::{MediaFile, MediaReference, TextFile};
+
+        let image = MediaFile { id: uuid::Uuid::new_v4(), title: "colors.png".to_string(), content: vec![vec![1, 2], vec![3]] };
+        let missing = uuid::Uuid::new_v4();
+        let page = TextFile::new(
+            "index".to_string(),
+            "Hello".to_string(),
+            vec![MediaReference { location: 7, id: image.id }, MediaReference { location: 8, id: missing }],
+        );
+
+        let mut pages = BrowserStore::default();
+        pages.open(3, page.id);
+
+        assert_eq!(pages.on_text_file(3, &page), vec![(image.id, 7), (missing, 8)]);
+        // the same text file arriving again must not issue the requests twice
+        assert!(pages.on_text_file(3, &page).is_empty());
+
+        assert!(pages.on_media_file(3, &image));
+        assert!(pages.on_failure(3, &missing, "file not found"));
+
+        let page = pages.page(3).unwrap();
+        assert!(page.is_complete());
+        assert_eq!(page.media[0].status, MediaStatus::Loaded { title: "colors.png".to_string(), bytes: vec![1, 2, 3] });
+        assert_eq!(page.media[1].status, MediaStatus::Failed("file not found".to_string()));
+    }
+
+    #[test]
+    fn test_integrity_checksums_and_retransmissions() {
+        use crate::integrity::{crc32, IntegrityTracker, Verification};
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::{Fragment, Packet};
+
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let mut tracker = IntegrityTracker::default();
+        let added = uuid::Uuid::new_v4();
+        tracker.expect_added(1, 5, b"hello world");
+        // an add that failed does not shift the checksums of the next ones
+        tracker.expect_added(2, 5, b"lost");
+        tracker.on_add_failed(2);
+        tracker.expect_added(3, 5, b"second");
+        tracker.on_added(1, added);
+        let second = uuid::Uuid::new_v4();
+        tracker.on_added(3, second);
+        assert_eq!(tracker.verify(&second, b"second"), Verification::Verified);
+
+        assert_eq!(tracker.verify(&added, b"hello world"), Verification::Verified);
+        assert!(matches!(tracker.verify(&added, b"hello wordl"), Verification::Mismatch { .. }));
+        assert_eq!(tracker.verify(&uuid::Uuid::new_v4(), b"hello world"), Verification::Unknown);
+
+        let fragment = |index| Packet::new_fragment(
+            SourceRoutingHeader::new(vec![5, 1, 3], 1),
+            42,
+            Fragment::new(index, 2, [0; 128]),
+        );
+        assert!(!tracker.on_packet_sent(&fragment(0)));
+        assert!(!tracker.on_packet_sent(&fragment(1)));
+        assert!(tracker.on_packet_sent(&fragment(1)));
+
+        let transfer = tracker.last_transfer(5, 3).unwrap();
+        assert_eq!(transfer.session_id, 42);
+        assert_eq!(transfer.retransmitted(), 1);
+
+        // acked in full, the transfer leaves the sessions in flight but is still reported
+        for index in 0..2 {
+            tracker.on_packet_sent(&Packet::new_ack(SourceRoutingHeader::new(vec![3, 1, 5], 1), 42, index));
+        }
+        assert_eq!(tracker.in_flight(), 0);
+        assert_eq!(tracker.last_transfer(5, 3).map(|t| t.retransmitted()), Some(1));
+    }
+
+    #[test]
+    fn test_seeding_plan_links_text_to_media() {
+        use crate::seeding::{glob_match, plan, SeedConfig};
+
+        assert!(glob_match("*.png", "img/colors.png"));
+        assert!(glob_match("img/**/*.png", "img/a/b/colors.png"));
+        assert!(glob_match("img/**/*.png", "img/colors.png"));
+        assert!(!glob_match("img/*.png", "img/a/colors.png"));
+        assert!(glob_match("page?.txt", "page1.txt"));
+
+        let dir = std::env::temp_dir().join(format!("simulation_controller_seed_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+        std::fs::write(dir.join("index.txt"), "Welcome").unwrap();
+        std::fs::write(dir.join("notes.md"), "Not linked").unwrap();
+        std::fs::write(dir.join("img/colors.png"), [1, 2, 3]).unwrap();
+        std::fs::write(dir.join("img/skip.bin"), [4]).unwrap();
+        std::fs::write(dir.join("seed.toml"), "[[page]]\ntext = \"index.txt\"\nmedia = [\"img/colors.png\"]\n").unwrap();
+
+        let seed = SeedConfig {
+            server: 5,
+            dir: dir.display().to_string(),
+            recursive: true,
+            exclude: vec!["*.bin".to_string()],
+            media_server: Some(6),
+            ..Default::default()
+        };
+        let plan = plan(&seed).expect("Failed to plan the seeding");
+
+        assert_eq!(plan.media_files.len(), 1);
+        assert_eq!(plan.media_files[0].title, "img/colors.png");
+        assert_eq!(plan.text_files.len(), 2);
+
+        let index = plan.text_files.iter().find(|f| f.title == "index.txt").unwrap();
+        assert_eq!(index.media_refs.len(), 1);
+        assert_eq!(index.media_refs[0].location, 6);
+        assert_eq!(index.media_refs[0].id, plan.media_files[0].id);
+
+        // not recursive: the media referenced by the manifest is missing
+        let flat = SeedConfig { recursive: false, ..seed.clone() };
+        assert!(crate::seeding::plan(&flat).is_err());
+
+        // seeds survive an export of the network file
+        let mut config = crate::topology::TopologyConfig::default();
+        config.seed.push(seed);
+        // Windows paths and globs with quotes need TOML escaping, not Rust's
+        config.seed.push(SeedConfig {
+            server: 6,
+            dir: "C:\\seeds\\\"site\"".to_string(),
+            include: vec!["*.txt".to_string(), "it's \\*.png".to_string()],
+            manifest: Some("pages\\seed.toml".to_string()),
+            ..Default::default()
+        });
+        let reloaded = crate::topology::TopologyConfig::from_toml(&config.to_toml()).unwrap();
+        assert_eq!(config, reloaded);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_seeding_skips_linked_directories() {
+        use crate::seeding::{collect_files, SeedConfig};
+
+        let dir = std::env::temp_dir().join(format!("simulation_controller_seed_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("pages")).unwrap();
+        std::fs::write(dir.join("pages/index.txt"), "Welcome").unwrap();
+        std::os::unix::fs::symlink("..", dir.join("pages/up")).unwrap();
+        std::os::unix::fs::symlink("index.txt", dir.join("pages/home.txt")).unwrap();
+
+        let seed = SeedConfig { server: 6, dir: dir.display().to_string(), recursive: true, ..Default::default() };
+        let files = collect_files(&seed).expect("A link to a parent must not stop the seeding");
+        assert_eq!(files, vec!["pages/home.txt".to_string(), "pages/index.txt".to_string()], "Linked files are kept, linked directories are not walked");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_requests_are_matched_with_their_events() {
+        use crate::requests::{RequestKind, RequestStatus, RequestTracker};
+        use common::types::{ChatEvent, WebEvent};
+        use std::time::{Duration, Instant};
+
+        let mut tracker = RequestTracker::new(Duration::from_secs(5));
+        let missing = uuid::Uuid::new_v4();
+        let start = Instant::now();
+
+        let get = tracker.issue_at(3, RequestKind::GetTextFile(missing), start);
+        let send = tracker.issue_at(3, RequestKind::SendMessage { to: 4 }, start);
+        let slow = tracker.issue_at(7, RequestKind::GetTextFiles, start);
+
+        assert_eq!(tracker.on_web_event(&WebEvent::FileNotFound { notification_from: 3, uuid: missing }), Some(get));
+        assert_eq!(tracker.on_chat_event(&ChatEvent::MessageSent { notification_from: 3, to: 4 }), Some(send));
+        // nothing is waiting for a message to 5
+        assert_eq!(tracker.on_chat_event(&ChatEvent::MessageSent { notification_from: 3, to: 5 }), None);
+
+        assert_eq!(tracker.expire(start + Duration::from_secs(6)), 1);
+        assert_eq!(tracker.get(slow).unwrap().status, RequestStatus::TimedOut);
+
+        assert_eq!(tracker.get(get).unwrap().status, RequestStatus::Failed(format!("file {missing} not found")));
+        assert_eq!(tracker.get(send).unwrap().status, RequestStatus::Completed);
+        // only commands some event answers are tracked, the others have no kind
+        assert_eq!(RequestKind::of_web(&common::types::WebCommand::GetTextFiles), Some(RequestKind::GetTextFiles));
+        assert_eq!(RequestKind::of_chat(&common::types::ChatCommand::SendMessage(common::types::Message { from: 3, to: 4, text: String::new() })), Some(RequestKind::SendMessage { to: 4 }));
+
+        // the oldest requests go first
+        for _ in 0..crate::requests::CAPACITY {
+            tracker.issue_at(7, RequestKind::GetCachedFiles, start);
+        }
+        assert_eq!(tracker.requests().len(), crate::requests::CAPACITY);
+        assert!(tracker.get(get).is_none());
+        assert_eq!(tracker.get(slow + 1).map(|r| r.kind.clone()), Some(RequestKind::GetCachedFiles));
+    }
+
+    #[test]
+    fn test_validation_rules_on_config() {
+        use crate::validation::{can_remove_sender_client, can_remove_sender_drone, can_remove_sender_server, is_a_drone, is_connected, validate_topology};
+        use std::collections::HashMap;
+
+        let mut config = crate::topology::TopologyConfig::from_file("./config/config.toml").unwrap();
+        assert!(validate_topology(&config).is_ok());
+
+        let graph = config.to_generic_graph();
+        assert!(is_connected(&graph));
+        assert!(is_a_drone(&graph, 6, 2));
+        assert!(!is_a_drone(&graph, 6, 4));
+
+        // client 4 has two drones, client 5 only one
+        assert!(can_remove_sender_client(&graph, 4, 2, &HashMap::new()));
+        assert!(!can_remove_sender_client(&graph, 5, 1, &HashMap::new()));
+        assert!(!can_remove_sender_client(&graph, 5, 3, &HashMap::new()), "Drone 3 is not a sender of client 5");
+
+        // server 6 cannot go below two drones, from either end of the link
+        assert!(!can_remove_sender_server(&graph, 6, 2, &HashMap::new()));
+        assert!(!can_remove_sender_drone(&graph, 2, 6, &HashMap::new()));
+        assert!(can_remove_sender_drone(&graph, 2, 4, &HashMap::new()));
+
+        config.server[0].connected_drone_ids = vec![2];
+        config.drone[2].connected_node_ids.retain(|id| *id != 6);
+        let errors = validate_topology(&config).unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("server 6 must be connected to at least two drones")), "{errors:?}");
+
+        config.client[1].connected_drone_ids.clear();
+        config.drone[0].connected_node_ids.retain(|id| *id != 5);
+        assert!(!is_connected(&config.to_generic_graph()), "Client 5 is no longer reachable");
+    }
+
+    #[test]
+    fn test_network_view_edits() {
+        use crate::harness::Harness;
+
+        let mut harness = Harness::start("./config/config.toml");
+        let adjacents = |sc: &crate::SimulationController, id| {
+            let node = sc.network_view.nodes.iter().find(|n| n.get_id() == id).expect("node not in the network view");
+            let mut adjacents = node.get_adjacents().iter().copied().collect::<Vec<_>>();
+            adjacents.sort();
+            adjacents
+        };
+
+        utils::add_edge(6, 1, &mut harness.sc);
+        assert_eq!(adjacents(&harness.sc, 6), vec![1, 2, 3]);
+        assert!(adjacents(&harness.sc, 1).contains(&6));
+
+        utils::remove_node(5, &mut harness.sc);
+        assert!(!harness.sc.network_view.nodes.iter().any(|n| n.get_id() == 5));
+        assert!(!adjacents(&harness.sc, 1).contains(&5));
+        assert!(!harness.sc.clients.contains_key(&5));
+
+        // drone 2 and 3 are the only drones of server 6 until it is linked to drone 1
+        utils::remove_edge(6, 1, &mut harness.sc);
+        assert!(harness.sc.crash(2).is_err());
+        assert!(harness.sc.drones.contains_key(&2));
+    }
+
+    #[test]
+    fn test_client_gets_file_after_drone_crash() {
+        use crate::harness::{Harness, TIMEOUT};
+        use common::types::{TextFile, WebCommand, WebEvent};
+
+        let mut harness = Harness::start("./config/config.toml");
+        let file = TextFile::new("index.txt".to_string(), "Hello from 6".to_string(), vec![]);
+
+        harness.web(6, WebCommand::AddTextFile(file.clone()));
+        let added = harness.wait_web(TIMEOUT, |event| match event {
+            WebEvent::TextFileAdded { notification_from: 6, uuid } => Some(*uuid),
+            _ => None,
+        }).expect("Server 6 did not add the file");
+
+        // server 6 needs a third drone before drone 2 can go
+        harness.sc.add_sender(6, 1).unwrap();
+        harness.sc.add_sender(1, 6).unwrap();
+        harness.sc.crash(2).expect("Drone 2 could not be crashed");
+
+        harness.web(4, WebCommand::QueryTextFilesList);
+        harness.wait_web(TIMEOUT, |event| match event {
+            WebEvent::FilesLists { notification_from: 4, files_map } if files_map.contains_key(&6) => Some(()),
+            _ => None,
+        }).expect("Client 4 did not get the files list of server 6");
+
+        harness.web(4, WebCommand::GetFile(added));
+        let received = harness.wait_web(TIMEOUT, |event| match event {
+            WebEvent::File { notification_from: 4, file } if file.text_file.id == added => Some(file.text_file.content.clone()),
+            WebEvent::TextFile { notification_from: 4, file } if file.id == added => Some(file.content.clone()),
+            _ => None,
+        });
+
+        assert_eq!(received.as_deref(), Some("Hello from 6"), "Client 4 did not get the file after drone 2 crashed");
+        assert_eq!(harness.sc.stats.lock().unwrap().integrity_errors, 0);
+    }
+
+    #[test]
+    fn test_mock_nodes_drive_controller_state() {
+        use crate::harness::TIMEOUT;
+        use crate::mock::MockNetwork;
+        use crate::requests::RequestStatus;
+        use common::types::{Event, NodeType, TextFile, WebCommand, WebEvent};
+
+        let file = TextFile::new("index".to_string(), "Hello".to_string(), vec![]);
+        let mut tampered = file.clone();
+        tampered.content = "Hellp".to_string();
+
+        let listed = file.clone();
+        let mut harness = MockNetwork::new()
+            .server(6, NodeType::TextServer, Box::new(move |command| match command.downcast_ref::<WebCommand>() {
+                Some(WebCommand::GetTextFiles) => vec![Box::new(WebEvent::TextFiles { notification_from: 6, files: vec![listed.clone()] }) as Box<dyn Event>],
+                _ => vec![],
+            }))
+            .client(4, NodeType::WebBrowser, Box::new(move |command| match command.downcast_ref::<WebCommand>() {
+                Some(WebCommand::GetTextFile(_)) => vec![Box::new(WebEvent::TextFile { notification_from: 4, file: tampered.clone() }) as Box<dyn Event>],
+                _ => vec![],
+            }))
+            .into_harness();
+
+        harness.web(6, WebCommand::GetTextFiles);
+        harness.wait_web(TIMEOUT, |event| matches!(event, WebEvent::TextFiles { .. }).then_some(())).expect("Server 6 did not list its files");
+        assert_eq!(harness.sc.files.lock().unwrap().entries(6).len(), 1);
+
+        harness.web(4, WebCommand::GetTextFile(file.id));
+        harness.wait_web(TIMEOUT, |event| matches!(event, WebEvent::TextFile { .. }).then_some(())).expect("Client 4 did not get the file");
+
+        let stats = harness.sc.stats.lock().unwrap().clone();
+        assert_eq!(stats.integrity_errors, 1, "The tampered file must not match the checksum listed by server 6");
+        assert_eq!(stats.files_verified, 0);
+
+        let requests = harness.sc.requests.lock().unwrap();
+        assert_eq!(requests.requests().len(), 2);
+        assert!(requests.requests().iter().all(|r| r.status == RequestStatus::Completed));
+    }
+
+    #[test]
+    fn test_mock_drones_feed_stats() {
+        use crate::harness::TIMEOUT;
+        use crate::mock::MockNetwork;
+        use common::types::NodeType;
+        use wg_internal::controller::{DroneCommand, DroneEvent};
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::{Fragment, Packet};
+
+        let packet = Packet::new_fragment(SourceRoutingHeader::new(vec![5, 1, 3], 1), 42, Fragment::new(0, 1, [0; 128]));
+
+        let dropped = packet.clone();
+        let network = MockNetwork::new()
+            .drone(1, 0.1, Box::new(move |command| match command {
+                DroneCommand::SetPacketDropRate(_) => vec![DroneEvent::PacketDropped(dropped.clone())],
+                _ => vec![],
+            }))
+            .client(3, NodeType::ChatClient, MockNetwork::silent_node())
+            .client(5, NodeType::ChatClient, MockNetwork::silent_node());
+        let shortcuts = network.drone_events();
+        let delivered = network.packets(3);
+        let mut harness = network.into_harness();
+
+        harness.sc.set_packet_drop_rate(1, 0.5).unwrap();
+        assert!(harness.sc.set_packet_drop_rate(1, 1.5).is_err());
+        harness.wait_drone(TIMEOUT, |event| matches!(event, DroneEvent::PacketDropped(_)).then_some(())).expect("Drone 1 did not drop the packet");
+
+        shortcuts.send(DroneEvent::ControllerShortcut(packet.clone())).unwrap();
+        harness.wait_drone(TIMEOUT, |event| matches!(event, DroneEvent::ControllerShortcut(_)).then_some(())).unwrap();
+        assert_eq!(delivered.try_recv().ok().map(|p| p.session_id), Some(42), "The shortcut must reach the destination of the packet");
+
+        let stats = harness.sc.stats.lock().unwrap().clone();
+        assert_eq!(stats.packets_dropped, 1);
+        assert_eq!(stats.controller_shortcuts, 1);
+        assert_eq!(harness.sc.get_drones_pdr(), vec![(1, 0.5)]);
+    }
+
+    #[derive(Debug, Clone)]
+    enum TopologyOp {
+        Crash(proptest::sample::Index),
+        AddSender(proptest::sample::Index, proptest::sample::Index),
+        RemoveSender(proptest::sample::Index, proptest::sample::Index),
+    }
+
+    fn topology_op() -> impl proptest::strategy::Strategy<Value = TopologyOp> {
+        use proptest::prelude::*;
+
+        prop_oneof![
+            any::<proptest::sample::Index>().prop_map(TopologyOp::Crash),
+            (any::<proptest::sample::Index>(), any::<proptest::sample::Index>()).prop_map(|(a, b)| TopologyOp::AddSender(a, b)),
+            (any::<proptest::sample::Index>(), any::<proptest::sample::Index>()).prop_map(|(a, b)| TopologyOp::RemoveSender(a, b)),
+        ]
+    }
+
+    /// Servers keep two drones or more, clients one or more, and the network stays connected.
+    fn check_topology_invariants(sc: &crate::SimulationController) -> Result<(), String> {
+        let graph = sc.generic_graph();
+        let drones_of = |id: u8, kind: &str| graph.get(&(id, kind.to_string())).map(|a| a.iter().filter(|n| sc.drones.contains_key(n)).count()).unwrap_or(0);
+
+        for id in sc.servers.keys() {
+            if drones_of(*id, "server") < 2 {
+                return Err(format!("server {id} has {} drone(s)", drones_of(*id, "server")));
+            }
+        }
+        for id in sc.clients.keys() {
+            if drones_of(*id, "client") < 1 {
+                return Err(format!("client {id} has no drone"));
+            }
+        }
+        if !crate::validation::is_connected(&graph) {
+            return Err("the network is not connected".to_string());
+        }
+        Ok(())
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(32))]
+
+        #[test]
+        fn test_topology_invariants_under_random_commands(
+            seed in proptest::prelude::any::<u64>(),
+            shape in 0usize..4,
+            ops in proptest::collection::vec(topology_op(), 1..40),
+        ) {
+            use crate::generator::{generate, GeneratorParams, PdrDistribution, Shape};
+            use crate::mock::MockNetwork;
+
+            let shape = [Shape::Ring, Shape::Grid { cols: 4 }, Shape::ErdosRenyi { p: 0.3 }, Shape::BarabasiAlbert { m: 2 }][shape];
+            let params = GeneratorParams { shape, drones: 12, clients: 4, servers: 3, pdr: PdrDistribution::Fixed(0.0), seed };
+            let config = generate(&params);
+            proptest::prop_assume!(config.is_ok());
+
+            let mut harness = MockNetwork::from_topology(&config.unwrap()).into_harness();
+            let sc = &mut harness.sc;
+            proptest::prop_assert_eq!(check_topology_invariants(sc), Ok(()));
+
+            for op in ops {
+                let mut drones = sc.drones.keys().copied().collect::<Vec<_>>();
+                drones.sort();
+                let mut nodes = sc.generic_graph().keys().map(|(id, _)| *id).collect::<Vec<_>>();
+                nodes.sort();
+
+                let before = sc.generic_graph();
+                let result = match &op {
+                    TopologyOp::Crash(_) if drones.is_empty() => continue,
+                    TopologyOp::Crash(drone) => sc.crash(*drone.get(&drones)),
+                    TopologyOp::AddSender(a, b) => sc.add_sender(*a.get(&nodes), *b.get(&nodes)),
+                    TopologyOp::RemoveSender(a, b) => {
+                        let node = *a.get(&nodes);
+                        let mut adjacent = before.iter().find(|((id, _), _)| *id == node).map(|(_, a)| a.iter().copied().collect::<Vec<_>>()).unwrap_or_default();
+                        adjacent.sort();
+                        if adjacent.is_empty() {
+                            continue;
+                        }
+                        sc.remove_sender(node, *b.get(&adjacent))
+                    }
+                };
+
+                match result {
+                    Ok(()) => {
+                        proptest::prop_assert_eq!(check_topology_invariants(sc), Ok(()), "after {:?}", op);
+                    }
+                    Err(_) => {
+                        proptest::prop_assert_eq!(&sc.generic_graph(), &before, "rejected {:?} changed the network view", op);
+                    }
+                }
+            }
+        }
+    }
+
+    // Benchmarks, each fails when its timing goes past a bound. The slow ones are ignored,
+    // run them with `cargo test --release -- --ignored --nocapture bench_`
+
+    fn large_network(drones: usize) -> crate::topology::TopologyConfig {
+        use crate::generator::{generate, GeneratorParams, PdrDistribution, Shape};
+
+        let params = GeneratorParams { shape: Shape::BarabasiAlbert { m: 2 }, drones, clients: 30, servers: 20, pdr: PdrDistribution::Fixed(0.0), seed: 7 };
+        generate(&params).expect("Failed to generate a large network")
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_event_throughput() {
+        use crate::harness::TIMEOUT;
+        use crate::mock::MockNetwork;
+        use common::types::{Event, NodeEvent};
+        use wg_internal::controller::DroneEvent;
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::{Fragment, Packet};
+
+        const EVENTS: usize = 50_000;
+
+        for drones in [50, 200] {
+            let network = MockNetwork::from_topology(&large_network(drones));
+            let (node_events, drone_events) = (network.node_events(), network.drone_events());
+            let mut harness = network.into_harness();
+            let packet = Packet::new_fragment(SourceRoutingHeader::new(vec![1, 2, 3], 1), 1, Fragment::new(0, 1, [0; 128]));
+
+            let start = std::time::Instant::now();
+            for i in 0..EVENTS {
+                if i % 2 == 0 {
+                    node_events.send(Box::new(NodeEvent::PacketSent(packet.clone())) as Box<dyn Event>).unwrap();
+                } else {
+                    drone_events.send(DroneEvent::PacketSent(packet.clone())).unwrap();
+                }
+            }
+            let mut seen = 0;
+            harness.wait_for(TIMEOUT * 6, |_| { seen += 1; (seen == EVENTS / 2).then_some(()) }).expect("Events were not all processed");
+            let elapsed = start.elapsed();
+
+            let rate = EVENTS as f64 / elapsed.as_secs_f64();
+            println!("{} nodes: {EVENTS} events in {elapsed:?}, {rate:.0} events/s", drones + 50);
+            assert!(rate >= 5_000., "{} nodes: only {rate:.0} events/s", drones + 50);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_graph_redraw() {
+        use crate::graph_utils::GraphLayout;
+        use slint::Model;
+
+        for drones in [50, 200] {
+            let mut graph = large_network(drones).to_generic_graph();
+            let mut layout = GraphLayout::default();
+
+            let start = std::time::Instant::now();
+            assert!(layout.update(&graph));
+            let full = start.elapsed();
+
+            // crash a drone: its node and links go, nothing else moves
+            let drone = (1, "drone".to_string());
+            let crashed = graph.remove(&drone).unwrap();
+            for id in crashed {
+                graph.iter_mut().filter(|((other, _), _)| *other == id).for_each(|(_, adjacent)| { adjacent.remove(&1); });
+            }
+            let start = std::time::Instant::now();
+            assert!(!layout.update(&graph), "Removing a node must not lay the graph out again");
+            let incremental = start.elapsed();
+
+            assert_eq!(layout.nodes.row_count(), graph.len());
+            println!("{} nodes: full layout {full:?}, incremental update {incremental:?}", graph.len() + 1);
+            assert!(full < std::time::Duration::from_secs(2), "Laying out {} nodes took {full:?}", graph.len() + 1);
+            assert!(incremental < full, "The incremental update ({incremental:?}) is not faster than a full layout ({full:?})");
+        }
+    }
+
+    #[test]
+    fn test_log_buffer_keeps_the_last_lines() {
+        use crate::logs::{LogBuffer, CAPACITY};
+        use crate::LogMessage;
+        use slint::{Model, VecModel};
+
+        let model = VecModel::<LogMessage>::default();
+        let mut buffer = LogBuffer::default();
+        for i in 0..(CAPACITY as i32 + 10) {
+            buffer.push(LogMessage { message: format!("PACKET SENT {i}").into(), color: slint::Color::from_rgb_u8(123, 132, 150), id: i });
+            if i % 100 == 99 {
+                buffer.flush(&model);
+            }
+        }
+        buffer.flush(&model);
+
+        assert_eq!(model.row_count(), CAPACITY);
+        assert_eq!(model.row_data(0).unwrap().message, "PACKET SENT 10");
+        assert_eq!(model.row_data(CAPACITY - 1).unwrap().message, format!("PACKET SENT {}", CAPACITY + 9));
+
+        // clearing also drops what was not flushed yet
+        buffer.push(LogMessage { message: "PACKET SENT".into(), color: slint::Color::from_rgb_u8(123, 132, 150), id: 0 });
+        buffer.clear(&model);
+        assert_eq!((buffer.flush(&model), model.row_count()), (0, 0));
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_log_flush() {
+        use crate::logs::LogBuffer;
+        use crate::LogMessage;
+        use slint::VecModel;
+
+        let model = VecModel::<LogMessage>::default();
+        let mut buffer = LogBuffer::default();
+
+        let start = std::time::Instant::now();
+        for i in 0..100_000 {
+            buffer.push(LogMessage { message: format!("PACKET SENT {i}").into(), color: slint::Color::from_rgb_u8(123, 132, 150), id: i });
+            if i % 1_000 == 999 {
+                buffer.flush(&model);
+            }
+        }
+        let elapsed = start.elapsed();
+
+        println!("100000 lines flushed in batches of 1000 in {elapsed:?}");
+        assert!(elapsed < std::time::Duration::from_secs(2), "Flushing 100000 lines took {elapsed:?}");
+    }
+
+    #[test]
+    fn test_graph_layout_updates_incrementally() {
+        use crate::graph_utils::GraphLayout;
+        use slint::Model;
+
+        let config = crate::topology::TopologyConfig::from_file("./config/config.toml").unwrap();
+        let mut graph = config.to_generic_graph();
+        let mut layout = GraphLayout::default();
+
+        assert!(layout.update(&graph));
+        assert_eq!(layout.nodes.row_count(), 6);
+        assert_eq!(layout.edges.row_count(), 8, "Links must be drawn once, not once per end");
+        let before = layout.nodes.iter().find(|p| p.id == 6).unwrap();
+
+        graph.get_mut(&(6, "server".to_string())).unwrap().insert(1);
+        graph.get_mut(&(1, "drone".to_string())).unwrap().insert(6);
+        graph.remove(&(5, "client".to_string()));
+        graph.get_mut(&(1, "drone".to_string())).unwrap().remove(&5);
+
+        assert!(!layout.update(&graph));
+        assert_eq!(layout.nodes.row_count(), 5);
+        assert_eq!(layout.edges.row_count(), 8);
+        assert_eq!(layout.nodes.iter().find(|p| p.id == 6).unwrap(), before, "Nodes must stay where they are");
+        assert!(!layout.positions().contains_key(&5));
+        assert!(layout.last_positions().contains_key(&5), "Exports still draw client 5 where it was");
+    }
+
+    fn http(addr: std::net::SocketAddr, method: &str, path: &str, body: &str) -> (u16, serde_json::Value) {
+        use std::io::{Read, Write};
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(stream, "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn test_control_api_drives_the_controller() {
+        use crate::mock::MockNetwork;
+        use std::sync::{Arc, Mutex};
+
+        let config = crate::topology::TopologyConfig::from_file("./config/config.toml").unwrap();
+        let sc = Arc::new(Mutex::new(MockNetwork::from_topology(&config).into_controller()));
+        let changes = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = changes.clone();
+        let server = crate::api::serve(0, sc.clone(), Arc::new(move || { counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst); })).unwrap();
+        let addr = server.addr();
+
+        let (status, topology) = http(addr, "GET", "/topology", "");
+        assert_eq!(status, 200);
+        assert_eq!(topology["drones"].as_array().unwrap().len(), 3);
+        assert_eq!(topology["servers"][0]["neighbors"], serde_json::json!([2, 3]));
+
+        let (status, body) = http(addr, "POST", "/crash", r#"{"node": 2}"#);
+        assert_eq!(status, 400, "Server 6 would be left with one drone");
+        assert!(body["error"].as_str().unwrap().contains("Cannot remove drone 2"));
+
+        assert_eq!(http(addr, "POST", "/pdr", r#"{"node": 1, "pdr": 0.5}"#).0, 200);
+        assert_eq!(http(addr, "POST", "/pdr", r#"{"node": 1}"#).0, 400);
+        assert_eq!(http(addr, "GET", "/nowhere", "").0, 404);
+        assert_eq!(http(addr, "GET", "/topology", "").1["drones"][0]["pdr"], serde_json::json!(0.5));
+        assert_eq!(changes.load(std::sync::atomic::Ordering::SeqCst), 1, "Only accepted commands redraw the UI");
+
+        // only files of the output and config directories can be added
+        let (status, body) = http(addr, "POST", "/web/add", r#"{"node": 6, "path": "./Cargo.toml", "media": false}"#);
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("not in the output or config directory"), "{body}");
+        let (status, body) = http(addr, "POST", "/web/add", r#"{"node": 6, "path": "./config/../src/main.rs", "media": false}"#);
+        assert_eq!(status, 400, "{body}");
+
+        let (status, stats) = http(addr, "GET", "/stats", "");
+        assert_eq!(status, 200);
+        assert_eq!(stats["packets_dropped"], serde_json::json!(0));
+
+        // web pages cannot reach the API, by their origin or through a rebound domain
+        let raw = |request: &str| {
+            use std::io::{Read, Write};
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response.split_whitespace().nth(1).unwrap().parse::<u16>().unwrap()
+        };
+        assert_eq!(raw(&format!("POST /crash HTTP/1.1\r\nHost: {addr}\r\nOrigin: http://example.com\r\nContent-Type: text/plain\r\nContent-Length: 11\r\n\r\n{{\"node\": 1}}")), 403);
+        assert_eq!(raw("GET /topology HTTP/1.1\r\nHost: evil.example.com:7878\r\n\r\n"), 403);
+        assert_eq!(raw("GET /stats HTTP/1.1\r\n\r\n"), 403, "A request without Host is refused too");
+        assert_eq!(raw(&format!("GET /events HTTP/1.1\r\nHost: localhost:{}\r\nOrigin: http://example.com\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n", addr.port())), 403);
+        assert_eq!(changes.load(std::sync::atomic::Ordering::SeqCst), 1, "The refused crash was not run");
+
+        let (mut socket, _) = tungstenite::connect(format!("ws://{addr}/events")).expect("Failed to open the event stream");
+        crate::utils::log_default("control api test line");
+        loop {
+            let message = socket.read().expect("The event stream closed");
+            if message.to_text().unwrap().contains("control api test line") {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_observers_see_events_and_commands() {
+        use crate::harness::TIMEOUT;
+        use crate::mock::MockNetwork;
+        use crate::observer::EventObserver;
+        use crate::EventContext;
+        use common::types::{Event, NodeEvent, NodeType, WebCommand};
+        use std::any::Any;
+        use std::sync::Mutex;
+        use wg_internal::controller::{DroneCommand, DroneEvent};
+        use wg_internal::network::{NodeId, SourceRoutingHeader};
+        use wg_internal::packet::Packet;
+
+        #[derive(Default)]
+        struct Seen {
+            web_events: usize,
+            drone_events: usize,
+            commands: Vec<NodeId>,
+            unknown: usize,
+        }
+
+        struct Recorder(Arc<Mutex<Seen>>);
+
+        impl EventObserver for Recorder {
+            fn on_node_event(&mut self, event: &dyn Any, _: &EventContext) -> bool {
+                if event.is::<WebEvent>() {
+                    self.0.lock().unwrap().web_events += 1;
+                }
+                false
+            }
+
+            fn on_drone_event(&mut self, _: &DroneEvent, _: &EventContext) {
+                self.0.lock().unwrap().drone_events += 1;
+            }
+
+            fn on_command(&mut self, node: NodeId, _: &dyn Any, _: &EventContext) {
+                self.0.lock().unwrap().commands.push(node);
+            }
+        }
+
+        struct Unknown(Arc<Mutex<Seen>>);
+
+        impl EventObserver for Unknown {
+            fn on_node_event(&mut self, _: &dyn Any, _: &EventContext) -> bool {
+                self.0.lock().unwrap().unknown += 1;
+                true
+            }
+        }
+
+        let network = MockNetwork::new()
+            .server(6, NodeType::TextServer, Box::new(|command| match command.downcast_ref::<WebCommand>() {
+                Some(WebCommand::GetTextFiles) => vec![Box::new(WebEvent::TextFiles { notification_from: 6, files: vec![] }) as Box<dyn Event>],
+                _ => vec![],
+            }))
+            .drone(1, 0.1, Box::new(|command| match command {
+                DroneCommand::SetPacketDropRate(_) => vec![DroneEvent::PacketSent(Packet::new_ack(SourceRoutingHeader::new(vec![6, 1], 1), 7, 0))],
+                _ => vec![],
+            }));
+        let mut harness = network.into_harness();
+
+        let seen = Arc::new(Mutex::new(Seen::default()));
+        harness.sc.observe(Box::new(Recorder(seen.clone())));
+        harness.sc.observers.lock().unwrap().set_fallback(Box::new(Unknown(seen.clone())));
+
+        harness.web(6, WebCommand::GetTextFiles);
+        harness.wait_web(TIMEOUT, |event| matches!(event, WebEvent::TextFiles { .. }).then_some(())).expect("Server 6 did not list its files");
+        harness.sc.set_packet_drop_rate(1, 0.5).unwrap();
+        harness.wait_drone(TIMEOUT, |event| matches!(event, DroneEvent::PacketSent(_)).then_some(())).expect("Drone 1 did not send the ack");
+
+        // the built-in observers still run alongside the registered one
+        assert_eq!(harness.sc.stats.lock().unwrap().packets_sent, 1);
+
+        SimulationController::process_node_event(&"not an event", Weak::default(), &harness.sc.event_context());
+        SimulationController::process_node_event(&NodeEvent::NodeRemoved(3), Weak::default(), &harness.sc.event_context());
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.web_events, 1);
+        assert_eq!(seen.drone_events, 1);
+        assert_eq!(seen.commands, vec![6, 1]);
+        assert_eq!(seen.unknown, 1, "Only the event of an unknown type goes to the fallback");
+    }
+
+    #[test]
+    fn test_unknown_events_are_numbered_and_counted() {
+        use crate::mock::MockNetwork;
+        use crate::observer::{EventObserver, UnknownEventObserver};
+
+        let sc = MockNetwork::new().into_controller();
+        let ctx = sc.event_context();
+        let logs = utils::subscribe_logs();
+
+        let mut observer = UnknownEventObserver::default();
+        observer.on_node_event(&"not an event", &ctx);
+        observer.on_node_event(&42_u32, &ctx);
+        observer.on_node_event(&"still not an event", &ctx);
+
+        let lines = logs.try_iter()
+            .map(|entry| entry.message.to_string())
+            .filter(|line| line.contains("UNKNOWN EVENT"))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3, "{lines:?}");
+        assert!(lines[0].contains("unknown type #1 (1 so far)"), "{}", lines[0]);
+        assert!(lines[1].contains("unknown type #2 (1 so far)"), "{}", lines[1]);
+        assert!(lines[2].contains("unknown type #1 (2 so far)"), "{}", lines[2]);
+    }
+
+    #[test]
+    fn test_metrics_in_openmetrics_format() {
+        use crate::harness::TIMEOUT;
+        use crate::mock::MockNetwork;
+        use common::types::{Event, Message, NodeType, TextFile};
+        use wg_internal::controller::DroneEvent;
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::{Fragment, Packet};
+
+        let fragment = Packet::new_fragment(SourceRoutingHeader::new(vec![5, 1, 6], 1), 42, Fragment::new(0, 1, [0; 128]));
+        let network = MockNetwork::new()
+            .drone(1, 0.25, MockNetwork::silent_drone())
+            .client(5, NodeType::ChatClient, MockNetwork::silent_node())
+            .server(6, NodeType::TextServer, MockNetwork::silent_node());
+        let drone_events = network.drone_events();
+        let node_events = network.node_events();
+        let mut harness = network.into_harness();
+
+        drone_events.send(DroneEvent::PacketDropped(fragment.clone())).unwrap();
+        drone_events.send(DroneEvent::ControllerShortcut(fragment.clone())).unwrap();
+        harness.wait_drone(TIMEOUT, |event| matches!(event, DroneEvent::ControllerShortcut(_)).then_some(())).expect("The shortcut was not processed");
+        node_events.send(Box::new(ChatEvent::MessageReceived { notification_from: 5, msg: Message { from: 3, to: 5, text: "hi".to_string() } }) as Box<dyn Event>).unwrap();
+        node_events.send(Box::new(WebEvent::TextFile { notification_from: 5, file: TextFile::new("a".to_string(), "b".to_string(), vec![]) }) as Box<dyn Event>).unwrap();
+        harness.wait_web(TIMEOUT, |event| matches!(event, WebEvent::TextFile { .. }).then_some(())).expect("The file event was not processed");
+
+        let text = crate::metrics::render(&harness.sc);
+        for line in [
+            "# TYPE simulation_drone_packets_dropped counter",
+            "simulation_drone_packets_dropped_total{drone=\"1\"} 1",
+            "simulation_drone_packets_sent_total{drone=\"1\"} 0",
+            "simulation_drone_controller_shortcuts_total{drone=\"1\"} 1",
+            "simulation_controller_shortcuts_total{packet_type=\"msg_fragment\"} 1",
+            "simulation_drone_pdr{drone=\"1\"} 0.25",
+            "simulation_nodes{type=\"drone\"} 1",
+            "simulation_chat_messages_delivered_total 1",
+            "simulation_files_transferred_total{kind=\"text\"} 1",
+            "simulation_pending_requests 0",
+        ] {
+            assert!(text.lines().any(|l| l == line), "Missing {line} in\n{text}");
+        }
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn test_graph_exports() {
+        use crate::export::{ExportGraph, ExportNode, Format};
+
+        let config = crate::topology::TopologyConfig::from_file("./config/config.toml").unwrap();
+        let mut graph = ExportGraph::of_config(&config);
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(graph.edges.len(), 8);
+
+        graph.traffic.insert((2, 6), 5);
+        graph.nodes.push(ExportNode { id: 7, kind: "drone".to_string(), pdr: None, crashed: true });
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("graph network {"));
+        assert!(dot.contains("    3 [label=\"3\", shape=circle, fillcolor=\"#805f2f\", type=\"drone\", pdr=\"0.14\", crashed=\"false\"];"));
+        assert!(dot.contains("    7 [label=\"7\", shape=circle, fillcolor=\"#4c566a\", type=\"drone\", crashed=\"true\"];"));
+        assert!(dot.contains("    2 -- 6 [packets=\"5\", label=\"5\"];"));
+        assert!(dot.contains("    1 -- 5;"));
+
+        let graphml = graph.to_graphml();
+        assert_eq!(graphml.matches("<node id=").count(), 7);
+        assert_eq!(graphml.matches("<edge ").count(), 8);
+        assert!(graphml.contains("<data key=\"crashed\">true</data>"));
+        assert!(graphml.contains("<data key=\"packets\">5</data>"));
+
+        // crashed nodes are drawn like in DOT and GraphML, even without a position
+        let svg = graph.to_svg(&graph.layout());
+        assert_eq!(svg.matches("<circle").count(), 7);
+        assert_eq!(svg.matches("<line").count(), 8);
+        assert!(svg.contains("fill=\"#4c566a\""));
+        assert_eq!(graph.to_svg(&std::collections::HashMap::new()).matches("<circle").count(), 7);
+        assert!(svg.contains(">5</text>"));
+
+        let args = ["config/config.toml", "--out", "graph.svg"].map(String::from);
+        assert_eq!(crate::cli::parse_export(&args).unwrap(), ("config/config.toml".to_string(), Format::Svg, Some("graph.svg".to_string())));
+        assert!(crate::cli::parse_export(&["--format".to_string(), "png".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_timeline_records_lifecycle_moments() {
+        use crate::harness::TIMEOUT;
+        use crate::mock::MockNetwork;
+        use crate::timeline::{MomentKind, Timeline};
+        use crate::LogMessage;
+        use common::types::{Event, NodeEvent};
+        use slint::{Color, VecModel};
+
+        let config = crate::topology::TopologyConfig::from_file("./config/config.toml").unwrap();
+        let network = MockNetwork::from_topology(&config);
+        let node_events = network.node_events();
+        let mut harness = network.into_harness();
+
+        harness.sc.set_packet_drop_rate(1, 0.5).unwrap();
+        harness.sc.add_sender(6, 1).unwrap();
+        harness.sc.crash(2).unwrap();
+        node_events.send(Box::new(NodeEvent::FloodStarted(3, 4)) as Box<dyn Event>).unwrap();
+        node_events.send(Box::new(ChatEvent::MessageSent { notification_from: 4, to: 5 }) as Box<dyn Event>).unwrap();
+        harness.wait_chat(TIMEOUT, |event| matches!(event, ChatEvent::MessageSent { .. }).then_some(())).expect("The chat event was not processed");
+
+        let timeline = harness.sc.timeline.lock().unwrap();
+        let kinds = timeline.moments().iter().map(|m| (m.kind, m.nodes[0])).collect::<Vec<_>>();
+        assert_eq!(kinds[0], (MomentKind::PdrChanged, 1));
+        assert_eq!(kinds[1], (MomentKind::SenderAdded, 6));
+        // the neighbours of drone 2 stop sending to it before it crashes
+        assert_eq!(kinds.iter().filter(|(kind, _)| *kind == MomentKind::SenderRemoved).count(), 4);
+        assert!(kinds.contains(&(MomentKind::Crash, 2)));
+        assert!(kinds.contains(&(MomentKind::FloodStarted, 4)));
+        assert_eq!(kinds.last(), Some(&(MomentKind::MessageSent, 4)));
+        assert!(timeline.moments().iter().zip(timeline.moments().iter().skip(1)).all(|(a, b)| a.at <= b.at && a.id < b.id));
+        assert_eq!(timeline.lanes(), vec![1, 2, 3, 4, 6]);
+
+        let moment = timeline.moments().back().unwrap();
+        assert_eq!(timeline.moment(moment.id).map(|m| m.id), Some(moment.id));
+        let line = |offset: i32, text: &str| LogMessage { message: text.into(), color: Color::default(), id: moment.log_id + offset };
+        // the same line for an earlier and a later message, and a line from another thread in between
+        let logs = VecModel::from(vec![
+            line(-5, "NOTIFICATION FROM: 4, MESSAGE SENT TO: 5"),
+            line(-1, "NOTIFICATION FROM: 4, MESSAGE SENT TO: 5"),
+            line(0, "PACKET SENT: ..."),
+            line(1, "NOTIFICATION FROM: 4, MESSAGE SENT TO: 5"),
+        ]);
+        assert_eq!(Timeline::log_row(moment, &logs), Some(1));
+        let unlogged = crate::timeline::Moment { needle: None, ..moment.clone() };
+        assert_eq!(Timeline::log_row(&unlogged, &logs), Some(2));
+
+        // the oldest moments go first, ids keep growing
+        let mut timeline = Timeline::default();
+        for i in 0..=crate::timeline::CAPACITY {
+            timeline.record(vec![(i % 2) as u8], MomentKind::MessageSent, format!("{i}"), None);
+        }
+        assert_eq!(timeline.moments().len(), crate::timeline::CAPACITY);
+        assert!(timeline.moment(0).is_none());
+        assert_eq!(timeline.moment(1).map(|m| m.label.as_str()), Some("1"));
+        timeline.restart();
+        assert!(timeline.moments().is_empty() && timeline.lanes().is_empty());
+        timeline.record(vec![3], MomentKind::Crash, "3".to_string(), None);
+        assert_eq!(timeline.moments().back().map(|m| m.id), Some(crate::timeline::CAPACITY as u64 + 1));
+    }
+
+    #[test]
+    fn test_capture_round_trip_with_filters() {
+        use crate::capture::{self, CaptureWriter, Disposition, Filter, Payload};
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::{Fragment, Packet};
+
+        let path = std::env::temp_dir().join(format!("capture_{}.jsonl", std::process::id()));
+        let mut data = [0; 128];
+        data[..3].copy_from_slice(b"abc");
+        let fragment = Packet::new_fragment(SourceRoutingHeader::new(vec![5, 1, 2, 6], 2), 42, Fragment { fragment_index: 0, total_n_fragments: 2, length: 3, data });
+        let ack = Packet::new_ack(SourceRoutingHeader::new(vec![6, 2, 1, 5], 1), 42, 0);
+        let other = Packet::new_fragment(SourceRoutingHeader::new(vec![4, 3, 6], 1), 7, Fragment::new(1, 2, [0; 128]));
+
+        {
+            let mut writer = CaptureWriter::create(&path).unwrap();
+            writer.write(&fragment, Some(1), Disposition::Sent).unwrap();
+            writer.write(&ack, Some(2), Disposition::Dropped).unwrap();
+            writer.write(&other, Some(3), Disposition::Shortcut).unwrap();
+            assert_eq!(writer.written(), 3);
+        }
+
+        let all = capture::read(&path, &Filter::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].observer, Some(1));
+        assert_eq!((all[0].source(), all[0].destination(), all[0].hop_index), (Some(5), Some(6), 2));
+        assert!(matches!(&all[0].payload, Payload::MsgFragment { length, data, .. } if *length == 3 && data == "616263"));
+        assert!(all.windows(2).all(|w| w[0].elapsed_ms <= w[1].elapsed_ms));
+
+        let ids = |filter: Filter| capture::read(&path, &filter).unwrap().iter().map(|r| r.observer.unwrap()).collect::<Vec<_>>();
+        assert_eq!(ids(Filter { session: Some(42), ..Default::default() }), vec![1, 2]);
+        assert_eq!(ids(Filter { packet_type: Some("ack".to_string()), ..Default::default() }), vec![2]);
+        assert_eq!(ids(Filter { source: Some(4), ..Default::default() }), vec![3]);
+        assert_eq!(ids(Filter { destination: Some(6), ..Default::default() }), vec![1, 3]);
+        assert_eq!(ids(Filter { hop: Some(2), ..Default::default() }), vec![1, 2]);
+        assert_eq!(ids(Filter { hop: Some(1), disposition: Some(Disposition::Dropped), ..Default::default() }), vec![2]);
+
+        let args = ["capture.jsonl", "--session", "42", "--type", "nack", "--json"].map(String::from);
+        let (file, filter, json) = crate::cli::parse_capture(&args).unwrap();
+        assert_eq!((file.as_str(), filter.session, filter.packet_type.as_deref(), json), ("capture.jsonl", Some(42), Some("nack"), true));
+        assert!(crate::cli::parse_capture(&["--type".to_string(), "ping".to_string()]).is_err());
+
+        std::fs::write(&path, "{\"broken\": true}\n").unwrap();
+        assert!(capture::read(&path, &Filter::default()).unwrap_err().to_string().contains("line 1"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_inspector_decodes_and_filters_packets() {
+        use crate::harness::TIMEOUT;
+        use crate::inspector;
+        use crate::mock::MockNetwork;
+        use common::types::NodeType;
+        use wg_internal::controller::DroneEvent;
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::{Fragment, Nack, NackType, Packet};
+
+        let mut data = [0; 128];
+        data[..5].copy_from_slice(b"hi\x00\x7fA");
+        let fragment = Packet::new_fragment(SourceRoutingHeader::new(vec![5, 1, 6], 2), 42, Fragment { fragment_index: 1, total_n_fragments: 3, length: 5, data });
+        let nack = Packet::new_nack(SourceRoutingHeader::new(vec![1, 5], 0), 42, Nack { fragment_index: 1, nack_type: NackType::Dropped });
+        let network = MockNetwork::new()
+            .drone(1, 0.25, MockNetwork::silent_drone())
+            .client(5, NodeType::ChatClient, MockNetwork::silent_node())
+            .server(6, NodeType::TextServer, MockNetwork::silent_node());
+        let drone_events = network.drone_events();
+        let mut harness = network.into_harness();
+
+        drone_events.send(DroneEvent::PacketSent(fragment)).unwrap();
+        drone_events.send(DroneEvent::PacketDropped(nack)).unwrap();
+        harness.wait_drone(TIMEOUT, |event| matches!(event, DroneEvent::PacketDropped(_)).then_some(())).expect("The drop was not processed");
+
+        let inspector = harness.sc.inspector.clone();
+        let mut inspector = inspector.lock().unwrap();
+        let ids = |inspector: &inspector::Inspector| inspector.matching().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(ids(&inspector), vec![0, 1]);
+
+        let sent = inspector::describe(0, inspector.get(0).unwrap());
+        assert!(sent.contains("sent by 1"));
+        assert!(sent.contains("Route:       5 > 1 > [6]"));
+        assert!(sent.contains("Fragment:    1 of 3"));
+        assert!(sent.contains("0000  68 69 00 7f 41"));
+        assert!(sent.contains("|hi..A|"));
+        let dropped = inspector::describe(1, inspector.get(1).unwrap());
+        assert!(dropped.contains("dropped by 1"));
+        assert!(dropped.contains("Nack type:   Dropped"));
+
+        inspector.set_filter(inspector::parse_filter("42", "NACK", "", "", "", "").unwrap());
+        assert_eq!(ids(&inspector), vec![1]);
+        inspector.set_filter(inspector::parse_filter("", "", "5", "6", "1", "sent").unwrap());
+        assert_eq!(ids(&inspector), vec![0]);
+        inspector.set_filter(inspector::parse_filter("", "", "", "", "", "shortcut").unwrap());
+        assert!(ids(&inspector).is_empty());
+        assert!(inspector::parse_filter("x", "", "", "", "", "").is_err());
+        assert!(inspector::parse_filter("", "ping", "", "", "", "").is_err());
+
+        // a new run keeps the filter but not the packets
+        inspector.restart();
+        assert!(inspector.get(0).is_none());
+        assert!(ids(&inspector).is_empty());
+    }
+
+    #[test]
+    fn test_shortcut_policies_and_failures() {
+        use crate::harness::TIMEOUT;
+        use crate::mock::MockNetwork;
+        use crate::shortcut::Policy;
+        use wg_internal::controller::DroneEvent;
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::Packet;
+
+        let ack_to = |destination, session| Packet::new_ack(SourceRoutingHeader::new(vec![2, destination], 1), session, 0);
+        let config = crate::topology::TopologyConfig::from_file("./config/config.toml").unwrap();
+        let network = MockNetwork::from_topology(&config);
+        let drone_events = network.drone_events();
+        let server = network.packets(6);
+        let mut harness = network.into_harness();
+        let shortcut = |harness: &mut crate::harness::Harness, packet: Packet| {
+            let session = packet.session_id;
+            drone_events.send(DroneEvent::ControllerShortcut(packet)).unwrap();
+            harness.wait_drone(TIMEOUT, |event| matches!(event, DroneEvent::ControllerShortcut(p) if p.session_id == session).then_some(())).expect("The shortcut was not processed");
+        };
+
+        shortcut(&mut harness, ack_to(6, 1));
+        assert_eq!(server.try_recv().ok().map(|p| p.session_id), Some(1));
+
+        harness.sc.shortcuts.lock().unwrap().policy = Policy::Drop;
+        shortcut(&mut harness, ack_to(6, 2));
+        assert!(server.try_recv().is_err(), "A dropped shortcut must not reach its destination");
+
+        harness.sc.shortcuts.lock().unwrap().policy = Policy::parse("delay:50").unwrap();
+        let sent = std::time::Instant::now();
+        shortcut(&mut harness, ack_to(6, 3));
+        assert_eq!(server.recv_timeout(TIMEOUT).ok().map(|p| p.session_id), Some(3));
+        assert!(sent.elapsed() >= std::time::Duration::from_millis(50));
+
+        harness.sc.shortcuts.lock().unwrap().policy = Policy::Deliver;
+        harness.sc.shutdown(4).unwrap();
+        shortcut(&mut harness, ack_to(4, 4));
+        shortcut(&mut harness, ack_to(9, 5));
+
+        let shortcuts = harness.sc.shortcuts.lock().unwrap();
+        assert_eq!(shortcuts.count("delivered", "ack"), 1);
+        assert_eq!(shortcuts.count("dropped", "ack"), 1);
+        assert_eq!(shortcuts.count("delayed", "ack"), 1);
+        assert_eq!(shortcuts.count("failed", "ack"), 2);
+        drop(shortcuts);
+        assert!(crate::metrics::render(&harness.sc).contains("simulation_controller_shortcut_outcomes_total{outcome=\"failed\",packet_type=\"ack\"} 2"));
+
+        assert_eq!(Policy::parse("delay:250").unwrap().to_string(), "delay:250");
+        assert!(Policy::parse("delay").is_err());
+        assert!(Policy::parse("sometimes").is_err());
+    }
+
+    #[test]
+    fn test_link_faults_delay_duplicate_and_round_trip() {
+        use crate::faults::{LinkConfig, LinkFault};
+        use crate::harness::TIMEOUT;
+        use crate::mock::MockNetwork;
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::Packet;
+        use std::time::{Duration, Instant};
+
+        let config = crate::topology::TopologyConfig::from_file("./config/config.toml").unwrap();
+        let network = MockNetwork::from_topology(&config);
+        let server = network.packets(6);
+        let mut harness = network.into_harness();
+
+        let fault = LinkFault::from_fields("50", "", "", "100", "").unwrap();
+        assert_eq!(fault, LinkFault { latency_ms: 50, duplicate: 1., ..LinkFault::default() });
+        harness.sc.set_link_fault(6, 2, fault).unwrap();
+        assert_eq!(harness.sc.link_faults().get(&(2, 6)), Some(&fault));
+
+        let sent = Instant::now();
+        let ack = Packet::new_ack(SourceRoutingHeader::new(vec![2, 6], 1), 7, 0);
+        harness.sc.link_sender(2, 6).unwrap().send(ack).unwrap();
+        for _ in 0..2 {
+            assert_eq!(server.recv_timeout(TIMEOUT).ok().map(|p| p.session_id), Some(7));
+        }
+        assert!(sent.elapsed() >= Duration::from_millis(50));
+        assert!(server.recv_timeout(Duration::from_millis(100)).is_err(), "Only one duplicate per packet");
+
+        // the faults are saved with the network and read back
+        let saved = crate::topology::TopologyConfig::from_simulation_controller(&harness.sc);
+        assert_eq!(saved.link, vec![LinkConfig { between: [2, 6], fault }]);
+        let reloaded = crate::topology::TopologyConfig::from_toml(&saved.to_toml()).unwrap();
+        assert_eq!(reloaded.link, saved.link);
+
+        // a cut link loses everything until it heals
+        harness.sc.set_link_fault(2, 6, LinkFault { cut_ms: 60_000, ..LinkFault::default() }).unwrap();
+        let ack = Packet::new_ack(SourceRoutingHeader::new(vec![2, 6], 1), 8, 0);
+        harness.sc.link_sender(2, 6).unwrap().send(ack).unwrap();
+        assert!(server.recv_timeout(Duration::from_millis(100)).is_err(), "A cut link must lose its packets");
+
+        // clearing the faults gives the nodes their direct channels back
+        harness.sc.set_link_fault(2, 6, LinkFault::default()).unwrap();
+        assert!(harness.sc.link_faults().is_empty());
+        let ack = Packet::new_ack(SourceRoutingHeader::new(vec![2, 6], 1), 9, 0);
+        harness.sc.link_sender(2, 6).unwrap().send(ack).unwrap();
+        assert_eq!(server.try_recv().ok().map(|p| p.session_id), Some(9));
+
+        assert!(harness.sc.set_link_fault(5, 6, fault).is_err(), "5 and 6 are not linked");
+        assert!(LinkFault::from_fields("fast", "", "", "", "").is_err());
+        assert!(LinkFault::from_fields("", "", "150", "", "").is_err());
+        // huge delays would overflow `Instant` in the forwarding threads
+        assert!(LinkFault::from_fields("", "", "", "", &u64::MAX.to_string()).is_err());
+        assert!(LinkFault::from_fields("", &u64::MAX.to_string(), "", "", "").is_err());
+        assert!(LinkFault { latency_ms: crate::faults::MAX_MS + 1, ..Default::default() }.check().is_err());
+        assert!(LinkFault { latency_ms: crate::faults::MAX_MS, jitter_ms: crate::faults::MAX_MS, cut_ms: crate::faults::MAX_MS, ..Default::default() }.check().is_ok());
+    }
+
+    #[test]
+    fn test_client_crash_keeps_dead_node_until_removed() {
+        use crate::mock::MockNetwork;
+        use crate::shortcut::{Failure, Outcome};
+        use crate::timeline::MomentKind;
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::Packet;
+
+        let config = crate::topology::TopologyConfig::from_file("./config/config.toml").unwrap();
+        let mut harness = MockNetwork::from_topology(&config).into_harness();
+        let moments = |harness: &crate::harness::Harness, kind: MomentKind| harness.sc.timeline.lock().unwrap().moments().iter()
+            .filter(|m| m.kind == kind)
+            .map(|m| m.nodes.clone())
+            .collect::<Vec<_>>();
+
+        // client 5 only has drone 1, a shutdown would be refused but a crash just happens
+        harness.sc.crash(5).expect("Client 5 could not be crashed");
+        assert!(!harness.sc.clients.contains_key(&5));
+        assert!(!harness.sc.packet_senders.contains_key(&5));
+        assert_eq!(moments(&harness, MomentKind::Crash), vec![vec![5]], "The timeline observer sees the Shutdown as a crash");
+        assert!(moments(&harness, MomentKind::Shutdown).is_empty());
+        assert!(moments(&harness, MomentKind::SenderRemoved).is_empty(), "Neighbours must not be told about a crash");
+
+        // still drawn, but out of the running network
+        assert!(harness.sc.network_view.nodes.iter().any(|n| n.get_id() == 5));
+        assert!(!harness.sc.generic_graph().keys().any(|(id, _)| *id == 5));
+        assert!(harness.sc.generic_graph()[&(1, "drone".to_string())].iter().all(|id| *id != 5));
+        let saved = crate::topology::TopologyConfig::from_simulation_controller(&harness.sc);
+        assert!(saved.client.iter().all(|c| c.id != 5));
+        assert!(saved.drone.iter().all(|d| !d.connected_node_ids.contains(&5)));
+        let export = crate::export::ExportGraph::of_controller(&harness.sc);
+        assert!(export.nodes.iter().any(|n| n.id == 5 && n.kind == "client" && n.crashed));
+        assert_eq!(crate::api::topology(&harness.sc)["crashed"], serde_json::json!([5]));
+
+        let ack = Packet::new_ack(SourceRoutingHeader::new(vec![1, 5], 1), 1, 0);
+        assert_eq!(harness.sc.shortcuts.lock().unwrap().route(&ack), Outcome::Failed(Failure::Gone(5)));
+        assert!(harness.sc.add_sender(3, 5).is_err());
+
+        harness.sc.remove_dead(5).expect("Client 5 could not be removed");
+        assert!(!harness.sc.network_view.nodes.iter().any(|n| n.get_id() == 5));
+        assert_eq!(moments(&harness, MomentKind::SenderRemoved), vec![vec![1, 5]]);
+        assert!(harness.sc.remove_dead(5).is_err());
+        assert!(harness.sc.remove_dead(4).is_err(), "Client 4 did not crash");
+        assert!(harness.sc.crash(9).is_err());
+    }
+
+    #[test]
+    fn test_capture_is_flushed_when_the_simulation_stops() {
+        use crate::capture::{self, CaptureWriter, Disposition, Filter};
+        use crate::observer::EventObserver;
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::Packet;
+
+        let path = std::env::temp_dir().join(format!("capture_stop_{}.jsonl", std::process::id()));
+        let ack = Packet::new_ack(SourceRoutingHeader::new(vec![6, 2, 1, 5], 1), 42, 0);
+
+        let mut writer = CaptureWriter::create(&path).unwrap();
+        writer.write(&ack, Some(2), Disposition::Sent).unwrap();
+        writer.on_stop(&SimulationController::default().event_context());
+        assert_eq!(capture::read(&path, &Filter::default()).unwrap().len(), 1);
+        assert!(!writer.is_failed());
+
+        drop(writer);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_faulty_link_stops_forwarding() {
+        use crate::faults::{FaultyLink, LinkFault};
+        use crossbeam::channel::unbounded;
+        use wg_internal::network::SourceRoutingHeader;
+        use wg_internal::packet::Packet;
+        use std::time::{Duration, Instant};
+
+        let (to_a, _at_a) = unbounded();
+        let (to_b, at_b) = unbounded();
+        let mut link = FaultyLink::new(1, to_a, 2, to_b, LinkFault { latency_ms: 60_000, ..LinkFault::default() });
+        let into_b = link.sender_to(2).unwrap();
+        let ack = Packet::new_ack(SourceRoutingHeader::new(vec![1, 2], 1), 7, 0);
+        into_b.send(ack.clone()).unwrap();
+
+        link.stop();
+        assert!(link.is_stopped());
+        // the thread drops its end of the channel once it stopped
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while into_b.send(ack.clone()).is_ok() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(into_b.send(ack).is_err(), "A stopped link must not forward");
+        assert!(at_b.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_invalid_config_is_not_started() {
+        let mut sc = SimulationController::default();
+        let errors = sc.launch("./config/test_config.toml").err().expect("A network split in two must not start");
+        assert!(errors.iter().any(|e| e.contains("not connected")), "{errors:?}");
+        assert!(!*sc.is_running.read().unwrap());
+        assert!(sc.network_initializer.is_none());
+
+        let errors = sc.launch("./config/missing.toml").err().expect("A missing config must not start");
+        assert!(errors[0].contains("cannot read"), "{errors:?}");
+    }
@@ -0,0 +1,294 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use slint::{Color, Model, VecModel};
+
+use common::types::{ChatEvent, NodeCommand, NodeEvent, WebEvent};
+use wg_internal::controller::DroneCommand;
+use wg_internal::network::NodeId;
+
+use crate::observer::EventObserver;
+use crate::{utils, EventContext, LogMessage, MainWindow, TimelineLane, TimelineMark};
+
+/// Most moments kept, the oldest ones go first.
+pub const CAPACITY: usize = 5000;
+
+/// Log lines looked at before a moment for the one with its needle, lines logged by other threads may come in between.
+const LOOKBACK: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MomentKind {
+    Crash,
+    Shutdown,
+    PdrChanged,
+    SenderAdded,
+    SenderRemoved,
+    FloodStarted,
+    MessageSent,
+    MessageReceived,
+    FileSent,
+    FileReceived,
+}
+
+impl MomentKind {
+    fn color(self) -> Color {
+        match self {
+            MomentKind::Crash | MomentKind::Shutdown => Color::from_rgb_u8(255, 110, 94),
+            MomentKind::PdrChanged => Color::from_rgb_u8(241, 255, 94),
+            MomentKind::SenderAdded | MomentKind::SenderRemoved => Color::from_rgb_u8(94, 161, 255),
+            MomentKind::FloodStarted => Color::from_rgb_u8(255, 94, 241),
+            MomentKind::MessageSent | MomentKind::MessageReceived => Color::from_rgb_u8(94, 255, 108),
+            MomentKind::FileSent | MomentKind::FileReceived => Color::from_rgb_u8(255, 189, 94),
+        }
+    }
+}
+
+/// Something that happened to a node, shown on its lane.
+#[derive(Debug, Clone)]
+pub struct Moment {
+    /// Increasing with every moment, also across restarts.
+    pub id: u64,
+    pub at: Duration,
+    pub logged_at: DateTime<Local>,
+    /// The lane is the first node, the others are highlighted with it.
+    pub nodes: Vec<NodeId>,
+    pub kind: MomentKind,
+    pub label: String,
+    /// Part of the log line written for the same event, if there is one.
+    pub needle: Option<String>,
+    /// Id of the last log line written before the moment, see `utils::last_log_id`.
+    pub log_id: i32,
+}
+
+/// Lifecycle and traffic moments of every node since the network started.
+#[derive(Debug)]
+pub struct Timeline {
+    start: Instant,
+    moments: VecDeque<Moment>,
+    next_id: u64,
+    /// Moments on the lane of each node.
+    lanes: BTreeMap<NodeId, usize>,
+    changed: bool,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self { start: Instant::now(), moments: VecDeque::new(), next_id: 0, lanes: BTreeMap::new(), changed: true }
+    }
+}
+
+impl Timeline {
+
+    /// Forgets everything, times now count from here.
+    pub fn restart(&mut self) {
+        *self = Self { next_id: self.next_id, ..Self::default() };
+    }
+
+    pub fn moments(&self) -> &VecDeque<Moment> {
+        &self.moments
+    }
+
+    pub fn moment(&self, id: u64) -> Option<&Moment> {
+        let first = self.moments.front()?.id;
+        self.moments.get(usize::try_from(id.checked_sub(first)?).ok()?)
+    }
+
+    pub fn record(&mut self, nodes: Vec<NodeId>, kind: MomentKind, label: String, needle: Option<String>) {
+        self.record_at(Instant::now(), nodes, kind, label, needle);
+    }
+
+    pub fn record_at(&mut self, now: Instant, nodes: Vec<NodeId>, kind: MomentKind, label: String, needle: Option<String>) {
+        if self.moments.len() == CAPACITY {
+            if let Some(lane) = self.moments.pop_front().and_then(|m| m.nodes.first().copied()) {
+                if let Some(count) = self.lanes.get_mut(&lane) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.lanes.remove(&lane);
+                    }
+                }
+            }
+        }
+        if let Some(lane) = nodes.first() {
+            *self.lanes.entry(*lane).or_default() += 1;
+        }
+
+        let at = now.saturating_duration_since(self.start);
+        self.moments.push_back(Moment { id: self.next_id, at, logged_at: Local::now(), nodes, kind, label, needle, log_id: utils::last_log_id() });
+        self.next_id += 1;
+        self.changed = true;
+    }
+
+    /// Nodes with at least one moment, in id order.
+    pub fn lanes(&self) -> Vec<NodeId> {
+        self.lanes.keys().copied().collect()
+    }
+
+    /// `crashed` tells a `Shutdown` the controller sent to crash `node` from a regular one.
+    pub fn on_command(&mut self, node: NodeId, command: &dyn Any, crashed: bool) {
+        if let Some(command) = command.downcast_ref::<DroneCommand>() {
+            match command {
+                DroneCommand::Crash => self.record(vec![node], MomentKind::Crash, format!("Drone {node} crashed"), None),
+                DroneCommand::SetPacketDropRate(pdr) => self.record(vec![node], MomentKind::PdrChanged, format!("PDR of {node} set to {:.0}%", pdr * 100.), None),
+                DroneCommand::AddSender(other, _) => self.record(vec![node, *other], MomentKind::SenderAdded, format!("{node} can send to {other}"), None),
+                DroneCommand::RemoveSender(other) => self.record(vec![node, *other], MomentKind::SenderRemoved, format!("{node} stopped sending to {other}"), None),
+            }
+        } else if let Some(command) = command.downcast_ref::<NodeCommand>() {
+            match command {
+                NodeCommand::Shutdown if crashed => self.record(vec![node], MomentKind::Crash, format!("Node {node} crashed"), None),
+                NodeCommand::Shutdown => self.record(vec![node], MomentKind::Shutdown, format!("Node {node} shut down"), None),
+                NodeCommand::AddSender(other, _) => self.record(vec![node, *other], MomentKind::SenderAdded, format!("{node} can send to {other}"), None),
+                NodeCommand::RemoveSender(other) => self.record(vec![node, *other], MomentKind::SenderRemoved, format!("{node} stopped sending to {other}"), None),
+                _ => {},
+            }
+        }
+    }
+
+    /// Records the events worth a mark, returns whether `event` is a type it knows.
+    pub fn on_node_event(&mut self, event: &dyn Any) -> bool {
+        if let Some(event) = event.downcast_ref::<NodeEvent>() {
+            if let NodeEvent::FloodStarted(counter, node) = event {
+                self.record(vec![*node], MomentKind::FloodStarted, format!("Flood {counter} started by {node}"), Some(format!("NOTIFICATION FROM: {node}, FLOOD STARTED")));
+            }
+        } else if let Some(event) = event.downcast_ref::<ChatEvent>() {
+            match event {
+                ChatEvent::MessageSent { notification_from, to } => self.record(
+                    vec![*notification_from, *to],
+                    MomentKind::MessageSent,
+                    format!("{notification_from} sent a message to {to}"),
+                    Some(format!("NOTIFICATION FROM: {notification_from}, MESSAGE SENT TO: {to}")),
+                ),
+                ChatEvent::MessageReceived { notification_from, msg } => self.record(
+                    vec![*notification_from, msg.from],
+                    MomentKind::MessageReceived,
+                    format!("{notification_from} received a message from {}", msg.from),
+                    Some(format!("NOTIFICATION FROM: {notification_from}, RECEIVED MESSAGE")),
+                ),
+                _ => {},
+            }
+        } else if let Some(event) = event.downcast_ref::<WebEvent>() {
+            match event {
+                WebEvent::FileServed { notification_from, file } => self.record(
+                    vec![*notification_from],
+                    MomentKind::FileSent,
+                    format!("{notification_from} served {file}"),
+                    Some(format!("NOTIFICATION FROM: {notification_from}, FILE SERVED")),
+                ),
+                WebEvent::File { notification_from, file } => self.record(
+                    vec![*notification_from],
+                    MomentKind::FileReceived,
+                    format!("{notification_from} received file {}", file.text_file.title),
+                    Some(format!("NOTIFICATION FROM: {notification_from}, FILE RECEIVED")),
+                ),
+                WebEvent::TextFile { notification_from, file } => self.record(
+                    vec![*notification_from],
+                    MomentKind::FileReceived,
+                    format!("{notification_from} received text file {}", file.title),
+                    Some(format!("NOTIFICATION FROM: {notification_from}, TEXT FILE RECEIVED")),
+                ),
+                WebEvent::MediaFile { notification_from, file } => self.record(
+                    vec![*notification_from],
+                    MomentKind::FileReceived,
+                    format!("{notification_from} received media file {}", file.title),
+                    Some(format!("NOTIFICATION FROM: {notification_from}, MEDIA FILE RECEIVED")),
+                ),
+                _ => {},
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Row of the log line written for `moment`: the closest line before it with its needle,
+    /// else the last line logged before it.
+    pub fn log_row(moment: &Moment, logs: &VecModel<LogMessage>) -> Option<usize> {
+        let before = (0..logs.row_count()).rev()
+            .filter_map(|row| Some((row, logs.row_data(row)?)))
+            .skip_while(|(_, line)| line.id > moment.log_id)
+            .take(LOOKBACK)
+            .collect::<Vec<_>>();
+
+        let needle = moment.needle.as_deref();
+        before.iter().find(|(_, line)| needle.is_some_and(|n| line.message.contains(n)))
+            .or(before.first())
+            .map(|(row, _)| *row)
+    }
+}
+
+/// Feeds the timeline from the events and commands, registered with the other built-in observers.
+pub struct TimelineObserver;
+
+impl EventObserver for TimelineObserver {
+    fn on_node_event(&mut self, event: &dyn Any, ctx: &EventContext) -> bool {
+        ctx.timeline.lock().unwrap().on_node_event(event)
+    }
+
+    fn on_command(&mut self, node: NodeId, command: &dyn Any, ctx: &EventContext) {
+        ctx.timeline.lock().unwrap().on_command(node, command, ctx.dead.contains(&node));
+    }
+}
+
+/// What the timeline panel shows, lives on the UI thread.
+#[derive(Default)]
+struct TimelineView {
+    lanes: Vec<NodeId>,
+    marks: Rc<VecModel<TimelineMark>>,
+    /// Id of the first moment not drawn yet.
+    next: u64,
+}
+
+thread_local! {
+    static VIEW: RefCell<Option<TimelineView>> = RefCell::new(None);
+}
+
+fn mark(moment: &Moment, lanes: &[NodeId]) -> Option<TimelineMark> {
+    Some(TimelineMark {
+        id: moment.id as i32,
+        lane: lanes.binary_search(moment.nodes.first()?).ok()? as i32,
+        at: moment.at.as_secs_f32(),
+        label: moment.label.clone().into(),
+        color: moment.kind.color(),
+    })
+}
+
+/// Brings the timeline panel up to date while it is open: evicted marks go, new ones are appended,
+/// everything is redrawn only when a lane appears or disappears.
+pub fn refresh(main_window: &MainWindow, timeline: &mut Timeline) {
+    if !timeline.changed || !main_window.get_timeline_visible() {
+        return;
+    }
+    timeline.changed = false;
+
+    VIEW.with(|view| {
+        let mut view = view.borrow_mut();
+        let view = view.get_or_insert_with(|| {
+            let view = TimelineView::default();
+            main_window.set_timeline_marks(view.marks.clone().into());
+            view
+        });
+
+        let lanes = timeline.lanes();
+        if lanes != view.lanes {
+            view.marks.set_vec(timeline.moments().iter().filter_map(|m| mark(m, &lanes)).collect::<Vec<_>>());
+            main_window.set_timeline_lanes(Rc::new(VecModel::from(lanes.iter().map(|id| TimelineLane { label: id.to_string().into() }).collect::<Vec<_>>())).into());
+            view.lanes = lanes;
+        } else {
+            let first = timeline.moments().front().map_or(timeline.next_id, |m| m.id);
+            while view.marks.row_data(0).is_some_and(|m| (m.id as u64) < first) {
+                view.marks.remove(0);
+            }
+            for moment in timeline.moments().iter().skip(view.next.saturating_sub(first) as usize) {
+                if let Some(mark) = mark(moment, &view.lanes) {
+                    view.marks.push(mark);
+                }
+            }
+        }
+        view.next = timeline.next_id;
+    });
+
+    main_window.set_timeline_end(timeline.moments().back().map(|m| m.at.as_secs_f32()).unwrap_or(0.));
+}
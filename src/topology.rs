@@ -0,0 +1,243 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use wg_internal::network::NodeId;
+use wg_internal::packet::NodeType as WGNodeType;
+
+use crate::faults::LinkConfig;
+use crate::seeding::SeedConfig;
+use crate::SimulationController;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroneConfig {
+    pub id: NodeId,
+    pub connected_node_ids: Vec<NodeId>,
+    pub pdr: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientConfig {
+    pub id: NodeId,
+    pub connected_drone_ids: Vec<NodeId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    pub id: NodeId,
+    pub connected_drone_ids: Vec<NodeId>,
+}
+
+/// In-memory form of a network initialization file.
+///
+/// Mirrors the `[[drone]]` / `[[client]]` / `[[server]]` tables used by the files in `config/`,
+/// so anything written from here can be loaded again by `NetworkInitializer`.
+/// `[[seed]]` and `[[link]]` tables are only read by the controller, see `seeding::SeedConfig` and `faults::LinkConfig`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TopologyConfig {
+    #[serde(default)]
+    pub drone: Vec<DroneConfig>,
+    #[serde(default)]
+    pub client: Vec<ClientConfig>,
+    #[serde(default)]
+    pub server: Vec<ServerConfig>,
+    #[serde(default)]
+    pub seed: Vec<SeedConfig>,
+    #[serde(default)]
+    pub link: Vec<LinkConfig>,
+}
+
+impl TopologyConfig {
+
+    /// Builds a config from the live `network_view` of the simulation controller.
+    ///
+    /// Crashed or shut down nodes are not part of the view anymore, so they are left out,
+    /// as are crashed clients and servers the view still shows.
+    /// Drones keep the packet drop rate currently stored in `sc.drones`.
+    pub fn from_simulation_controller(sc: &SimulationController) -> Self {
+        let mut config = TopologyConfig::default();
+
+        for node in sc.network_view.nodes.iter().filter(|n| !sc.dead.contains_key(&n.get_id())) {
+            let id = node.get_id();
+            let adjacents: BTreeSet<NodeId> = node.get_adjacents().iter().copied().filter(|id| !sc.dead.contains_key(id)).collect();
+
+            match node.get_node_type() {
+                WGNodeType::Drone => {
+                    let pdr = sc.drones.get(&id).map(|(pdr, _)| *pdr).unwrap_or(0.);
+                    config.drone.push(DroneConfig { id, connected_node_ids: adjacents.into_iter().collect(), pdr });
+                }
+                WGNodeType::Client => {
+                    config.client.push(ClientConfig { id, connected_drone_ids: adjacents.into_iter().collect() });
+                }
+                WGNodeType::Server => {
+                    config.server.push(ServerConfig { id, connected_drone_ids: adjacents.into_iter().collect() });
+                }
+            }
+        }
+
+        config.link = sc.link_faults().into_iter()
+            .map(|((a, b), fault)| LinkConfig { between: [a, b], fault })
+            .collect();
+        config.sort();
+        config
+    }
+
+    pub fn sort(&mut self) {
+        self.drone.sort_by_key(|d| d.id);
+        self.client.sort_by_key(|c| c.id);
+        self.server.sort_by_key(|s| s.id);
+        self.link.sort_by_key(|l| l.between);
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let content = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("cannot read {}: {e}", path.as_ref().display()))?;
+        Self::from_toml(&content)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| e.to_string())
+    }
+
+    /// Serializes the config with one blank line between tables, like the hand-written files in `config/`.
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+
+        for drone in &self.drone {
+            out.push_str(&format!(
+                "[[drone]]\nid = {}\nconnected_node_ids = {}\npdr = {}\n\n",
+                drone.id,
+                format_ids(&drone.connected_node_ids),
+                format_pdr(drone.pdr)
+            ));
+        }
+        for client in &self.client {
+            out.push_str(&format!(
+                "[[client]]\nid = {}\nconnected_drone_ids = {}\n\n",
+                client.id,
+                format_ids(&client.connected_drone_ids)
+            ));
+        }
+        for server in &self.server {
+            out.push_str(&format!(
+                "[[server]]\nid = {}\nconnected_drone_ids = {}\n\n",
+                server.id,
+                format_ids(&server.connected_drone_ids)
+            ));
+        }
+        for seed in &self.seed {
+            out.push_str(&format!("[[seed]]\nserver = {}\ndir = {}\n", seed.server, format_str(&seed.dir)));
+            if seed.recursive {
+                out.push_str("recursive = true\n");
+            }
+            if !seed.include.is_empty() {
+                out.push_str(&format!("include = {}\n", format_strs(&seed.include)));
+            }
+            if !seed.exclude.is_empty() {
+                out.push_str(&format!("exclude = {}\n", format_strs(&seed.exclude)));
+            }
+            if let Some(media_server) = seed.media_server {
+                out.push_str(&format!("media_server = {media_server}\n"));
+            }
+            if let Some(manifest) = &seed.manifest {
+                out.push_str(&format!("manifest = {}\n", format_str(manifest)));
+            }
+            out.push('\n');
+        }
+        for link in &self.link {
+            let fault = &link.fault;
+            out.push_str(&format!("[[link]]\nbetween = {}\n", format_ids(&link.between)));
+            for (name, value) in [("latency_ms", fault.latency_ms), ("jitter_ms", fault.jitter_ms), ("cut_ms", fault.cut_ms)] {
+                if value > 0 {
+                    out.push_str(&format!("{name} = {value}\n"));
+                }
+            }
+            for (name, value) in [("reorder", fault.reorder), ("duplicate", fault.duplicate)] {
+                if value > 0. {
+                    out.push_str(&format!("{name} = {}\n", format_pdr(value)));
+                }
+            }
+            out.push('\n');
+        }
+
+        out.trim_end().to_string()
+    }
+
+    /// Writes the config to `path`, creating the parent directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        fs::write(path, self.to_toml())
+    }
+
+    /// Same shape as `utils::generate_generic_network_view`, built from the declared links.
+    ///
+    /// Links are added from both ends, so a link declared only once still shows up as an edge.
+    /// Links to unknown ids and self-loops are skipped, the linter reports them.
+    pub fn to_generic_graph(&self) -> HashMap<(NodeId, String), HashSet<NodeId>> {
+        let kinds = self.node_kinds();
+
+        let mut graph: HashMap<(NodeId, String), HashSet<NodeId>> = HashMap::new();
+        for (id, kind) in &kinds {
+            graph.entry((*id, kind.to_string())).or_default();
+        }
+
+        for (a, b) in self.declared_links() {
+            if a == b {
+                continue;
+            }
+            let (Some(kind_a), Some(kind_b)) = (kinds.get(&a), kinds.get(&b)) else {
+                continue;
+            };
+            graph.entry((a, kind_a.to_string())).or_default().insert(b);
+            graph.entry((b, kind_b.to_string())).or_default().insert(a);
+        }
+
+        graph
+    }
+
+    /// Maps every declared id to `"drone"`, `"client"` or `"server"`.
+    pub fn node_kinds(&self) -> HashMap<NodeId, &'static str> {
+        let mut kinds = HashMap::new();
+        for d in &self.drone { kinds.insert(d.id, "drone"); }
+        for c in &self.client { kinds.insert(c.id, "client"); }
+        for s in &self.server { kinds.insert(s.id, "server"); }
+        kinds
+    }
+
+    /// Every `(declaring node, listed node)` pair, in file order.
+    pub fn declared_links(&self) -> Vec<(NodeId, NodeId)> {
+        self.drone.iter().flat_map(|d| d.connected_node_ids.iter().map(move |n| (d.id, *n)))
+            .chain(self.client.iter().flat_map(|c| c.connected_drone_ids.iter().map(move |n| (c.id, *n))))
+            .chain(self.server.iter().flat_map(|s| s.connected_drone_ids.iter().map(move |n| (s.id, *n))))
+            .collect()
+    }
+}
+
+fn format_ids(ids: &[NodeId]) -> String {
+    format!("[{}]", ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", "))
+}
+
+/// `value` as a TOML string literal. `{:?}` uses Rust escapes, which TOML does not always read back.
+pub fn format_str(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+fn format_strs(values: &[String]) -> String {
+    format!("[{}]", values.iter().map(|v| format_str(v)).collect::<Vec<_>>().join(", "))
+}
+
+fn format_pdr(pdr: f32) -> String {
+    // `{}` prints `1.0_f32` as `1`, which TOML would read back as an integer
+    let formatted = format!("{}", pdr);
+    if formatted.contains('.') { formatted } else { format!("{formatted}.0") }
+}
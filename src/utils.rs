@@ -1,12 +1,13 @@
 use std::rc::Rc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::fs::File;
 use std::io::Write;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use once_cell::sync::OnceCell;
 use chrono::{Datelike, Local, Timelike};
-use slint::{Color, Image, SharedString, VecModel, Weak};
+use slint::{Color, ComponentHandle, Image, SharedString, VecModel, Weak};
 use wg_internal::network::NodeId;
 use common::types::Message;
 use crate::{Client, Drone, Server, SimulationController};
@@ -20,61 +21,78 @@ use common::types::{Command, NodeType, NodeCommand};
 use wg_internal::{controller::DroneCommand};
 use wg_internal::packet::NodeType as WGNodeType;
 static LOGGER: OnceCell<Box<dyn Fn(LogMessage) + Send + Sync + 'static>> = OnceCell::new();
+static SUBSCRIBERS: Mutex<Vec<crossbeam::channel::Sender<LogMessage>>> = Mutex::new(Vec::new());
+static LAST_LOG_ID: AtomicI32 = AtomicI32::new(0);
 
 pub fn set_logger(cb: Box<dyn Fn(LogMessage) + Send + Sync + 'static>) {
     let _ = LOGGER.set(cb);
 }
 
+/// Every line logged from now on, until the receiver is dropped.
+pub fn subscribe_logs() -> crossbeam::channel::Receiver<LogMessage> {
+    let (sender, receiver) = crossbeam::channel::unbounded();
+    SUBSCRIBERS.lock().unwrap().push(sender);
+    receiver
+}
+
 pub fn log<S: Into<SharedString>>(msg: S, color: Color) {
-    if let Some(cb) = LOGGER.get() {
-        let now = chrono::Local::now();
-        let formatted = format!(
-            "[{}/{}/{} {:02}:{:02}:{:02}] {}",
-            now.day(),
-            now.month(),
-            now.year(),
-            now.hour(),
-            now.minute(),
-            now.second(),
-            msg.into()
-        );
-
-        cb(LogMessage {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    if LOGGER.get().is_some() || !subscribers.is_empty() {
+        let formatted = format!("{} {}", timestamp(&chrono::Local::now()), msg.into());
+
+        let entry = LogMessage {
             message: formatted.into(),
             color,
-        });
+            id: LAST_LOG_ID.fetch_add(1, Ordering::Relaxed) + 1,
+        };
+        subscribers.retain(|subscriber| subscriber.send(entry.clone()).is_ok());
+        drop(subscribers);
+
+        if let Some(cb) = LOGGER.get() {
+            cb(entry);
+        }
     }
 }
 
+/// Id of the last line logged, 0 before the first one.
+pub fn last_log_id() -> i32 {
+    LAST_LOG_ID.load(Ordering::Relaxed)
+}
+
+/// The `[day/month/year hh:mm:ss]` prefix of log lines.
+pub fn timestamp(now: &chrono::DateTime<chrono::Local>) -> String {
+    format!("[{}/{}/{} {:02}:{:02}:{:02}]", now.day(), now.month(), now.year(), now.hour(), now.minute(), now.second())
+}
+
 pub fn log_default<S: Into<SharedString>>(msg: S) {
     log(msg, Color::from_rgb_u8(255, 255, 255));
 }
 
-/// Saves chat history into `chats_history_{notification_from}`.
+/// Saves chat history into `dir_path`.
 ///
 /// For each pair of clients, creates a file `clients_{client1}_{client2}.txt`
-/// containing all messages exchanged.
+/// containing all messages exchanged, and returns the paths of the files written.
 ///
 /// # Errors
 ///
 /// Returns an error if the directory cannot be created or any file cannot be created or written to.
-pub fn save_chat_history(notification_from: &u8, history: &HashMap<NodeId, Vec<Message>>) -> std::io::Result<()> {
-    let dir_name = format!("chats_history_{notification_from}");
-    let dir_path = Path::new(&dir_name);
+pub fn save_chat_history(dir_path: &Path, notification_from: &u8, history: &HashMap<NodeId, Vec<Message>>) -> std::io::Result<Vec<PathBuf>> {
     fs::create_dir_all(dir_path)?;
 
+    let mut saved = Vec::new();
     for (other_client, messages) in history {
         if other_client == notification_from {
             continue;
         }
         let file_name = format!("clients_{}_{}.txt", notification_from, other_client);
         let file_path = dir_path.join(file_name);
-        let mut f = File::create(file_path)?;
+        let mut f = File::create(&file_path)?;
         for message in messages {
             writeln!(f, "From {} to {}: {}", message.from, message.to, message.text)?;
         }
+        saved.push(file_path);
     }
-    Ok(())
+    Ok(saved)
 }
 
 pub fn generate_generic_network_view(
@@ -110,7 +128,13 @@ pub fn generate_generic_network_view(
             } else if servers.contains_key(&node2_id) {
                 "server"
             } else {
-                "unknown"
+                // crashed clients and servers are only left in the view
+                match network.nodes.iter().find(|n| n.get_id() == node2_id).map(|n| n.get_node_type()) {
+                    Some(WGNodeType::Client) => "client",
+                    Some(WGNodeType::Server) => "server",
+                    Some(WGNodeType::Drone) => "drone",
+                    None => "unknown",
+                }
             };
 
             graph.entry((node2_id, node2_type.to_string()))
@@ -214,14 +238,50 @@ pub fn draw_menu(main_window: &MainWindow, sc: &SimulationController) {
 
     main_window.set_drones(drones.clone().into());
 
-    // Clients & Servers
+    // Clients & Servers, crashed ones stay listed until they are removed
     let (clients, servers) = sc.get_nodes_with_type();
+    let mut clients = clients.into_iter().map(|(id, node_type)| (id, node_type.clone(), node_type)).collect::<Vec<_>>();
+    let mut servers = servers.into_iter().map(|(id, node_type)| (id, node_type.clone(), node_type)).collect::<Vec<_>>();
+    for node in &sc.network_view.nodes {
+        if let Some(node_type) = sc.dead.get(&node.get_id()) {
+            let entry = (node.get_id(), format!("Crashed {node_type}"), "Crashed".to_string());
+            match node.get_node_type() {
+                WGNodeType::Client => clients.push(entry),
+                _ => servers.push(entry),
+            }
+        }
+    }
+    clients.sort_by_key(|(id, _, _)| *id);
+    servers.sort_by_key(|(id, _, _)| *id);
 
     // Clients    
-    let clients = Rc::new(VecModel::from(clients.iter().map(|(node_id, node_type)| Client { title: format!("Client {node_id}").into(), subtitle: node_type.into(), id: node_id.to_string().into(), kind: node_type.into() }).collect::<Vec<_>>()));
+    let clients = Rc::new(VecModel::from(clients.iter().map(|(node_id, subtitle, kind)| Client { title: format!("Client {node_id}").into(), subtitle: subtitle.into(), id: node_id.to_string().into(), kind: kind.into() }).collect::<Vec<_>>()));
     main_window.set_clients(clients.clone().into());
 
     // Servers
-    let servers = Rc::new(VecModel::from(servers.iter().map(|(node_id, node_type)| Server { title: format!("Server {node_id}").into(), subtitle: node_type.into(), id: node_id.to_string().into(), kind: node_type.into() }).collect::<Vec<_>>()));
+    let servers = Rc::new(VecModel::from(servers.iter().map(|(node_id, subtitle, kind)| Server { title: format!("Server {node_id}").into(), subtitle: subtitle.into(), id: node_id.to_string().into(), kind: kind.into() }).collect::<Vec<_>>()));
     main_window.set_servers(servers.clone().into());
-}
\ No newline at end of file
+}
+/// Stops the running simulation, if any, and starts a new one from the config at `path`.
+///
+/// The graph and the node columns are redrawn from the new network.
+pub fn load_simulation(path: &str, sc: &mut SimulationController, main_window: &MainWindow) {
+    sc.stop_simulation();
+    let started = sc.start_simulation(path, main_window.as_weak());
+
+    draw_menu(main_window, sc);
+
+    crate::graph_utils::generate_graph(
+        main_window,
+        &sc.network_view,
+        &sc.clients,
+        &sc.servers,
+        &sc.drones,
+    );
+    crate::graph_utils::mark_faulty(sc.link_faults().keys().copied().collect());
+
+    match started {
+        Ok(()) => log(format!("Simulation started from {path}"), Color::from_rgb_u8(123, 132, 150)),
+        Err(e) => log(e, Color::from_rgb_u8(255, 94, 160)),
+    }
+}
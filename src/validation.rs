@@ -6,6 +6,8 @@ use common::types::{Command, NodeType, NodeCommand};
 use wg_internal::{controller::DroneCommand, network::NodeId};
 use wg_internal::packet::NodeType as WGNodeType;
 
+use crate::topology::TopologyConfig;
+
 
 pub fn can_remove_drone(network_graph: &HashMap<(NodeId, String), HashSet<NodeId>>, drone_id: NodeId, servers: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>) -> bool {
 
@@ -25,40 +27,51 @@ pub fn can_remove_drone(network_graph: &HashMap<(NodeId, String), HashSet<NodeId
             return false; // This server would have less than two drones
         }}
     }
-    true
-
-}
 
-pub fn can_remove_sender_drone(network_graph: &HashMap<(NodeId, String), HashSet<NodeId>>, drone_id: NodeId, sender_id: NodeId, servers: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>) -> bool {
-    if let Some((_, sender_type)) = network_graph.keys().find(|(id, _)| id == &sender_id) {
-        if sender_type != "server" {
-            return true; // if the sender is not a server, we can remove it
+    // clients keep at least one drone
+    for ((id, kind), adjacent) in network_graph {
+        if kind == "client" && adjacent.contains(&drone_id) && adjacent.iter().all(|adj_id| *adj_id == drone_id) {
+            return false;
         }
-    } else {
-        return true; // if the sender is not found, we can remove it
     }
 
-    // check if it has at least two drones after removing the drone id if present
-    if let Some(adjacent) = network_graph.get(&(sender_id, "server".to_string())) {
-        let drone_count = adjacent.iter().filter(|&&adj_id| {
-            // the adjacent node should only be drones so i won't check adj_type == "drone"
-            if let Some((_, adj_type)) = network_graph.keys().find(|(id, _)| id == &adj_id) {
-                adj_id != drone_id
+    connected_without(network_graph, |a, b| a == drone_id || b == drone_id, Some(drone_id))
+}
+
+pub fn can_remove_sender_drone(network_graph: &HashMap<(NodeId, String), HashSet<NodeId>>, drone_id: NodeId, sender_id: NodeId, servers: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>) -> bool {
+    let allowed = match network_graph.keys().find(|(id, _)| id == &sender_id) {
+        Some((_, sender_type)) if sender_type == "server" => {
+            // check if it has at least two drones after removing the drone id if present
+            if let Some(adjacent) = network_graph.get(&(sender_id, "server".to_string())) {
+                let drone_count = adjacent.iter().filter(|&&adj_id| {
+                    // the adjacent node should only be drones so i won't check adj_type == "drone"
+                    if let Some((_, adj_type)) = network_graph.keys().find(|(id, _)| id == &adj_id) {
+                        adj_id != drone_id
+                    } else {
+                        false
+                    }
+                }).count();
+                drone_count >= 2
             } else {
                 false
             }
-        }).count();
-        drone_count >= 2
-    } else {
-        false
-    }
+        }
+        // a client keeps at least one drone
+        Some((_, sender_type)) if sender_type == "client" => {
+            network_graph.get(&(sender_id, "client".to_string())).is_some_and(|adjacent| adjacent.iter().any(|id| *id != drone_id))
+        }
+        _ => true, // if the sender is another drone or is not found, only connectivity matters
+    };
+
+    allowed && connected_without(network_graph, |a, b| (a == drone_id && b == sender_id) || (a == sender_id && b == drone_id), None)
 }
 
 // when the client wants to elminate a drone
 pub fn can_remove_sender_client(network_graph: &HashMap<(NodeId, String), HashSet<NodeId>>, client_id: NodeId, sender_id: NodeId, clients: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>) -> bool {
     if let Some(h) = network_graph.get(&(client_id, "client".to_string())) {
         if h.contains(&sender_id) {
-            return h.len() > 1; // if the sender(drone) is in the client, we can remove it only if there are more than 1 drones
+            // if the sender(drone) is in the client, we can remove it only if there are more than 1 drones
+            return h.len() > 1 && connected_without(network_graph, |a, b| (a == client_id && b == sender_id) || (a == sender_id && b == client_id), None);
         }
         return false; // if the sender is not in the client, we cannot remove it
     }
@@ -69,15 +82,320 @@ pub fn can_remove_sender_client(network_graph: &HashMap<(NodeId, String), HashSe
 pub fn can_remove_sender_server(network_graph: &HashMap<(NodeId, String), HashSet<NodeId>>, server_id: NodeId, sender_id: NodeId, servers: &HashMap<NodeId, (NodeType, Sender<Box<dyn Command>>)>) -> bool {
     if let Some(h) = network_graph.get(&(server_id, "server".to_string())) {
         if h.contains(&sender_id) {
-            return h.len() > 2; // if the sender(drone) is in the server, we can remove it only if there are more than 2 drones
+            // if the sender(drone) is in the server, we can remove it only if there are more than 2 drones
+            return h.len() > 2 && connected_without(network_graph, |a, b| (a == server_id && b == sender_id) || (a == sender_id && b == server_id), None);
         }
         return false; // if the sender is not in the server, we cannot remove it
     }
     false // cannot find the server
 }
+
+/// Whether `network_graph` stays connected once the links matching `removed` and the node `gone` are taken out.
+fn connected_without(network_graph: &HashMap<(NodeId, String), HashSet<NodeId>>, removed: impl Fn(NodeId, NodeId) -> bool, gone: Option<NodeId>) -> bool {
+    let remaining = network_graph.iter()
+        .filter(|((id, _), _)| Some(*id) != gone)
+        .map(|(key, adjacent)| (key.clone(), adjacent.iter().copied().filter(|adj_id| !removed(key.0, *adj_id)).collect()))
+        .collect();
+    is_connected(&remaining)
+}
+
 pub fn is_a_drone(network_graph: &HashMap<(NodeId, String), HashSet<NodeId>>, server_id: NodeId,node_id:NodeId ) -> bool {
     if let Some(h) = network_graph.get(&(node_id, "drone".to_string())) {
         return true; // if the node to be added is a drone, we can add it
     }
     false // that id does not correspond to a drone
 }
+
+/// Pre-start validation of a whole network, checked before a simulation is started from a file.
+///
+/// The rules are the ones the protocol relies on: clients are attached to one or two drones,
+/// servers to at least two, clients and servers only talk to drones, PDRs are probabilities
+/// and every node can be reached through the drones.
+pub fn validate_topology(config: &TopologyConfig) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    let kinds = config.node_kinds();
+
+    let declared = config.drone.len() + config.client.len() + config.server.len();
+    if kinds.len() != declared {
+        errors.push("some node ids are declared more than once".to_string());
+    }
+
+    for drone in &config.drone {
+        if !(0.0..=1.0).contains(&drone.pdr) {
+            errors.push(format!("drone {} has pdr {} outside of [0, 1]", drone.id, drone.pdr));
+        }
+    }
+
+    let graph = config.to_generic_graph();
+
+    for client in &config.client {
+        let adjacent = graph.get(&(client.id, "client".to_string())).cloned().unwrap_or_default();
+        if adjacent.is_empty() || adjacent.len() > 2 {
+            errors.push(format!("client {} must be connected to one or two drones, found {}", client.id, adjacent.len()));
+        }
+        for id in adjacent.iter().filter(|id| kinds.get(id) != Some(&"drone")) {
+            errors.push(format!("client {} is connected to {id}, which is not a drone", client.id));
+        }
+    }
+
+    for server in &config.server {
+        let adjacent = graph.get(&(server.id, "server".to_string())).cloned().unwrap_or_default();
+        if adjacent.len() < 2 {
+            errors.push(format!("server {} must be connected to at least two drones, found {}", server.id, adjacent.len()));
+        }
+        for id in adjacent.iter().filter(|id| kinds.get(id) != Some(&"drone")) {
+            errors.push(format!("server {} is connected to {id}, which is not a drone", server.id));
+        }
+    }
+
+    if !is_connected(&graph) {
+        errors.push("the network is not connected through the drones".to_string());
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+/// Checks that every node can be reached from a drone, only passing through drones.
+pub fn is_connected(network_graph: &HashMap<(NodeId, String), HashSet<NodeId>>) -> bool {
+    let kinds: HashMap<NodeId, &str> = network_graph.keys().map(|(id, kind)| (*id, kind.as_str())).collect();
+
+    let Some(start) = kinds.iter().find(|(_, kind)| **kind == "drone").map(|(id, _)| *id) else {
+        return kinds.is_empty();
+    };
+
+    let mut seen = HashSet::from([start]);
+    let mut stack = vec![start];
+
+    while let Some(id) = stack.pop() {
+        if kinds.get(&id) != Some(&"drone") {
+            continue; // clients and servers do not forward packets
+        }
+        if let Some(adjacent) = network_graph.get(&(id, "drone".to_string())) {
+            for next in adjacent {
+                if kinds.contains_key(next) && seen.insert(*next) {
+                    stack.push(*next);
+                }
+            }
+        }
+    }
+
+    seen.len() == kinds.len()
+}
+
+//? CONFIG LINTER
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One finding of the config linter, printed as `file:line: severity: message`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+    /// Set for one-sided links, which `fix_symmetric_links` can repair.
+    pub fixable: bool,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}:{}: {}: {}", self.file, self.line, severity, self.message)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct LintConfig {
+    #[serde(default)]
+    drone: Vec<LintNode>,
+    #[serde(default)]
+    client: Vec<LintNode>,
+    #[serde(default)]
+    server: Vec<LintNode>,
+}
+
+#[derive(serde::Deserialize)]
+struct LintNode {
+    id: toml::Spanned<NodeId>,
+    #[serde(default, alias = "connected_drone_ids")]
+    connected_node_ids: Vec<toml::Spanned<NodeId>>,
+}
+
+/// Lints the network file at `path`, see `lint_str`.
+pub fn lint_file(path: &str) -> Result<Vec<Diagnostic>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+    lint_str(path, &content)
+}
+
+/// Checks the links declared in a network file.
+///
+/// Drones declare `connected_node_ids` and clients/servers declare `connected_drone_ids`, so every
+/// link is written twice and the two ends can disagree. Reports one-sided links, unknown ids,
+/// self-loops, duplicate ids and entries, clients or servers linked to something that is not a drone
+/// and servers declaring fewer than two drones.
+pub fn lint_str(file: &str, content: &str) -> Result<Vec<Diagnostic>, String> {
+    let config: LintConfig = toml::from_str(content).map_err(|e| format!("{file}: {e}"))?;
+
+    let line_of = |offset: usize| content[..offset.min(content.len())].matches('\n').count() + 1;
+    let mut diagnostics = Vec::new();
+    let mut push = |line: usize, severity: Severity, message: String, fixable: bool| {
+        diagnostics.push(Diagnostic { file: file.to_string(), line, severity, message, fixable });
+    };
+
+    let nodes: Vec<(&str, &LintNode)> = config.drone.iter().map(|n| ("drone", n))
+        .chain(config.client.iter().map(|n| ("client", n)))
+        .chain(config.server.iter().map(|n| ("server", n)))
+        .collect();
+
+    let mut declared: HashMap<NodeId, (&str, &LintNode)> = HashMap::new();
+    for (kind, node) in &nodes {
+        let id = *node.id.get_ref();
+        if let Some((other_kind, other)) = declared.get(&id) {
+            push(line_of(node.id.span().start), Severity::Error,
+                format!("{kind} {id} is already declared as {other_kind} at line {}", line_of(other.id.span().start)), false);
+            continue;
+        }
+        declared.insert(id, (kind, node));
+    }
+
+    for (kind, node) in &nodes {
+        let id = *node.id.get_ref();
+        let mut seen = HashSet::new();
+
+        for entry in &node.connected_node_ids {
+            let other = *entry.get_ref();
+            let line = line_of(entry.span().start);
+
+            if other == id {
+                push(line, Severity::Error, format!("{kind} {id} is linked to itself"), false);
+                continue;
+            }
+            if !seen.insert(other) {
+                push(line, Severity::Warning, format!("{kind} {id} lists {other} more than once"), false);
+                continue;
+            }
+            let Some((other_kind, other_node)) = declared.get(&other) else {
+                push(line, Severity::Error, format!("{kind} {id} lists {other}, which is not declared"), false);
+                continue;
+            };
+            if *kind != "drone" && *other_kind != "drone" {
+                push(line, Severity::Error, format!("{kind} {id} is linked to {other_kind} {other}, clients and servers can only be linked to drones"), false);
+                continue;
+            }
+            if !other_node.connected_node_ids.iter().any(|e| *e.get_ref() == id) {
+                push(line, Severity::Error, format!("{kind} {id} lists {other}, but {other_kind} {other} does not list {id}"), true);
+            }
+        }
+
+        if *kind == "server" {
+            let drones = seen.iter().filter(|other| matches!(declared.get(other), Some(("drone", _)))).count();
+            if drones < 2 {
+                push(line_of(node.id.span().start), Severity::Error, format!("server {id} is linked to {drones} drone(s), at least two are needed"), false);
+            }
+        }
+        if *kind == "client" && seen.is_empty() {
+            push(line_of(node.id.span().start), Severity::Error, format!("client {id} is not linked to any drone"), false);
+        }
+    }
+
+    diagnostics.sort_by_key(|d| d.line);
+    Ok(diagnostics)
+}
+
+/// Adds the missing end of every one-sided link and drops duplicate entries.
+///
+/// Links to unknown ids, self-loops and links between two non-drones are left alone,
+/// they need a human decision. Returns the fixed config without writing it.
+pub fn fix_symmetric_links(config: &TopologyConfig) -> TopologyConfig {
+    let mut fixed = config.clone();
+    let kinds = config.node_kinds();
+
+    for (a, b) in config.declared_links() {
+        let (Some(kind_a), Some(kind_b)) = (kinds.get(&a), kinds.get(&b)) else {
+            continue;
+        };
+        if a == b || (*kind_a != "drone" && *kind_b != "drone") {
+            continue;
+        }
+        let neighbours = match *kind_b {
+            "drone" => fixed.drone.iter_mut().find(|d| d.id == b).map(|d| &mut d.connected_node_ids),
+            "client" => fixed.client.iter_mut().find(|c| c.id == b).map(|c| &mut c.connected_drone_ids),
+            _ => fixed.server.iter_mut().find(|s| s.id == b).map(|s| &mut s.connected_drone_ids),
+        };
+        if let Some(neighbours) = neighbours {
+            if !neighbours.contains(&a) {
+                neighbours.push(a);
+            }
+        }
+    }
+
+    let dedup = |ids: &mut Vec<NodeId>| {
+        let mut seen = HashSet::new();
+        ids.retain(|id| seen.insert(*id));
+    };
+    fixed.drone.iter_mut().for_each(|d| dedup(&mut d.connected_node_ids));
+    fixed.client.iter_mut().for_each(|c| dedup(&mut c.connected_drone_ids));
+    fixed.server.iter_mut().for_each(|s| dedup(&mut s.connected_drone_ids));
+
+    fixed
+}
+
+/// `fix_symmetric_links` on the text of a network file.
+///
+/// Only the link arrays that change are edited, in place: comments, ordering and
+/// the formatting of everything else stay as they were.
+pub fn fix_symmetric_links_in(content: &str) -> Result<String, String> {
+    let fixed = fix_symmetric_links(&TopologyConfig::from_toml(content)?);
+    let mut document = content.parse::<toml_edit::DocumentMut>().map_err(|e| e.to_string())?;
+
+    for (kind, key) in [("drone", "connected_node_ids"), ("client", "connected_drone_ids"), ("server", "connected_drone_ids")] {
+        let Some(tables) = document.get_mut(kind).and_then(|item| item.as_array_of_tables_mut()) else {
+            continue;
+        };
+        for table in tables.iter_mut() {
+            let Some(id) = table.get("id").and_then(|id| id.as_integer()) else {
+                continue;
+            };
+            let wanted = match kind {
+                "drone" => fixed.drone.iter().find(|d| d.id as i64 == id).map(|d| &d.connected_node_ids),
+                "client" => fixed.client.iter().find(|c| c.id as i64 == id).map(|c| &c.connected_drone_ids),
+                _ => fixed.server.iter().find(|s| s.id as i64 == id).map(|s| &s.connected_drone_ids),
+            };
+            let Some(wanted) = wanted else {
+                continue;
+            };
+
+            let Some(array) = table.get_mut(key).and_then(|item| item.as_array_mut()) else {
+                if !wanted.is_empty() {
+                    table.insert(key, toml_edit::value(wanted.iter().map(|id| *id as i64).collect::<toml_edit::Array>()));
+                }
+                continue;
+            };
+
+            // duplicates go, the entries left keep their layout and the missing ones are appended
+            let mut seen = HashSet::new();
+            let mut i = 0;
+            while i < array.len() {
+                if array.get(i).and_then(|v| v.as_integer()).map_or(true, |id| seen.insert(id)) {
+                    i += 1;
+                } else {
+                    array.remove(i);
+                }
+            }
+            for id in wanted.iter().map(|id| *id as i64) {
+                if seen.insert(id) {
+                    array.push(id);
+                }
+            }
+        }
+    }
+
+    Ok(document.to_string())
+}